@@ -0,0 +1,172 @@
+use crate::{
+    chunk::{Block, BlockEntity, BlockStates, Chunk, AIR},
+    entity::{Entity, MapColors},
+    world::Dimension,
+};
+use glam::{IVec2, IVec3};
+use indexmap::IndexSet;
+use internment::Intern;
+use std::{borrow::Cow, collections::HashMap};
+
+lazy_static::lazy_static! {
+    static ref NO_MAPS: HashMap<i32, MapColors> = HashMap::new();
+}
+
+/// Anything that blocks can be read from, such as a world, a schematic or
+/// a volume built in code. Only `block_at` is required, and the rest
+/// defaults to an overworld with nothing but blocks in full daylight.
+///
+/// Any function from positions to blocks is a block source too, which is
+/// the simplest way to generate blocks procedurally.
+pub trait BlockSource {
+    /// The block at a position, or `None` if there is nothing loaded there.
+    fn block_at(&self, pos: IVec3) -> Option<Intern<Block>>;
+
+    /// All the blocks in the 16x16x16 section at a section position, or
+    /// `None` if none of it is loaded. Sources that store blocks in
+    /// sections should return them directly instead of reading each block.
+    fn section_at(&self, pos: IVec3) -> Option<Cow<'_, BlockStates>> {
+        let mut palette = IndexSet::from([*AIR]);
+        let mut data = Box::new([0; 4096]);
+        let mut loaded = false;
+        for (offset, index) in data.iter_mut().enumerate() {
+            let offset = offset as i32;
+            let block_pos = pos * 16
+                + IVec3::new(offset % 16, offset / 256, offset / 16 % 16);
+            if let Some(block) = self.block_at(block_pos) {
+                *index = palette.insert_full(block).0 as u16;
+                loaded = true;
+            }
+        }
+        loaded.then(|| {
+            Cow::Owned(BlockStates {
+                palette: palette.into_iter().collect(),
+                data,
+            })
+        })
+    }
+
+    /// The block entity at a position, such as a chest or a sign, if there is
+    /// one.
+    fn block_entity_at(&self, _pos: IVec3) -> Option<&BlockEntity> {
+        None
+    }
+
+    /// The light from light sources at a position, from 0 to 15.
+    fn block_light_at(&self, _pos: IVec3) -> u8 {
+        0
+    }
+
+    /// The light from the sky at a position, from 0 to 15.
+    fn sky_light_at(&self, _pos: IVec3) -> u8 {
+        15
+    }
+
+    /// The block light plus the sky light at a position, from 0 to 30.
+    fn light_at(&self, pos: IVec3) -> u8 {
+        self.block_light_at(pos) + self.sky_light_at(pos)
+    }
+
+    /// The name of the biome at a position, if the source has biomes.
+    fn biome_at(&self, _pos: IVec3) -> Option<Intern<Box<str>>> {
+        None
+    }
+
+    /// The highest block in a column that isn't air, and its Y coordinate.
+    fn surface_at(&self, x: i32, z: i32) -> Option<(i32, Intern<Block>)> {
+        (-64..320).rev().find_map(|y| {
+            let block = self.block_at(IVec3::new(x, y, z))?;
+            (!block.is_air()).then_some((y, block))
+        })
+    }
+
+    /// The entities in the source, such as item frames and mobs.
+    fn entities(&self) -> Box<dyn Iterator<Item = &Entity> + '_> {
+        Box::new(std::iter::empty())
+    }
+
+    /// The colors of the maps that item frames hold, by map ID.
+    fn maps(&self) -> &HashMap<i32, MapColors> {
+        &NO_MAPS
+    }
+
+    /// Which dimension the blocks are in, which decides the sky.
+    fn dimension(&self) -> Dimension {
+        Dimension::Overworld
+    }
+
+    /// The time of day in ticks, where 0 is sunrise, if the source has one.
+    fn day_time(&self) -> Option<i64> {
+        None
+    }
+
+    /// The newest version of Minecraft that saved any of the blocks, if it
+    /// is known.
+    fn data_version(&self) -> Option<i32> {
+        None
+    }
+
+    /// The seed that the world was generated from, if it is known.
+    fn seed(&self) -> Option<i64> {
+        None
+    }
+
+    /// The chunk at a chunk position, with what Minecraft keeps about it,
+    /// if the source is made of chunks and it is loaded.
+    fn chunk(&self, _pos: IVec2) -> Option<&Chunk> {
+        None
+    }
+}
+
+impl<F: Fn(IVec3) -> Option<Intern<Block>>> BlockSource for F {
+    fn block_at(&self, pos: IVec3) -> Option<Intern<Block>> {
+        self(pos)
+    }
+}
+
+/// A box of blocks kept in memory, with its first corner at the origin.
+pub struct Volume {
+    /// How many blocks the volume spans along each axis.
+    pub size: IVec3,
+    /// The blocks in the order Y, Z, X from the slowest to the fastest
+    /// changing.
+    blocks: Vec<Intern<Block>>,
+    /// Block entities by their position within the volume.
+    pub block_entities: HashMap<IVec3, BlockEntity>,
+}
+
+impl Volume {
+    /// A volume full of air.
+    pub fn new(size: IVec3) -> Self {
+        Self {
+            size,
+            blocks: vec![*AIR; (size.x * size.y * size.z) as usize],
+            block_entities: HashMap::new(),
+        }
+    }
+
+    fn index(&self, pos: IVec3) -> Option<usize> {
+        if pos.cmplt(IVec3::ZERO).any() || pos.cmpge(self.size).any() {
+            return None;
+        }
+        Some(((pos.y * self.size.z + pos.z) * self.size.x + pos.x) as usize)
+    }
+
+    /// Panics if the position is outside of the volume.
+    pub fn set_block(&mut self, pos: IVec3, block: Intern<Block>) {
+        let index = self.index(pos).unwrap_or_else(|| {
+            panic!("{pos} is outside of a volume of size {}", self.size)
+        });
+        self.blocks[index] = block;
+    }
+}
+
+impl BlockSource for Volume {
+    fn block_at(&self, pos: IVec3) -> Option<Intern<Block>> {
+        Some(self.blocks[self.index(pos)?])
+    }
+
+    fn block_entity_at(&self, pos: IVec3) -> Option<&BlockEntity> {
+        self.block_entities.get(&pos)
+    }
+}
@@ -0,0 +1,100 @@
+use glam::{Mat3, Mat4, Vec2, Vec3};
+use std::f32::consts::PI;
+
+const NEAR_PLANE: f32 = 0.05;
+
+/// The yaws of isometric views looking towards each compass direction, by
+/// the names of the directions.
+pub const ISOMETRIC_DIRECTIONS: [(&str, f32); 4] = [
+    ("ne", 0.75 * PI),
+    ("se", 0.25 * PI),
+    ("sw", -0.25 * PI),
+    ("nw", -0.75 * PI),
+];
+/// The pitches of isometric views in degrees.
+pub const ISOMETRIC_PITCHES: [f32; 2] = [30.0, 45.0];
+/// How many blocks fit in the height of an orthographic view unless chosen
+/// otherwise.
+pub const ORTHOGRAPHIC_HEIGHT: f32 = 128.0;
+
+/// How the camera maps the world onto the screen.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// Things further away look smaller, like with an eye.
+    Perspective,
+    /// Parallel lines stay parallel and things don't get smaller with
+    /// distance. The camera's position is in the middle of the view, which
+    /// reaches the view distance both in front of it and behind it.
+    Orthographic {
+        /// How many blocks fit in the height of the view.
+        height: f32,
+    },
+}
+
+/// Where the camera is and which way it looks.
+#[derive(Clone, Copy)]
+pub struct Camera {
+    /// The position of the eye in blocks.
+    pub position: Vec3,
+    /// Rotation about the Y axis in radians, where 0 looks towards +Z.
+    pub yaw: f32,
+    /// Rotation about the X axis in radians, where positive values look
+    /// down.
+    pub pitch: f32,
+    /// Vertical field of view in radians.
+    pub vfov: f32,
+    /// How the camera maps the world onto the screen.
+    pub projection: Projection,
+}
+
+impl Camera {
+    /// Turns camera space, where the camera looks towards +Z, into world
+    /// space.
+    pub fn rotation(&self) -> Mat3 {
+        Mat3::from_euler(glam::EulerRot::ZYX, 0.0, self.yaw, self.pitch)
+    }
+
+    /// The matrix that turns world positions into clip space, with depth
+    /// reaching `view_distance`.
+    pub fn view_proj(&self, aspect_ratio: f32, view_distance: f32) -> Mat4 {
+        let proj = match self.projection {
+            Projection::Perspective => Mat4::perspective_rh_gl(
+                self.vfov,
+                aspect_ratio,
+                NEAR_PLANE,
+                view_distance,
+            ),
+            Projection::Orthographic { height } => {
+                let half_size = Vec2::new(height * aspect_ratio, height) * 0.5;
+                Mat4::orthographic_rh_gl(
+                    -half_size.x,
+                    half_size.x,
+                    -half_size.y,
+                    half_size.y,
+                    -view_distance,
+                    view_distance,
+                )
+            }
+        };
+        let view =
+            Mat4::look_to_rh(self.position, self.rotation() * Vec3::Z, Vec3::Y);
+        proj * view
+    }
+
+    /// Whether parallel lines stay parallel.
+    pub fn is_orthographic(&self) -> bool {
+        matches!(self.projection, Projection::Orthographic { .. })
+    }
+}
+
+/// Narrows a projection to the rectangle of the screen from `min` to `max`
+/// in normalized device coordinates, so that the rectangle fills the
+/// viewport. Rendering every part of a grid this way gives the tiles of a
+/// bigger image.
+pub fn crop(view_proj: Mat4, min: Vec2, max: Vec2) -> Mat4 {
+    let scale = 2.0 / (max - min);
+    let center = (min + max) * 0.5;
+    Mat4::from_scale(scale.extend(1.0))
+        * Mat4::from_translation((-center).extend(0.0))
+        * view_proj
+}
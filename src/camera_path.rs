@@ -0,0 +1,207 @@
+use crate::camera::{Camera, Projection};
+use glam::Vec3;
+use std::{f32::consts::TAU, fs, path::Path};
+
+/// A camera path read from a file with one keyframe per line:
+///
+/// ```text
+/// # time x y z yaw pitch fov
+/// 0 100 80 -20 0 15 70
+/// 5 140 90 -20 -45 20 70
+/// ```
+///
+/// Times are in seconds. Yaw, pitch and the vertical field of view are in
+/// degrees, with yaw and pitch as shown on Minecraft's debug screen.
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+}
+
+struct Keyframe {
+    time: f32,
+    camera: Camera,
+}
+
+impl CameraPath {
+    /// Reads a camera path from a file, see [`CameraPath::parse`].
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = fs::read_to_string(path)
+            .map_err(|error| format!("{}: {error}", path.display()))?;
+        Self::parse(&file)
+            .map_err(|error| format!("{}: {error}", path.display()))
+    }
+
+    /// Parses a camera path with one keyframe per line.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut keyframes: Vec<Keyframe> = Vec::new();
+        for (line_number, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error =
+                |message: &str| format!("line {}: {message}", line_number + 1);
+            let [time, x, y, z, yaw, pitch, fov]: [f32; 7] = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| error("expected numbers"))?
+                .try_into()
+                .map_err(|_| error("expected `time x y z yaw pitch fov`"))?;
+            if keyframes.last().is_some_and(|last| last.time >= time) {
+                return Err(error("keyframes must be in order of time"));
+            }
+            // Minecraft's yaw turns the other way
+            let mut yaw = -yaw.to_radians();
+            // Turn the short way around from the previous keyframe
+            if let Some(last) = keyframes.last() {
+                yaw = last.camera.yaw
+                    + (yaw - last.camera.yaw + TAU / 2.0).rem_euclid(TAU)
+                    - TAU / 2.0;
+            }
+            keyframes.push(Keyframe {
+                time,
+                camera: Camera {
+                    position: Vec3::new(x, y, z),
+                    yaw,
+                    pitch: pitch.to_radians(),
+                    vfov: fov.to_radians(),
+                    projection: Projection::Perspective,
+                },
+            });
+        }
+        if keyframes.is_empty() {
+            return Err("the camera path has no keyframes".to_owned());
+        }
+        Ok(Self { keyframes })
+    }
+
+    /// The time of the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().unwrap().time
+    }
+
+    /// The camera at a time in seconds, moving and turning along splines
+    /// through the keyframes that keep the same speed through each of them.
+    pub fn camera_at(&self, time: f32) -> Camera {
+        let keyframes = &self.keyframes;
+        let Some(next) = keyframes.iter().position(|key| key.time > time)
+        else {
+            return keyframes.last().unwrap().camera;
+        };
+        if next == 0 {
+            return keyframes[0].camera;
+        }
+
+        let rotation = self.spline(next, time, |camera| {
+            Vec3::new(camera.yaw, camera.pitch, camera.vfov)
+        });
+        Camera {
+            position: self.spline(next, time, |camera| camera.position),
+            yaw: rotation.x,
+            pitch: rotation.y,
+            vfov: rotation.z,
+            projection: keyframes[next - 1].camera.projection,
+        }
+    }
+
+    /// A value of the cameras at a time between the keyframe `next` and the
+    /// one before it, along a Catmull-Rom spline whose tangents are scaled
+    /// to the time between keyframes.
+    fn spline(
+        &self,
+        next: usize,
+        time: f32,
+        value: impl Fn(&Camera) -> Vec3,
+    ) -> Vec3 {
+        let keyframes = &self.keyframes;
+        // The rate of change through a keyframe, from those on either side
+        // of it, so that the ends of the path continue in a straight line
+        let rate = |index: usize| {
+            let before = &keyframes[index.saturating_sub(1)];
+            let after = &keyframes[(index + 1).min(keyframes.len() - 1)];
+            (value(&after.camera) - value(&before.camera))
+                / (after.time - before.time)
+        };
+        let (from, to) = (&keyframes[next - 1], &keyframes[next]);
+        let duration = to.time - from.time;
+        let t = (time - from.time) / duration;
+        let (t2, t3) = (t * t, t * t * t);
+        (2.0 * t3 - 3.0 * t2 + 1.0) * value(&from.camera)
+            + (t3 - 2.0 * t2 + t) * duration * rate(next - 1)
+            + (3.0 * t2 - 2.0 * t3) * value(&to.camera)
+            + (t3 - t2) * duration * rate(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn parses_keyframes() {
+        let path = CameraPath::parse(
+            "# time x y z yaw pitch fov\n\
+             0 1 2 3 90 -10 70\n\
+             \n\
+             2.5 4 5 6 -170 0 60 # the end\n",
+        )
+        .unwrap();
+        assert_eq!(path.duration(), 2.5);
+        let camera = path.camera_at(0.0);
+        assert_eq!(camera.position, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(camera.yaw, -FRAC_PI_2);
+        assert_eq!(camera.pitch, -10_f32.to_radians());
+        // From -90° to 170° the short way, through 180°
+        let yaw = path.camera_at(2.5).yaw;
+        assert!((yaw - -190_f32.to_radians()).abs() < 1e-5, "{yaw}");
+
+        let error = |s| CameraPath::parse(s).err().unwrap();
+        assert_eq!(error(""), "the camera path has no keyframes");
+        assert_eq!(error("0 1 2 3 4 5 x"), "line 1: expected numbers");
+        assert_eq!(
+            error("0 1 2 3 4 5"),
+            "line 1: expected `time x y z yaw pitch fov`"
+        );
+        assert_eq!(
+            error("1 0 0 0 0 0 70\n1 0 0 0 0 0 70"),
+            "line 2: keyframes must be in order of time"
+        );
+    }
+
+    #[test]
+    fn moves_and_turns_smoothly_through_keyframes() {
+        // A short segment and then a long one, at the same speed and rate
+        // of turning
+        let path = CameraPath::parse(
+            "0 0 0 0 0 0 70\n1 10 0 0 -10 0 70\n4 40 0 0 -40 0 70",
+        )
+        .unwrap();
+        for time in [0.0, 0.5, 1.0, 2.5, 4.0] {
+            let camera = path.camera_at(time);
+            assert!((camera.position.x - time * 10.0).abs() < 1e-4, "{time}");
+            assert!(
+                (camera.yaw - (time * 10.0).to_radians()).abs() < 1e-5,
+                "{time}"
+            );
+            assert_eq!(camera.vfov, 70_f32.to_radians());
+        }
+        assert_eq!(path.camera_at(-1.0).position, Vec3::ZERO);
+        assert_eq!(path.camera_at(5.0).position, Vec3::new(40.0, 0.0, 0.0));
+
+        // Through a keyframe between segments of different lengths, the
+        // speed doesn't jump
+        let path = CameraPath::parse(
+            "0 0 0 0 0 0 70\n1 10 0 0 0 0 70\n4 10 0 30 0 0 70",
+        )
+        .unwrap();
+        let velocity = |from: f32, to: f32| {
+            (path.camera_at(to).position - path.camera_at(from).position)
+                / (to - from)
+        };
+        let before = velocity(0.999, 1.0);
+        let after = velocity(1.0, 1.001);
+        assert!((before - after).length() < 0.05, "{before} {after}");
+        assert!(before.length() > 1.0);
+    }
+}
@@ -1,75 +1,221 @@
-use glam::IVec2;
+use glam::IVec3;
 use internment::Intern;
-use serde::Deserialize;
-use std::{collections::BTreeMap, io::Cursor, ops::Range};
+use serde::{de::IgnoredAny, Deserialize, Deserializer};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
 
 lazy_static::lazy_static! {
+    /// The block that fills everything that hasn't been set.
     pub static ref AIR: Intern<Block> = Intern::new(Block {
         name: Intern::from_ref("minecraft:air"),
         properties: Intern::default(),
     });
 }
 
+/// A 16 block wide column of the world, from the bottom of its dimension
+/// to the top.
 #[derive(Deserialize)]
 pub struct Chunk {
-    pub sections: [Section; 24],
+    /// The version of Minecraft that last saved the chunk.
+    #[serde(rename = "DataVersion")]
+    pub data_version: i32,
+    /// The 16x16x16 sections from the bottom up, which start at a lower Y
+    /// in the overworld than in the other dimensions.
+    pub sections: Vec<Section>,
+    /// Block entities by their position in the world.
+    #[serde(default, deserialize_with = "deserialize_block_entities")]
+    pub block_entities: HashMap<IVec3, BlockEntity>,
+    /// The game tick when the chunk was last saved.
+    #[serde(default, rename = "LastUpdate")]
+    pub last_update: i64,
+    /// How many ticks players have spent near the chunk, added up over
+    /// every player.
+    #[serde(default, rename = "InhabitedTime")]
+    pub inhabited_time: i64,
+    /// How far the chunk is through being generated, which is `full` or
+    /// `minecraft:full` once it's done.
+    #[serde(default, rename = "Status")]
+    pub status: String,
+    /// The structures that start in the chunk or reach into it.
+    #[serde(default)]
+    pub structures: Structures,
+    /// The heights of the highest blocks, as saved by Minecraft.
+    #[serde(default, rename = "Heightmaps")]
+    pub heightmaps: Heightmaps,
 }
 
 impl Chunk {
-    pub fn load(
-        file: &[u8],
-        area: Range<IVec2>,
-        x: i32,
-        z: i32,
-    ) -> Option<Self> {
-        if x * 16 + 15 < area.start.x
-            || x * 16 >= area.end.x
-            || z * 16 + 15 < area.start.y
-            || z * 16 >= area.end.y
-        {
-            return None;
-        }
-        let x = x.rem_euclid(32) as usize;
-        let z = z.rem_euclid(32) as usize;
-        let locations: &[[u8; 4]] = bytemuck::cast_slice(&file[..4096]);
-        let raw_location = locations[z * 32 + x];
-        let data_offset = u32::from_be_bytes([
-            0,
-            raw_location[0],
-            raw_location[1],
-            raw_location[2],
-        ]) as usize
-            * 4096;
-        if data_offset == 0 {
-            return None;
+    /// The section at a Y coordinate in sections, if the chunk has it.
+    pub fn section(&self, section_y: i32) -> Option<&Section> {
+        // Sections are saved in order, but there can be sections with only
+        // light in them below and above the dimension's blocks
+        let first = i32::from(self.sections.first()?.y);
+        self.sections
+            .get(usize::try_from(section_y - first).ok()?)
+            .filter(|section| i32::from(section.y) == section_y)
+    }
+}
+
+/// The structures a chunk has a part of, by their namespaced IDs.
+#[derive(Default, Deserialize)]
+pub struct Structures {
+    /// The structures that start in the chunk.
+    #[serde(default)]
+    pub starts: BTreeMap<String, IgnoredAny>,
+    /// The structures that start in other chunks and reach into this one,
+    /// with the positions of those chunks packed into longs.
+    #[serde(default, rename = "References")]
+    pub references: BTreeMap<String, Vec<i64>>,
+}
+
+/// The heights of each column of a chunk, which Minecraft keeps for
+/// different kinds of blocks.
+#[derive(Default, Deserialize)]
+pub struct Heightmaps {
+    /// One more than the height of the highest block that isn't air, above
+    /// the bottom of the world, as 9 bit values packed into longs.
+    #[serde(rename = "WORLD_SURFACE")]
+    pub world_surface: Option<Vec<i64>>,
+}
+
+impl Heightmaps {
+    /// The Y coordinate of the highest block in a column that isn't air,
+    /// if the heightmap was saved and the column has any blocks. `min_y` is
+    /// the bottom of the dimension, which heights are counted from.
+    pub fn surface_y(&self, x: i32, z: i32, min_y: i32) -> Option<i32> {
+        let index = (z.rem_euclid(16) * 16 + x.rem_euclid(16)) as usize;
+        let long = self.world_surface.as_ref()?.get(index / 7)?;
+        let height = (*long as u64 >> (index % 7 * 9)) & 0x1ff;
+        (height > 0).then(|| min_y + height as i32 - 1)
+    }
+}
+
+fn deserialize_block_entities<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<IVec3, BlockEntity>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Vec::<BlockEntity>::deserialize(deserializer)?
+        .into_iter()
+        .map(|block_entity| (block_entity.pos(), block_entity))
+        .collect())
+}
+
+/// Extra data about a block, such as the items in a chest or the text on
+/// a sign.
+#[derive(Deserialize)]
+pub struct BlockEntity {
+    /// The X coordinate of the block.
+    pub x: i32,
+    /// The Y coordinate of the block.
+    pub y: i32,
+    /// The Z coordinate of the block.
+    pub z: i32,
+    /// The ID and everything else, which depends on the kind of block
+    /// entity.
+    #[serde(flatten)]
+    pub data: HashMap<String, nbt::Value>,
+}
+
+impl BlockEntity {
+    /// A block entity at a position.
+    pub const fn new(pos: IVec3, data: HashMap<String, nbt::Value>) -> Self {
+        Self {
+            x: pos.x,
+            y: pos.y,
+            z: pos.z,
+            data,
         }
-        let payload = &file[data_offset..];
-        let length =
-            u32::from_be_bytes(bytemuck::cast_slice(payload)[0]) as usize - 1;
-        let data = &payload[5..][..length];
-        let compression_scheme = payload[4];
-        assert_eq!(
-            compression_scheme, 2,
-            "only zlib chunk compression is supported"
-        );
-        Some(nbt::from_zlib_reader(&mut Cursor::new(data)).unwrap())
+    }
+
+    /// The position of the block in the world.
+    pub const fn pos(&self) -> IVec3 {
+        IVec3::new(self.x, self.y, self.z)
     }
 }
 
+/// A 16x16x16 cube of a chunk.
 #[derive(Deserialize)]
 pub struct Section {
+    /// The Y coordinate of the section in sections.
+    #[serde(rename = "Y")]
+    pub y: i8,
+    /// Which block is where.
     #[serde(default)]
     pub block_states: BlockStates,
+    /// The light from light sources, 4 bits per block.
     #[serde(default)]
     #[serde(rename = "BlockLight")]
     pub block_light: Box<[i8]>,
+    /// The light from the sky, 4 bits per block.
     #[serde(default)]
     #[serde(rename = "SkyLight")]
     pub sky_light: Box<[i8]>,
+    /// Which biome is where, in cells of 4x4x4 blocks.
+    #[serde(default)]
+    pub biomes: Biomes,
+}
+
+/// The biomes of a section, as indices into a palette of biome names.
+pub struct Biomes {
+    /// Each distinct biome in the section.
+    pub palette: Vec<Intern<Box<str>>>,
+    /// Indices into the palette in the order Y, Z, X from the slowest to the
+    /// fastest changing.
+    pub data: [u8; 64],
+}
+
+impl Default for Biomes {
+    fn default() -> Self {
+        Self {
+            palette: vec![Intern::from_ref("minecraft:plains")],
+            data: [0; 64],
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Biomes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct DeBiomes {
+            palette: Vec<Intern<Box<str>>>,
+            data: Option<Vec<i64>>,
+        }
+
+        let DeBiomes { palette, data } = DeBiomes::deserialize(deserializer)?;
+        let mut indices = [0; 64];
+        // Unlike blocks, biomes use as few bits as they need
+        if let (Some(data), true) = (data, palette.len() > 1) {
+            let index_bit_length =
+                usize::BITS - (palette.len() - 1).leading_zeros();
+            let indices_per_long = 64 / index_bit_length;
+            let mask = (1u64 << index_bit_length) - 1;
+            for (i, index) in indices.iter_mut().enumerate() {
+                let long = data[i / indices_per_long as usize] as u64;
+                let shift = (i as u32 % indices_per_long) * index_bit_length;
+                *index = ((long >> shift) & mask) as u8;
+            }
+        }
+        Ok(Self {
+            palette,
+            data: indices,
+        })
+    }
 }
 
+/// The blocks of a section, as indices into a palette of block states.
+#[derive(Clone)]
 pub struct BlockStates {
+    /// Each distinct block in the section.
     pub palette: Vec<Intern<Block>>,
+    /// Indices into the palette in the order Y, Z, X from the slowest to the
+    /// fastest changing.
     pub data: Box<[u16; 4096]>,
 }
 
@@ -85,7 +231,7 @@ impl Default for BlockStates {
 impl<'de> Deserialize<'de> for BlockStates {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
-        D: serde::Deserializer<'de>,
+        D: Deserializer<'de>,
     {
         #[derive(Deserialize)]
         struct DeBlockStates {
@@ -124,10 +270,131 @@ impl<'de> Deserialize<'de> for BlockStates {
     }
 }
 
+/// A block state, which is a kind of block and the properties that set
+/// its shape, such as which way stairs face.
 #[derive(Debug, PartialEq, Eq, Hash, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Block {
+    /// The namespaced ID, such as `minecraft:stone`.
     pub name: Intern<Box<str>>,
+    /// Properties such as `facing`, by name.
     #[serde(default)]
     pub properties: Intern<BTreeMap<String, String>>,
 }
+
+impl Block {
+    /// Parses a block in the same format as commands, such as
+    /// `minecraft:oak_stairs[facing=east,half=bottom]`. The namespace
+    /// defaults to `minecraft`.
+    pub fn parse(state: &str) -> Intern<Self> {
+        let (name, properties) = state
+            .strip_suffix(']')
+            .and_then(|state| state.split_once('['))
+            .unwrap_or((state, ""));
+        let name = if name.contains(':') {
+            name.to_owned()
+        } else {
+            format!("minecraft:{name}")
+        };
+        let properties: BTreeMap<String, String> = properties
+            .split(',')
+            .filter_map(|property| property.split_once('='))
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect();
+        Intern::new(Self {
+            name: Intern::new(name.into_boxed_str()),
+            properties: Intern::new(properties),
+        })
+    }
+
+    /// Whether the block is any kind of air, which has no faces.
+    pub fn is_air(&self) -> bool {
+        matches!(
+            self.name.as_ref().as_ref(),
+            "minecraft:air" | "minecraft:cave_air" | "minecraft:void_air"
+        )
+    }
+}
+
+/// Formats the block in the same format as commands, such as
+/// `minecraft:oak_stairs[facing=east,half=bottom]`.
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.properties.is_empty() {
+            let properties: Vec<String> = self
+                .properties
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect();
+            write!(f, "[{}]", properties.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use std::io::Cursor;
+
+    fn read_block_states(palette: &[&str], indices: &[u16]) -> BlockStates {
+        #[derive(Deserialize)]
+        struct Section {
+            block_states: BlockStates,
+        }
+
+        let mut blob = nbt::Blob::new();
+        blob.insert("block_states", test_util::block_states(palette, indices))
+            .unwrap();
+        let mut bytes = Vec::new();
+        blob.to_writer(&mut bytes).unwrap();
+        let section: Section = nbt::from_reader(Cursor::new(bytes)).unwrap();
+        section.block_states
+    }
+
+    fn names(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("minecraft:block_{i}")).collect()
+    }
+
+    #[test]
+    fn single_block_palette_needs_no_data() {
+        let block_states = read_block_states(&["minecraft:stone"], &[]);
+        assert_eq!(block_states.palette, [Block::parse("minecraft:stone")]);
+        assert!(block_states.data.iter().all(|&index| index == 0));
+    }
+
+    #[test]
+    fn unpacks_every_palette_size() {
+        // 4 bits is the minimum, and 5 and 9 bits don't fill whole longs
+        for palette_size in [2, 16, 17, 32, 33, 300] {
+            let names = names(palette_size);
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            let indices: Vec<u16> = (0..4096)
+                .map(|i| ((i * 7 + i / 13) % palette_size) as u16)
+                .collect();
+            let block_states = read_block_states(&names, &indices);
+            assert_eq!(block_states.palette.len(), palette_size);
+            assert_eq!(
+                block_states.data[..],
+                indices[..],
+                "palette of {palette_size} blocks"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_block_states() {
+        let block = Block::parse("oak_stairs[facing=east,half=bottom]");
+        assert_eq!(&**block.name, "minecraft:oak_stairs");
+        assert_eq!(block.properties["facing"], "east");
+        assert_eq!(block.properties["half"], "bottom");
+        assert_eq!(Block::parse("minecraft:air"), *AIR);
+        assert!(Block::parse("cave_air").is_air());
+        assert_eq!(
+            block.to_string(),
+            "minecraft:oak_stairs[facing=east,half=bottom]"
+        );
+    }
+}
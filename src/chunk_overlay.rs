@@ -0,0 +1,378 @@
+//! Shows what Minecraft keeps about each chunk, such as how long players
+//! spent near it, as a heat map over the terrain and as a text report.
+
+use crate::{block_source::BlockSource, chunk::Chunk, mesh::Highlights};
+use glam::{IVec2, IVec3, Vec3};
+use std::{fmt::Write as _, fs, ops::Range, path::Path};
+
+/// The stages of generating a chunk, in order.
+const STATUSES: [&str; 13] = [
+    "empty",
+    "structure_starts",
+    "structure_references",
+    "biomes",
+    "noise",
+    "surface",
+    "carvers",
+    "liquid_carvers",
+    "features",
+    "initialize_light",
+    "light",
+    "spawn",
+    "full",
+];
+/// The colors of the heat map from the coldest to the hottest.
+const HEAT_COLORS: [Vec3; 4] = [
+    Vec3::new(0.1, 0.2, 0.9),
+    Vec3::new(0.1, 0.8, 0.3),
+    Vec3::new(1.0, 0.9, 0.1),
+    Vec3::new(0.9, 0.1, 0.1),
+];
+/// Game ticks per second.
+const TICKS_PER_SECOND: i64 = 20;
+
+/// Which of the chunks' data to show over them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChunkOverlay {
+    /// How long players spent near each chunk, on a logarithmic scale.
+    /// Chunks where no one has been are left out.
+    InhabitedTime,
+    /// When each chunk was last saved, from the oldest to the newest in the
+    /// area.
+    LastUpdate,
+    /// How far chunks that aren't fully generated got. Chunks that are
+    /// fully generated are left out.
+    Status,
+    /// Chunks where structures start, which are the hottest, and chunks that
+    /// other structures reach into.
+    Structures,
+}
+
+impl ChunkOverlay {
+    /// Parses `inhabited-time`, `last-update`, `status` or `structures`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "inhabited-time" => Ok(Self::InhabitedTime),
+            "last-update" => Ok(Self::LastUpdate),
+            "status" => Ok(Self::Status),
+            "structures" => Ok(Self::Structures),
+            _ => Err(format!(
+                "expected `inhabited-time`, `last-update`, `status` or \
+                 `structures` instead of `{s}`"
+            )),
+        }
+    }
+
+    /// Adds a flat box over each chunk in the area, at the height of its
+    /// highest block, colored by how hot the chunk is.
+    pub fn add_highlights(
+        self,
+        world: &dyn BlockSource,
+        area: &Range<IVec2>,
+        highlights: &mut Highlights,
+    ) {
+        let chunks = chunks(world, area);
+        let max_inhabited_time = chunks
+            .iter()
+            .map(|(_, chunk)| chunk.inhabited_time)
+            .max()
+            .unwrap_or(0);
+        let update_times = chunks.iter().map(|(_, chunk)| chunk.last_update);
+        let first_update = update_times.clone().min().unwrap_or(0);
+        let last_update = update_times.max().unwrap_or(0);
+        let heat = |chunk: &Chunk| -> Option<f32> {
+            match self {
+                Self::InhabitedTime => (chunk.inhabited_time > 0).then(|| {
+                    (chunk.inhabited_time as f32).ln_1p()
+                        / (max_inhabited_time as f32).ln_1p()
+                }),
+                Self::LastUpdate => Some(if last_update > first_update {
+                    (chunk.last_update - first_update) as f32
+                        / (last_update - first_update) as f32
+                } else {
+                    1.0
+                }),
+                Self::Status => {
+                    let stage = stage(&chunk.status);
+                    (stage < STATUSES.len() - 1)
+                        .then(|| stage as f32 / (STATUSES.len() - 1) as f32)
+                }
+                Self::Structures => {
+                    let structures = &chunk.structures;
+                    if !structures.starts.is_empty() {
+                        Some(1.0)
+                    } else if structures
+                        .references
+                        .values()
+                        .any(|references| !references.is_empty())
+                    {
+                        Some(0.5)
+                    } else {
+                        None
+                    }
+                }
+            }
+        };
+
+        for &(pos, chunk) in &chunks {
+            let Some(heat) = heat(chunk) else {
+                continue;
+            };
+            let min = (pos * 16).max(area.start);
+            let max = (pos * 16 + 16).min(area.end);
+            let top = (min.y..max.y)
+                .flat_map(|z| (min.x..max.x).map(move |x| (x, z)))
+                .filter_map(|(x, z)| world.surface_at(x, z))
+                .map(|(y, _block)| y + 1)
+                .max()
+                .unwrap_or(0);
+            highlights.add_box(
+                IVec3::new(min.x, top, min.y).as_vec3(),
+                IVec3::new(max.x, top, max.y).as_vec3(),
+                heat_color(heat),
+            );
+        }
+    }
+}
+
+/// The loaded chunks that overlap the area, by chunk position.
+fn chunks<'a>(
+    world: &'a dyn BlockSource,
+    area: &Range<IVec2>,
+) -> Vec<(IVec2, &'a Chunk)> {
+    let chunk_range =
+        |start: i32, end: i32| start.div_euclid(16)..=(end - 1).div_euclid(16);
+    chunk_range(area.start.y, area.end.y)
+        .flat_map(|z| {
+            chunk_range(area.start.x, area.end.x).map(move |x| IVec2::new(x, z))
+        })
+        .filter_map(|pos| Some((pos, world.chunk(pos)?)))
+        .collect()
+}
+
+/// How far through being generated a chunk is, as an index into
+/// [`STATUSES`]. Chunks saved without a status are taken to be finished,
+/// and stages from other versions of Minecraft to be the first.
+fn stage(status: &str) -> usize {
+    if status.is_empty() {
+        return STATUSES.len() - 1;
+    }
+    let status = status.strip_prefix("minecraft:").unwrap_or(status);
+    STATUSES
+        .iter()
+        .position(|stage| *stage == status)
+        .unwrap_or(0)
+}
+
+/// A color on the heat map, from 0 for the coldest to 1 for the hottest.
+fn heat_color(heat: f32) -> Vec3 {
+    let scaled = heat.clamp(0.0, 1.0) * (HEAT_COLORS.len() - 1) as f32;
+    let index = (scaled as usize).min(HEAT_COLORS.len() - 2);
+    HEAT_COLORS[index].lerp(HEAT_COLORS[index + 1], scaled - index as f32)
+}
+
+/// Writes a table of the chunks in the area, the most inhabited first,
+/// with how far they were generated, when they were last saved and which
+/// structures they have a part of.
+pub fn write_chunk_report(
+    world: &dyn BlockSource,
+    area: &Range<IVec2>,
+    path: &Path,
+) -> Result<(), String> {
+    let mut chunks = chunks(world, area);
+    chunks.sort_by_key(|(pos, chunk)| (-chunk.inhabited_time, pos.y, pos.x));
+
+    let mut report = format!(
+        "{:<12} {:<22} {:>12} {:>12}  structures\n",
+        "chunk", "status", "inhabited", "last update"
+    );
+    for (pos, chunk) in &chunks {
+        let structures = &chunk.structures;
+        let mut names: Vec<String> = structures
+            .starts
+            .keys()
+            .map(|name| format!("{name} (start)"))
+            .collect();
+        names.extend(
+            structures
+                .references
+                .iter()
+                .filter(|(name, references)| {
+                    !references.is_empty()
+                        && !structures.starts.contains_key(*name)
+                })
+                .map(|(name, _)| name.clone()),
+        );
+        let line = format!(
+            "{:<12} {:<22} {:>12} {:>12}  {}",
+            format!("{},{}", pos.x, pos.y),
+            chunk.status,
+            duration(chunk.inhabited_time),
+            chunk.last_update,
+            names.join(", "),
+        );
+        writeln!(report, "{}", line.trim_end()).unwrap();
+    }
+    let partial = chunks
+        .iter()
+        .filter(|(_, chunk)| stage(&chunk.status) < STATUSES.len() - 1)
+        .count();
+    let inhabited = chunks
+        .iter()
+        .filter(|(_, chunk)| chunk.inhabited_time > 0)
+        .count();
+    writeln!(
+        report,
+        "\n{} chunks, {inhabited} inhabited and {partial} not fully generated",
+        chunks.len(),
+    )
+    .unwrap();
+
+    fs::write(path, report)
+        .map_err(|error| format!("{}: {error}", path.display()))
+}
+
+/// A number of ticks as hours, minutes and seconds of play.
+fn duration(ticks: i64) -> String {
+    let seconds = ticks / TICKS_PER_SECOND;
+    format!(
+        "{}h {:02}m {:02}s",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util, World};
+    use nbt::Value;
+
+    /// Four chunks in a row with stone up to Y 15, of which the first was
+    /// lived in for an hour, the second has a village and the third
+    /// isn't finished.
+    fn world() -> World {
+        let directory = test_util::temp_dir("chunk-overlay");
+        let chunks: Vec<_> = [
+            (3600 * 20, "minecraft:full", None),
+            (100, "minecraft:full", Some(true)),
+            (0, "minecraft:features", Some(false)),
+            (0, "minecraft:full", None),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(x, (inhabited_time, status, village_start))| {
+            let block_states =
+                test_util::block_states(&["minecraft:stone"], &[]);
+            let mut chunk = test_util::chunk(&[(0, block_states)]);
+            chunk
+                .insert("InhabitedTime", Value::Long(inhabited_time))
+                .unwrap();
+            chunk.insert("Status", status).unwrap();
+            chunk
+                .insert("LastUpdate", Value::Long(x as i64 * 10))
+                .unwrap();
+            if let Some(start) = village_start {
+                let village = || "minecraft:village_plains".to_owned();
+                let starts = if start {
+                    nbt::Map::from([(
+                        village(),
+                        Value::Compound(nbt::Map::from([(
+                            "id".to_owned(),
+                            Value::String(village()),
+                        )])),
+                    )])
+                } else {
+                    nbt::Map::new()
+                };
+                let structures = nbt::Map::from([
+                    ("starts".to_owned(), Value::Compound(starts)),
+                    (
+                        "References".to_owned(),
+                        Value::Compound(nbt::Map::from([(
+                            village(),
+                            Value::LongArray(vec![1]),
+                        )])),
+                    ),
+                ]);
+                chunk
+                    .insert("structures", Value::Compound(structures))
+                    .unwrap();
+            }
+            (IVec2::new(x as i32, 0), chunk)
+        })
+        .collect();
+        test_util::write_region(&directory.join("region/r.0.0.mca"), &chunks);
+        World::new(
+            &directory,
+            &test_util::options(IVec2::ZERO..IVec2::new(64, 16)),
+        )
+    }
+
+    fn highlighted_chunks(overlay: ChunkOverlay) -> Vec<(Vec3, Vec3)> {
+        let mut highlights = Highlights::default();
+        overlay.add_highlights(
+            &world(),
+            &(IVec2::ZERO..IVec2::new(64, 16)),
+            &mut highlights,
+        );
+        // The first corner of each box and its color
+        highlights
+            .vertices
+            .chunks(24)
+            .map(|vertices| (vertices[0].pos, vertices[0].color))
+            .collect()
+    }
+
+    #[test]
+    fn colors_chunks_by_their_data() {
+        let inhabited = highlighted_chunks(ChunkOverlay::InhabitedTime);
+        assert_eq!(
+            inhabited,
+            [
+                (Vec3::new(0.0, 16.0, 0.0), heat_color(1.0)),
+                (
+                    Vec3::new(16.0, 16.0, 0.0),
+                    heat_color(101f32.ln() / 72001f32.ln())
+                ),
+            ]
+        );
+        let updated = highlighted_chunks(ChunkOverlay::LastUpdate);
+        assert_eq!(updated.len(), 4);
+        assert_eq!(updated[0].1, heat_color(0.0));
+        assert_eq!(updated[3].1, heat_color(1.0));
+        let unfinished = highlighted_chunks(ChunkOverlay::Status);
+        assert_eq!(unfinished.len(), 1);
+        assert_eq!(unfinished[0].0.x, 32.0);
+        let structures = highlighted_chunks(ChunkOverlay::Structures);
+        assert_eq!(
+            structures
+                .iter()
+                .map(|(_, color)| *color)
+                .collect::<Vec<_>>(),
+            [heat_color(1.0), heat_color(0.5)]
+        );
+        assert!(ChunkOverlay::parse("status").is_ok());
+        assert!(ChunkOverlay::parse("biomes").is_err());
+    }
+
+    #[test]
+    fn reports_the_most_inhabited_chunks_first() {
+        let path = test_util::temp_dir("chunk-report").join("chunks.txt");
+        write_chunk_report(&world(), &(IVec2::ZERO..IVec2::new(64, 16)), &path)
+            .unwrap();
+        let report = fs::read_to_string(path).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert!(lines[1].starts_with("0,0 "));
+        assert!(lines[1].contains("1h 00m 00s"));
+        assert!(lines[2].starts_with("1,0 "));
+        assert!(lines[2].ends_with("minecraft:village_plains (start)"));
+        assert!(lines[3].contains("minecraft:features"));
+        assert!(lines[3].ends_with("  minecraft:village_plains"));
+        assert_eq!(
+            lines.last().unwrap(),
+            &"4 chunks, 2 inhabited and 1 not fully generated"
+        );
+    }
+}
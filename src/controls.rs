@@ -0,0 +1,268 @@
+use miniquad::KeyCode;
+use std::{collections::HashMap, fs, path::Path};
+
+/// Something the camera can be told to do by holding down a key.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Moves the way the camera faces.
+    Forward,
+    /// Moves away from the way the camera faces.
+    Back,
+    /// Moves to the left.
+    Left,
+    /// Moves to the right.
+    Right,
+    /// Flies straight up.
+    Up,
+    /// Flies straight down.
+    Down,
+    /// Moves faster while held.
+    Sprint,
+    /// Turns the camera to the left.
+    TurnLeft,
+    /// Turns the camera to the right.
+    TurnRight,
+    /// Tilts the camera up.
+    LookUp,
+    /// Tilts the camera down.
+    LookDown,
+    /// Widens the field of view, which zooms out.
+    WidenFov,
+    /// Narrows the field of view, which zooms in.
+    NarrowFov,
+    /// While held, the scroll wheel changes the field of view instead of
+    /// the speed.
+    ScrollFov,
+    /// Lets go of the mouse so that it can leave the window.
+    ReleaseMouse,
+    /// Starts or stops moving along the camera path.
+    PlayPath,
+    /// Saves what the window shows to the screenshots folder.
+    Screenshot,
+    /// Saves the view at several times the window's resolution.
+    HiResScreenshot,
+    /// Switches between perspective and orthographic projections.
+    ToggleProjection,
+    /// Switches to an isometric view, turning to the next compass
+    /// direction if already orthographic.
+    NextIsometricDirection,
+    /// Switches to an isometric view, changing between looking down at
+    /// 30° and 45° if already orthographic.
+    NextIsometricPitch,
+}
+
+const ACTIONS: [(&str, Action); 21] = [
+    ("forward", Action::Forward),
+    ("back", Action::Back),
+    ("left", Action::Left),
+    ("right", Action::Right),
+    ("up", Action::Up),
+    ("down", Action::Down),
+    ("sprint", Action::Sprint),
+    ("turn_left", Action::TurnLeft),
+    ("turn_right", Action::TurnRight),
+    ("look_up", Action::LookUp),
+    ("look_down", Action::LookDown),
+    ("widen_fov", Action::WidenFov),
+    ("narrow_fov", Action::NarrowFov),
+    ("scroll_fov", Action::ScrollFov),
+    ("release_mouse", Action::ReleaseMouse),
+    ("play_path", Action::PlayPath),
+    ("screenshot", Action::Screenshot),
+    ("hi_res_screenshot", Action::HiResScreenshot),
+    ("toggle_projection", Action::ToggleProjection),
+    ("next_isometric_direction", Action::NextIsometricDirection),
+    ("next_isometric_pitch", Action::NextIsometricPitch),
+];
+
+/// Which action each key performs.
+pub struct KeyBindings(HashMap<KeyCode, Action>);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self(HashMap::from([
+            (KeyCode::W, Action::Forward),
+            (KeyCode::S, Action::Back),
+            (KeyCode::A, Action::Left),
+            (KeyCode::D, Action::Right),
+            (KeyCode::Space, Action::Up),
+            (KeyCode::LeftShift, Action::Down),
+            (KeyCode::LeftControl, Action::Sprint),
+            (KeyCode::H, Action::TurnLeft),
+            (KeyCode::L, Action::TurnRight),
+            (KeyCode::K, Action::LookUp),
+            (KeyCode::J, Action::LookDown),
+            (KeyCode::O, Action::WidenFov),
+            (KeyCode::I, Action::NarrowFov),
+            (KeyCode::LeftAlt, Action::ScrollFov),
+            (KeyCode::Escape, Action::ReleaseMouse),
+            (KeyCode::P, Action::PlayPath),
+            (KeyCode::F2, Action::Screenshot),
+            (KeyCode::F3, Action::HiResScreenshot),
+            (KeyCode::F5, Action::ToggleProjection),
+            (KeyCode::F6, Action::NextIsometricDirection),
+            (KeyCode::F7, Action::NextIsometricPitch),
+        ]))
+    }
+}
+
+impl KeyBindings {
+    /// Reads key bindings from a file with one action per line followed
+    /// by `=` and the keys for it, such as `forward = W Up`. Actions that
+    /// aren't mentioned keep their default keys.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = fs::read_to_string(path)
+            .map_err(|error| format!("{}: {error}", path.display()))?;
+        Self::parse(&file)
+            .map_err(|error| format!("{}: {error}", path.display()))
+    }
+
+    /// Parses lines of `action = keys`, with keys separated by spaces.
+    /// Actions that aren't mentioned keep their default keys.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut bindings = Self::default();
+        for (line_number, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error =
+                |message| format!("line {}: {message}", line_number + 1);
+            let (action_name, keys) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected `action = keys`".to_owned()))?;
+            let action_name = action_name.trim();
+            let &(_, action) = ACTIONS
+                .iter()
+                .find(|(name, _)| *name == action_name)
+                .ok_or_else(|| {
+                    error(format!("unknown action `{action_name}`"))
+                })?;
+            bindings.0.retain(|_, bound_action| *bound_action != action);
+            for key_name in keys.split_whitespace() {
+                let &(_, key) = KEYS
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(key_name))
+                    .ok_or_else(|| {
+                        error(format!("unknown key `{key_name}`"))
+                    })?;
+                bindings.0.insert(key, action);
+            }
+        }
+        Ok(bindings)
+    }
+
+    /// What a key does, if it is bound.
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        self.0.get(&key).copied()
+    }
+}
+
+/// The names of keys in key binding files.
+const KEYS: [(&str, KeyCode); 105] = [
+    ("Space", KeyCode::Space),
+    ("Apostrophe", KeyCode::Apostrophe),
+    ("Comma", KeyCode::Comma),
+    ("Minus", KeyCode::Minus),
+    ("Period", KeyCode::Period),
+    ("Slash", KeyCode::Slash),
+    ("Key0", KeyCode::Key0),
+    ("Key1", KeyCode::Key1),
+    ("Key2", KeyCode::Key2),
+    ("Key3", KeyCode::Key3),
+    ("Key4", KeyCode::Key4),
+    ("Key5", KeyCode::Key5),
+    ("Key6", KeyCode::Key6),
+    ("Key7", KeyCode::Key7),
+    ("Key8", KeyCode::Key8),
+    ("Key9", KeyCode::Key9),
+    ("Semicolon", KeyCode::Semicolon),
+    ("Equal", KeyCode::Equal),
+    ("A", KeyCode::A),
+    ("B", KeyCode::B),
+    ("C", KeyCode::C),
+    ("D", KeyCode::D),
+    ("E", KeyCode::E),
+    ("F", KeyCode::F),
+    ("G", KeyCode::G),
+    ("H", KeyCode::H),
+    ("I", KeyCode::I),
+    ("J", KeyCode::J),
+    ("K", KeyCode::K),
+    ("L", KeyCode::L),
+    ("M", KeyCode::M),
+    ("N", KeyCode::N),
+    ("O", KeyCode::O),
+    ("P", KeyCode::P),
+    ("Q", KeyCode::Q),
+    ("R", KeyCode::R),
+    ("S", KeyCode::S),
+    ("T", KeyCode::T),
+    ("U", KeyCode::U),
+    ("V", KeyCode::V),
+    ("W", KeyCode::W),
+    ("X", KeyCode::X),
+    ("Y", KeyCode::Y),
+    ("Z", KeyCode::Z),
+    ("LeftBracket", KeyCode::LeftBracket),
+    ("Backslash", KeyCode::Backslash),
+    ("RightBracket", KeyCode::RightBracket),
+    ("GraveAccent", KeyCode::GraveAccent),
+    ("Escape", KeyCode::Escape),
+    ("Enter", KeyCode::Enter),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("Insert", KeyCode::Insert),
+    ("Delete", KeyCode::Delete),
+    ("Right", KeyCode::Right),
+    ("Left", KeyCode::Left),
+    ("Down", KeyCode::Down),
+    ("Up", KeyCode::Up),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("CapsLock", KeyCode::CapsLock),
+    ("ScrollLock", KeyCode::ScrollLock),
+    ("NumLock", KeyCode::NumLock),
+    ("PrintScreen", KeyCode::PrintScreen),
+    ("Pause", KeyCode::Pause),
+    ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2),
+    ("F3", KeyCode::F3),
+    ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7),
+    ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9),
+    ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11),
+    ("F12", KeyCode::F12),
+    ("Kp0", KeyCode::Kp0),
+    ("Kp1", KeyCode::Kp1),
+    ("Kp2", KeyCode::Kp2),
+    ("Kp3", KeyCode::Kp3),
+    ("Kp4", KeyCode::Kp4),
+    ("Kp5", KeyCode::Kp5),
+    ("Kp6", KeyCode::Kp6),
+    ("Kp7", KeyCode::Kp7),
+    ("Kp8", KeyCode::Kp8),
+    ("Kp9", KeyCode::Kp9),
+    ("KpDecimal", KeyCode::KpDecimal),
+    ("KpDivide", KeyCode::KpDivide),
+    ("KpMultiply", KeyCode::KpMultiply),
+    ("KpSubtract", KeyCode::KpSubtract),
+    ("KpAdd", KeyCode::KpAdd),
+    ("KpEnter", KeyCode::KpEnter),
+    ("KpEqual", KeyCode::KpEqual),
+    ("LeftShift", KeyCode::LeftShift),
+    ("LeftControl", KeyCode::LeftControl),
+    ("LeftAlt", KeyCode::LeftAlt),
+    ("LeftSuper", KeyCode::LeftSuper),
+    ("RightShift", KeyCode::RightShift),
+    ("RightControl", KeyCode::RightControl),
+    ("RightAlt", KeyCode::RightAlt),
+    ("RightSuper", KeyCode::RightSuper),
+    ("Menu", KeyCode::Menu),
+];
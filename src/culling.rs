@@ -0,0 +1,208 @@
+use crate::mesh::{Lod, Section, LOD_SCALES};
+use glam::{IVec2, IVec3, Mat4, Vec3, Vec3Swizzles, Vec4};
+use std::collections::{HashMap, VecDeque};
+
+/// The six directions out of a section, in the order used by
+/// [`Visibility`]. Opposite directions differ only in the lowest bit.
+const DIRECTIONS: [IVec3; 6] = [
+    IVec3::NEG_X,
+    IVec3::X,
+    IVec3::NEG_Y,
+    IVec3::Y,
+    IVec3::NEG_Z,
+    IVec3::Z,
+];
+
+/// The planes bounding the volume that the camera can see.
+pub struct Frustum {
+    /// Each plane is stored as a normal pointing into the frustum and a
+    /// distance, so that a point is inside if its dot product with every
+    /// plane is positive.
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    pub fn new(view_proj: Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_proj.row(i));
+        Self {
+            planes: [w + x, w - x, w + y, w - y, w + z, w - z],
+        }
+    }
+
+    /// Whether any part of an axis-aligned box may be visible.
+    pub fn contains_box(&self, min: Vec3, max: Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner of the box furthest along the plane's normal
+            let corner =
+                Vec3::select(plane.truncate().cmpge(Vec3::ZERO), max, min);
+            plane.dot(corner.extend(1.0)) >= 0.0
+        })
+    }
+}
+
+/// Which faces of a section can be seen from which other faces by looking
+/// through the blocks inside it, like Minecraft's chunk visibility graph.
+#[derive(Clone, Copy)]
+pub struct Visibility(u64);
+
+impl Visibility {
+    /// Every face can be seen from every other face.
+    pub const ALL: Self = Self(u64::MAX);
+
+    /// Flood fills the transparent blocks of a section, given whether the
+    /// block at each offset within it is opaque.
+    pub fn compute(opaque: impl Fn(IVec3) -> bool) -> Self {
+        let mut visited = [false; 16 * 16 * 16];
+        let index = |pos: IVec3| (pos.y * 256 + pos.z * 16 + pos.x) as usize;
+        let mut visibility = Self(0);
+        let mut stack = Vec::new();
+        for start in 0..16 * 16 * 16 {
+            let start = IVec3::new(start % 16, start / 256, start / 16 % 16);
+            if visited[index(start)] || opaque(start) {
+                continue;
+            }
+            visited[index(start)] = true;
+            stack.push(start);
+            let mut faces = 0u8;
+            while let Some(pos) = stack.pop() {
+                for (direction, offset) in DIRECTIONS.into_iter().enumerate() {
+                    let neighbour = pos + offset;
+                    if neighbour.cmplt(IVec3::ZERO).any()
+                        || neighbour.cmpge(IVec3::splat(16)).any()
+                    {
+                        faces |= 1 << direction;
+                    } else if !visited[index(neighbour)] && !opaque(neighbour) {
+                        visited[index(neighbour)] = true;
+                        stack.push(neighbour);
+                    }
+                }
+            }
+            for from in 0..6 {
+                for to in 0..6 {
+                    if faces & (1 << from) != 0 && faces & (1 << to) != 0 {
+                        visibility.0 |= 1 << (from * 6 + to);
+                    }
+                }
+            }
+        }
+        visibility
+    }
+
+    fn connects(self, from: usize, to: usize) -> bool {
+        self.0 & (1 << (from * 6 + to)) != 0
+    }
+}
+
+/// The indices of the sections that can be seen from the camera, nearest
+/// first, found by walking outwards from the camera's section through
+/// faces that can see each other and skipping sections outside the
+/// frustum.
+pub fn visible_sections(
+    sections: &[Section],
+    camera: Vec3,
+    frustum: &Frustum,
+) -> Vec<usize> {
+    let by_pos: HashMap<IVec3, usize> = sections
+        .iter()
+        .enumerate()
+        .map(|(index, section)| (section.pos, index))
+        .collect();
+    let Some((min, max)) = sections
+        .iter()
+        .map(|section| (section.pos, section.pos))
+        .reduce(|(min, max), (pos, _)| (min.min(pos), max.max(pos)))
+    else {
+        return Vec::new();
+    };
+
+    // Start from the nearest section if the camera is outside the world
+    let start = (camera.floor().as_ivec3() >> 4_i32).clamp(min, max);
+    let mut visible = Vec::new();
+    let mut visited = vec![false; sections.len()];
+    // Each queued section remembers the face it was entered through and the
+    // directions taken to reach it, which are never walked back along
+    let mut queue = VecDeque::from([(start, None, 0u8)]);
+    visited[by_pos[&start]] = true;
+    while let Some((pos, entered_from, directions)) = queue.pop_front() {
+        let index = by_pos[&pos];
+        let section = &sections[index];
+        visible.push(index);
+        for (direction, offset) in DIRECTIONS.into_iter().enumerate() {
+            let opposite = direction ^ 1;
+            if directions & (1 << opposite) != 0
+                || entered_from.is_some_and(|from| {
+                    !section.visibility.connects(from, direction)
+                })
+            {
+                continue;
+            }
+            let neighbour_pos = pos + offset;
+            let Some(&neighbour) = by_pos.get(&neighbour_pos) else {
+                continue;
+            };
+            let neighbour_section = &sections[neighbour];
+            if visited[neighbour]
+                || !frustum
+                    .contains_box(neighbour_section.min, neighbour_section.max)
+            {
+                continue;
+            }
+            visited[neighbour] = true;
+            queue.push_back((
+                neighbour_pos,
+                Some(opposite),
+                directions | 1 << direction,
+            ));
+        }
+    }
+    visible
+}
+
+/// The sections and simplified chunk meshes to draw for a camera.
+pub fn draw_list(
+    sections: &[Section],
+    lods: &[Lod],
+    camera: Vec3,
+    frustum: &Frustum,
+    cave_culling: bool,
+    lod_distance: Option<f32>,
+) -> (Vec<usize>, Vec<usize>) {
+    let visible_sections = if cave_culling {
+        visible_sections(sections, camera, frustum)
+    } else {
+        (0..sections.len())
+            .filter(|&index| {
+                frustum.contains_box(sections[index].min, sections[index].max)
+            })
+            .collect()
+    };
+    let section_indices = visible_sections
+        .into_iter()
+        .filter(|&index| {
+            lod_scale(lod_distance, camera, sections[index].pos.xz()) == 1
+        })
+        .collect();
+    let lod_indices = (0..lods.len())
+        .filter(|&index| {
+            let lod = &lods[index];
+            lod_scale(lod_distance, camera, lod.chunk) == lod.scale
+                && frustum.contains_box(lod.min, lod.max)
+        })
+        .collect();
+    (section_indices, lod_indices)
+}
+
+/// How many blocks wide the cells of a chunk should be drawn, where 1
+/// means full detail.
+fn lod_scale(lod_distance: Option<f32>, camera: Vec3, chunk: IVec2) -> i32 {
+    let Some(lod_distance) = lod_distance else {
+        return 1;
+    };
+    let center = (chunk * 16 + 8).as_vec2();
+    let distance = camera.xz().distance(center);
+    std::iter::once(1)
+        .chain(LOD_SCALES)
+        .zip([1.0, 2.0, 4.0, f32::INFINITY])
+        .find(|&(_, limit)| distance < lod_distance * limit)
+        .map_or(1, |(scale, _)| scale)
+}
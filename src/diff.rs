@@ -0,0 +1,338 @@
+//! Compares two copies of a world, such as two backups, to find the blocks
+//! that were placed, broken or replaced between them.
+
+use crate::{
+    chunk::{Block, Chunk},
+    mesh::Highlights,
+    region,
+};
+use glam::{IVec2, IVec3, Vec3, Vec3Swizzles};
+use internment::Intern;
+use serde::{Deserialize, Serialize, Serializer};
+use std::{fmt::Write as _, fs, ops::Range, path::Path};
+
+/// The color of blocks that were placed since the older copy, or that
+/// replaced another block.
+pub const ADDED_COLOR: Vec3 = Vec3::new(0.2, 0.9, 0.2);
+/// The color of the boxes where blocks were broken.
+pub const REMOVED_COLOR: Vec3 = Vec3::new(0.9, 0.15, 0.1);
+
+/// The blocks that differ between an older and a newer copy of a world.
+#[derive(Default, Serialize)]
+pub struct WorldDiff {
+    /// How many chunks in the area are in both copies.
+    pub chunks_compared: u32,
+    /// How many of those have any blocks that changed.
+    pub chunks_changed: u32,
+    /// How many chunks are only in the newer copy, which aren't compared.
+    pub chunks_generated: u32,
+    /// How many chunks are only in the older copy, which aren't compared.
+    pub chunks_deleted: u32,
+    /// Every block that changed, from the bottom up.
+    pub changes: Vec<BlockChange>,
+}
+
+/// A block that isn't the same in both copies of a world.
+#[derive(Serialize)]
+pub struct BlockChange {
+    /// Where the block is.
+    pub pos: [i32; 3],
+    /// The block in the older copy.
+    #[serde(serialize_with = "serialize_block")]
+    pub before: Intern<Block>,
+    /// The block in the newer copy.
+    #[serde(serialize_with = "serialize_block")]
+    pub after: Intern<Block>,
+}
+
+fn serialize_block<S: Serializer>(
+    block: &Intern<Block>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&**block)
+}
+
+/// Just the last save time of a chunk, which is much faster to read than
+/// the whole chunk.
+#[derive(Deserialize)]
+struct ChunkSummary {
+    #[serde(default, rename = "LastUpdate")]
+    last_update: i64,
+}
+
+impl WorldDiff {
+    /// Compares the chunks of two world folders in an area.
+    ///
+    /// Chunks whose stored bytes are the same are skipped without being
+    /// read, and so are chunks with the same last save time, of which only
+    /// that time is read. Sections with the same palette and blocks are
+    /// skipped too, and only the rest are compared block by block.
+    pub fn compare(older: &Path, newer: &Path, area: &Range<IVec2>) -> Self {
+        let mut diff = Self::default();
+        let range = |start: i32, end: i32, size: i32| {
+            start.div_euclid(size)..=(end - 1).div_euclid(size)
+        };
+        for region_z in range(area.start.y, area.end.y, 512) {
+            for region_x in range(area.start.x, area.end.x, 512) {
+                let name = format!("r.{region_x}.{region_z}.mca");
+                let read = |world: &Path| {
+                    fs::read(world.join("region").join(&name)).ok()
+                };
+                let (older, newer) = (read(older), read(newer));
+                let region_area = IVec2::new(region_x, region_z) * 512
+                    ..IVec2::new(region_x + 1, region_z + 1) * 512;
+                let area = area.start.max(region_area.start)
+                    ..area.end.min(region_area.end);
+                for z in range(area.start.y, area.end.y, 16) {
+                    for x in range(area.start.x, area.end.x, 16) {
+                        match (
+                            older.as_deref().and_then(|file| {
+                                region::chunk_bytes(file, x, z)
+                            }),
+                            newer.as_deref().and_then(|file| {
+                                region::chunk_bytes(file, x, z)
+                            }),
+                        ) {
+                            (None, None) => {}
+                            (None, Some(_)) => diff.chunks_generated += 1,
+                            (Some(_), None) => diff.chunks_deleted += 1,
+                            (Some(older_bytes), Some(newer_bytes)) => {
+                                diff.chunks_compared += 1;
+                                if older_bytes == newer_bytes {
+                                    continue;
+                                }
+                                let last_update = |file: &Option<Vec<u8>>| {
+                                    let file = file.as_deref().unwrap();
+                                    region::read_chunk::<ChunkSummary>(
+                                        file, x, z,
+                                    )
+                                    .unwrap()
+                                    .last_update
+                                };
+                                // Chunks that weren't saved again since are
+                                // the same, but old chunks don't have a save
+                                // time
+                                let older_update = last_update(&older);
+                                if older_update != 0
+                                    && older_update == last_update(&newer)
+                                {
+                                    continue;
+                                }
+                                let read = |file: &Option<Vec<u8>>| {
+                                    let file = file.as_deref().unwrap();
+                                    region::read_chunk::<Chunk>(file, x, z)
+                                        .unwrap()
+                                };
+                                let change_count = diff.changes.len();
+                                diff.compare_chunks(
+                                    &read(&older),
+                                    &read(&newer),
+                                    IVec2::new(x, z),
+                                    &area,
+                                );
+                                if diff.changes.len() > change_count {
+                                    diff.chunks_changed += 1;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        diff.changes.sort_by_key(|change| {
+            let [x, y, z] = change.pos;
+            (y, z, x)
+        });
+        diff
+    }
+
+    fn compare_chunks(
+        &mut self,
+        older: &Chunk,
+        newer: &Chunk,
+        chunk_pos: IVec2,
+        area: &Range<IVec2>,
+    ) {
+        for older in &older.sections {
+            let section_y = i32::from(older.y);
+            let Some(newer) = newer.section(section_y) else {
+                continue;
+            };
+            let (older, newer) = (&older.block_states, &newer.block_states);
+            if older.palette == newer.palette && older.data == newer.data {
+                continue;
+            }
+            let pos = IVec3::new(chunk_pos.x, section_y, chunk_pos.y);
+            let min = (pos * 16).max(IVec3::new(
+                area.start.x,
+                i32::MIN,
+                area.start.y,
+            ));
+            let max = (pos * 16 + 16).min(IVec3::new(
+                area.end.x,
+                i32::MAX,
+                area.end.y,
+            ));
+            for y in min.y..max.y {
+                for z in min.z..max.z {
+                    for x in min.x..max.x {
+                        let p = IVec3 { x, y, z };
+                        let offset =
+                            (p - pos * 16).dot(IVec3::new(1, 256, 16)) as usize;
+                        let before =
+                            older.palette[usize::from(older.data[offset])];
+                        let after =
+                            newer.palette[usize::from(newer.data[offset])];
+                        // Air turning into cave air isn't a change
+                        if before != after
+                            && !(before.is_air() && after.is_air())
+                        {
+                            self.changes.push(BlockChange {
+                                pos: p.to_array(),
+                                before,
+                                after,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds a box around each changed block in the area, green where a
+    /// block was placed or replaced and red where one was broken.
+    pub fn add_highlights(
+        &self,
+        highlights: &mut Highlights,
+        area: &Range<IVec2>,
+    ) {
+        for change in &self.changes {
+            let pos = IVec3::from_array(change.pos);
+            if pos.xz().cmplt(area.start).any()
+                || pos.xz().cmpge(area.end).any()
+            {
+                continue;
+            }
+            let color = if change.after.is_air() {
+                REMOVED_COLOR
+            } else {
+                ADDED_COLOR
+            };
+            highlights.add_box(pos.as_vec3(), pos.as_vec3() + 1.0, color);
+        }
+    }
+
+    /// Saves the changes as JSON, or as a CSV table of the changed blocks if
+    /// the path ends in `.csv`.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let contents =
+            if path.extension().and_then(|extension| extension.to_str())
+                == Some("csv")
+            {
+                // Blocks with several properties have commas in them
+                let mut csv = "x,y,z,before,after\n".to_owned();
+                for change in &self.changes {
+                    let [x, y, z] = change.pos;
+                    writeln!(
+                        csv,
+                        "{x},{y},{z},\"{}\",\"{}\"",
+                        change.before, change.after
+                    )
+                    .unwrap();
+                }
+                csv
+            } else {
+                serde_json::to_string_pretty(self).unwrap()
+            };
+        fs::write(path, contents)
+            .map_err(|error| format!("{}: {error}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chunk::AIR, test_util};
+    use std::path::PathBuf;
+
+    /// A world whose chunks have the given blocks in their first section
+    /// above Y 0, where the first block is everywhere else.
+    fn world(name: &str, chunks: &[(IVec2, &[&str], &[u16], i64)]) -> PathBuf {
+        let directory = test_util::temp_dir(name);
+        let chunks: Vec<_> = chunks
+            .iter()
+            .map(|&(pos, palette, indices, last_update)| {
+                let block_states = test_util::block_states(palette, indices);
+                let mut chunk = test_util::chunk(&[(0, block_states)]);
+                chunk.insert("LastUpdate", last_update).unwrap();
+                (pos, chunk)
+            })
+            .collect();
+        test_util::write_region(&directory.join("region/r.0.0.mca"), &chunks);
+        directory
+    }
+
+    #[test]
+    fn finds_changed_blocks() {
+        let mut indices = vec![0; 4096];
+        let older = world(
+            "older",
+            &[
+                (IVec2::new(0, 0), &["minecraft:stone"], &[], 10),
+                (IVec2::new(1, 0), &["minecraft:stone"], &[], 10),
+                (IVec2::new(2, 0), &["minecraft:stone"], &[], 10),
+                (IVec2::new(4, 0), &["minecraft:stone"], &[], 10),
+            ],
+        );
+        // A block broken and another replaced in the first chunk
+        indices[1] = 1;
+        indices[256 + 16 + 3] = 2;
+        let newer = world(
+            "newer",
+            &[
+                (
+                    IVec2::new(0, 0),
+                    &["minecraft:stone", "minecraft:air", "minecraft:tnt"],
+                    &indices,
+                    20,
+                ),
+                // Saved again without changes
+                (IVec2::new(1, 0), &["minecraft:stone"], &[], 20),
+                // Different, but never saved since, so not read
+                (IVec2::new(2, 0), &["minecraft:dirt"], &[], 10),
+                (IVec2::new(3, 0), &["minecraft:stone"], &[], 20),
+            ],
+        );
+
+        let diff = WorldDiff::compare(
+            &older,
+            &newer,
+            &(IVec2::new(0, 0)..IVec2::new(80, 16)),
+        );
+        assert_eq!(diff.chunks_compared, 3);
+        assert_eq!(diff.chunks_changed, 1);
+        assert_eq!(diff.chunks_generated, 1);
+        assert_eq!(diff.chunks_deleted, 1);
+        let changes: Vec<_> = diff
+            .changes
+            .iter()
+            .map(|change| (change.pos, change.before, change.after))
+            .collect();
+        let stone = Block::parse("stone");
+        assert_eq!(
+            changes,
+            [
+                ([1, 0, 0], stone, *AIR),
+                ([3, 1, 1], stone, Block::parse("tnt")),
+            ]
+        );
+
+        let mut highlights = Highlights::default();
+        diff.add_highlights(
+            &mut highlights,
+            &(IVec2::new(2, 0)..IVec2::new(4, 4)),
+        );
+        assert_eq!(highlights.vertices.len(), 24);
+        assert_eq!(highlights.vertices[0].color, ADDED_COLOR);
+    }
+}
@@ -0,0 +1,318 @@
+use glam::{DVec3, Vec2};
+use serde::Deserialize;
+use std::{collections::HashMap, io::Cursor, path::Path};
+
+/// A chunk from a region file in the `entities` folder, which is where
+/// entities have been stored since 1.17.
+#[derive(Deserialize)]
+pub struct EntityChunk {
+    #[serde(default, rename = "Entities")]
+    pub entities: Vec<Entity>,
+}
+
+/// Anything in the world that isn't a block, such as a mob or an item
+/// frame.
+#[derive(Deserialize)]
+pub struct Entity {
+    /// The namespaced ID, such as `minecraft:item_frame`.
+    pub id: String,
+    #[serde(rename = "Pos")]
+    pos: [f64; 3],
+    #[serde(rename = "Rotation", default)]
+    rotation: [f32; 2],
+    /// Everything else, which depends on the kind of entity.
+    #[serde(flatten)]
+    pub data: HashMap<String, nbt::Value>,
+}
+
+impl Entity {
+    /// Where the entity is in the world.
+    pub fn pos(&self) -> DVec3 {
+        DVec3::from_array(self.pos)
+    }
+
+    /// Yaw and pitch in degrees.
+    pub fn rotation(&self) -> Vec2 {
+        Vec2::from_array(self.rotation)
+    }
+
+    /// The item held by an item frame.
+    pub fn item(&self) -> Option<Item<'_>> {
+        let Some(nbt::Value::Compound(item)) = self.data.get("Item") else {
+            return None;
+        };
+        Item::new(item)
+    }
+
+    /// The ID of the map in an item frame, if any.
+    pub fn map_id(&self) -> Option<i32> {
+        self.item()?.map_id()
+    }
+}
+
+/// An item stack stored as an NBT compound.
+pub struct Item<'a> {
+    /// The item ID without the namespace.
+    pub name: &'a str,
+    compound: &'a nbt::Map<String, nbt::Value>,
+}
+
+impl<'a> Item<'a> {
+    fn new(compound: &'a nbt::Map<String, nbt::Value>) -> Option<Self> {
+        let Some(nbt::Value::String(id)) = compound.get("id") else {
+            return None;
+        };
+        Some(Self {
+            name: id.strip_prefix("minecraft:").unwrap_or(id),
+            compound,
+        })
+    }
+
+    /// The ID of the map, if the item is a filled map.
+    pub fn map_id(&self) -> Option<i32> {
+        if self.name != "filled_map" {
+            return None;
+        }
+        // Since 1.20.5, item data is stored as components instead of a tag
+        let map_id =
+            match (self.compound.get("components"), self.compound.get("tag")) {
+                (Some(nbt::Value::Compound(components)), _) => {
+                    components.get("minecraft:map_id")
+                }
+                (_, Some(nbt::Value::Compound(tag))) => tag.get("map"),
+                _ => None,
+            };
+        nbt_int(map_id?)
+    }
+}
+
+/// The value of an integer NBT tag. Tags in flattened fields lose their
+/// type, so integers come back as the smallest type that fits them.
+pub fn nbt_int(value: &nbt::Value) -> Option<i32> {
+    match value {
+        nbt::Value::Byte(n) => Some(i32::from(*n)),
+        nbt::Value::Short(n) => Some(i32::from(*n)),
+        nbt::Value::Int(n) => Some(*n),
+        _ => None,
+    }
+}
+
+/// Which kinds of entities to render.
+#[derive(Clone, Copy)]
+pub struct EntityCategories {
+    /// Item frames and glow item frames, with the items or maps in them.
+    pub item_frames: bool,
+    /// Paintings on walls.
+    pub paintings: bool,
+    /// Armor stands and what they wear.
+    pub armor_stands: bool,
+    /// Every other entity, drawn as a box.
+    pub mobs: bool,
+}
+
+impl EntityCategories {
+    /// Every kind of entity.
+    pub const ALL: Self = Self {
+        item_frames: true,
+        paintings: true,
+        armor_stands: true,
+        mobs: true,
+    };
+
+    /// No entities at all.
+    pub const NONE: Self = Self {
+        item_frames: false,
+        paintings: false,
+        armor_stands: false,
+        mobs: false,
+    };
+
+    /// Parses a comma-separated list of categories, such as
+    /// `item_frames,paintings`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        s.split(',')
+            .filter(|category| !category.is_empty())
+            .try_fold(Self::NONE, |categories, category| {
+                Ok(match category {
+                    "all" => Self::ALL,
+                    "item_frames" => Self {
+                        item_frames: true,
+                        ..categories
+                    },
+                    "paintings" => Self {
+                        paintings: true,
+                        ..categories
+                    },
+                    "armor_stands" => Self {
+                        armor_stands: true,
+                        ..categories
+                    },
+                    "mobs" => Self {
+                        mobs: true,
+                        ..categories
+                    },
+                    _ => {
+                        return Err(format!(
+                            "unknown entity category `{category}`"
+                        ))
+                    }
+                })
+            })
+    }
+}
+
+/// The colors of a map item, stored in `data/map_<id>.dat`, as indices into
+/// the map color palette.
+pub struct MapColors(pub Box<[u8]>);
+
+impl MapColors {
+    /// The width and height of a map in pixels.
+    pub const SIZE: usize = 128;
+
+    /// Reads the colors of a map from a world's folder, if it has a map
+    /// with that ID.
+    pub fn load(world_path: &Path, id: i32) -> Option<Self> {
+        #[derive(Deserialize)]
+        struct MapFile {
+            data: MapData,
+        }
+
+        #[derive(Deserialize)]
+        struct MapData {
+            colors: Vec<i8>,
+        }
+
+        let file = std::fs::read(world_path.join(format!("data/map_{id}.dat")))
+            .ok()?;
+        let map: MapFile = nbt::from_gzip_reader(Cursor::new(file)).ok()?;
+        let colors = map.data.colors;
+        (colors.len() == Self::SIZE * Self::SIZE).then(|| {
+            Self(colors.into_iter().map(|color| color as u8).collect())
+        })
+    }
+
+    /// The RGBA color of the pixel at the given position.
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let color = self.0[y * Self::SIZE + x];
+        let base_color = usize::from(color / 4);
+        let Some(&[r, g, b]) =
+            MAP_BASE_COLORS.get(base_color).filter(|_| base_color != 0)
+        else {
+            return [0; 4];
+        };
+        let shade: u16 = [180, 220, 255, 135][usize::from(color % 4)];
+        let shaded = |channel: u8| (u16::from(channel) * shade / 255) as u8;
+        [shaded(r), shaded(g), shaded(b), 255]
+    }
+}
+
+const MAP_BASE_COLORS: [[u8; 3]; 62] = [
+    [0, 0, 0],
+    [127, 178, 56],
+    [247, 233, 163],
+    [199, 199, 199],
+    [255, 0, 0],
+    [160, 160, 255],
+    [167, 167, 167],
+    [0, 124, 0],
+    [255, 255, 255],
+    [164, 168, 184],
+    [151, 109, 77],
+    [112, 112, 112],
+    [64, 64, 255],
+    [143, 119, 72],
+    [255, 252, 245],
+    [216, 127, 51],
+    [178, 76, 216],
+    [102, 153, 216],
+    [229, 229, 51],
+    [127, 204, 25],
+    [242, 127, 165],
+    [76, 76, 76],
+    [153, 153, 153],
+    [76, 127, 153],
+    [127, 63, 178],
+    [51, 76, 178],
+    [102, 76, 51],
+    [102, 127, 51],
+    [153, 51, 51],
+    [25, 25, 25],
+    [250, 238, 77],
+    [92, 219, 213],
+    [74, 128, 255],
+    [0, 217, 58],
+    [129, 86, 49],
+    [112, 2, 0],
+    [209, 177, 161],
+    [159, 82, 36],
+    [149, 87, 108],
+    [112, 108, 138],
+    [186, 133, 36],
+    [103, 117, 53],
+    [160, 77, 78],
+    [57, 41, 35],
+    [135, 107, 98],
+    [87, 92, 92],
+    [122, 73, 88],
+    [76, 62, 92],
+    [76, 50, 35],
+    [76, 82, 42],
+    [142, 60, 46],
+    [37, 22, 16],
+    [189, 48, 49],
+    [148, 63, 97],
+    [92, 25, 29],
+    [22, 126, 134],
+    [58, 142, 140],
+    [86, 44, 62],
+    [20, 180, 133],
+    [100, 100, 100],
+    [216, 175, 147],
+    [127, 167, 150],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nbt::Value;
+
+    fn map(id: &str, key: &str, data: (&str, i32)) -> nbt::Map<String, Value> {
+        nbt::Map::from([
+            ("id".to_owned(), Value::String(id.to_owned())),
+            (
+                key.to_owned(),
+                Value::Compound(nbt::Map::from([(
+                    data.0.to_owned(),
+                    Value::Int(data.1),
+                )])),
+            ),
+        ])
+    }
+
+    #[test]
+    fn reads_map_ids_before_and_after_components() {
+        let map_id = |compound| Item::new(&compound)?.map_id();
+        assert_eq!(
+            map_id(map("minecraft:filled_map", "tag", ("map", 7))),
+            Some(7)
+        );
+        assert_eq!(
+            map_id(map(
+                "minecraft:filled_map",
+                "components",
+                ("minecraft:map_id", 8)
+            )),
+            Some(8)
+        );
+        assert_eq!(map_id(map("minecraft:map", "tag", ("map", 7))), None);
+    }
+
+    #[test]
+    fn parses_entity_categories() {
+        let categories = EntityCategories::parse("paintings,mobs").unwrap();
+        assert!(categories.paintings && categories.mobs);
+        assert!(!categories.item_frames && !categories.armor_stands);
+        assert!(EntityCategories::parse("all").unwrap().item_frames);
+        assert!(EntityCategories::parse("boats").is_err());
+    }
+}
@@ -0,0 +1,99 @@
+//! Renders and saves worlds without opening a window.
+
+mod data_maps;
+mod model;
+mod schematic;
+mod tile_map;
+mod timelapse;
+
+pub use data_maps::{export_data_maps, is_slime_chunk, DataMapExport};
+pub use model::{export_model, ModelExport};
+pub use schematic::{export_schematic, SchematicExport};
+pub use tile_map::{export_tile_map, TileMapExport};
+pub use timelapse::{export_timelapse, TimelapseExport};
+
+use crate::{
+    block_source::BlockSource,
+    camera::Camera,
+    camera_path::CameraPath,
+    mesh::Mesh,
+    raster::SoftwareRenderer,
+    sky::{Sky, SkyTextures},
+    texture::Atlas,
+    Options,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Where and how to save the frames of a flythrough.
+pub struct FrameExport {
+    /// The directory to save the frames to as numbered PNG files.
+    pub directory: PathBuf,
+    /// How many frames to render per second of the path.
+    pub fps: f32,
+}
+
+/// Renders every frame of a camera path without opening a window, saving
+/// them as numbered PNG files.
+pub fn export_frames(
+    world: &dyn BlockSource,
+    options: &Options,
+    camera_path: &CameraPath,
+    export: &FrameExport,
+) {
+    with_renderer(world, options, |renderer| {
+        fs::create_dir_all(&export.directory).unwrap();
+        let frame_count =
+            (camera_path.duration() * export.fps).floor() as u32 + 1;
+        let [width, height] = options.resolution;
+        for frame in 0..frame_count {
+            let camera = Camera {
+                projection: options.camera.projection,
+                ..camera_path.camera_at(frame as f32 / export.fps)
+            };
+            let image = renderer.render(&camera, width, height);
+            let path = export.directory.join(format!("frame_{frame:05}.png"));
+            image
+                .write_png(&path)
+                .unwrap_or_else(|error| panic!("{error}"));
+            eprintln!("Saved frame {}/{frame_count}", frame + 1);
+        }
+    });
+}
+
+/// Renders a single image from the camera in the options without opening
+/// a window.
+pub fn export_image(world: &dyn BlockSource, options: &Options, path: &Path) {
+    with_renderer(world, options, |renderer| {
+        let [width, height] = options.resolution;
+        renderer
+            .render(&options.camera, width, height)
+            .write_png(path)
+            .unwrap_or_else(|error| panic!("{error}"));
+    });
+}
+
+fn with_renderer(
+    world: &dyn BlockSource,
+    options: &Options,
+    f: impl FnOnce(&SoftwareRenderer),
+) {
+    let mesh = Mesh::build(world, options);
+    let atlas =
+        Atlas::build(&mesh.textures, &options.resource_pack_path, world.maps());
+    let sky = Sky::new(world.dimension(), options.day_time(world));
+    let sky_textures = SkyTextures::load(&options.resource_pack_path);
+    let renderer = SoftwareRenderer {
+        mesh: &mesh,
+        atlas: &atlas,
+        sky: &sky,
+        sky_textures: &sky_textures,
+        view_distance: options.view_distance(),
+        cave_culling: options.cave_culling,
+        lod_distance: options.lod_distance,
+        draw_sky: true,
+    };
+    f(&renderer);
+}
@@ -0,0 +1,357 @@
+use crate::{block_source::BlockSource, texture::Image, Options};
+use glam::{IVec2, IVec3};
+use pix::{gray::SGray16, Raster};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+/// The colors of common biomes in biome maps, close to those of other map
+/// tools. Other biomes get a color made from their name.
+const BIOME_COLORS: [(&str, [u8; 3]); 24] = [
+    ("minecraft:ocean", [0x00, 0x00, 0x70]),
+    ("minecraft:deep_ocean", [0x00, 0x00, 0x30]),
+    ("minecraft:warm_ocean", [0x00, 0x00, 0xac]),
+    ("minecraft:cold_ocean", [0x20, 0x20, 0x70]),
+    ("minecraft:frozen_ocean", [0x70, 0x70, 0xd6]),
+    ("minecraft:river", [0x00, 0x00, 0xff]),
+    ("minecraft:beach", [0xfa, 0xde, 0x55]),
+    ("minecraft:plains", [0x8d, 0xb3, 0x60]),
+    ("minecraft:sunflower_plains", [0xb5, 0xdb, 0x88]),
+    ("minecraft:desert", [0xfa, 0x94, 0x18]),
+    ("minecraft:forest", [0x05, 0x66, 0x21]),
+    ("minecraft:birch_forest", [0x30, 0x74, 0x44]),
+    ("minecraft:dark_forest", [0x40, 0x51, 0x1a]),
+    ("minecraft:taiga", [0x0b, 0x66, 0x59]),
+    ("minecraft:snowy_plains", [0xff, 0xff, 0xff]),
+    ("minecraft:swamp", [0x07, 0xf9, 0xb2]),
+    ("minecraft:jungle", [0x53, 0x7b, 0x09]),
+    ("minecraft:savanna", [0xbd, 0xb2, 0x5f]),
+    ("minecraft:badlands", [0xd9, 0x45, 0x15]),
+    ("minecraft:windswept_hills", [0x60, 0x60, 0x60]),
+    ("minecraft:mushroom_fields", [0xff, 0x00, 0xff]),
+    ("minecraft:nether_wastes", [0xbf, 0x3b, 0x3b]),
+    ("minecraft:the_end", [0x80, 0x80, 0xff]),
+    ("minecraft:the_void", [0x00, 0x00, 0x00]),
+];
+/// The color of slime chunks in their overlay, which is see-through.
+const SLIME_CHUNK_COLOR: [u8; 4] = [0x4c, 0xd9, 0x3a, 0x99];
+
+/// Where to save maps of the area's data.
+pub struct DataMapExport {
+    /// The directory to save the maps to.
+    pub directory: PathBuf,
+}
+
+/// Saves maps of the area seen from above, with one pixel per column of
+/// blocks and north up:
+///
+/// - `height.png`, a 16-bit grayscale image of one more than the height of
+///   the highest block above the bottom of the world, like Minecraft's
+///   heightmaps, with 0 where there are no blocks.
+/// - `biomes.png` with the biome at the surface, and `biomes.csv` saying
+///   which color is which biome.
+/// - `block_light.png` and `sky_light.png` with the light just above the
+///   surface.
+/// - `slime_chunks.png`, an overlay of the chunks where slimes spawn, if
+///   the world's seed is known.
+pub fn export_data_maps(
+    world: &dyn BlockSource,
+    options: &Options,
+    export: &DataMapExport,
+) {
+    let area = &options.area;
+    let size = (area.end - area.start).as_uvec2();
+    let columns = (area.start.y..area.end.y).flat_map(|z| {
+        (area.start.x..area.end.x).map(move |x| IVec2::new(x, z))
+    });
+    let surfaces: Vec<Option<IVec3>> = columns
+        .clone()
+        .map(|column| {
+            let (y, _block) = world.surface_at(column.x, column.y)?;
+            Some(IVec3::new(column.x, y, column.y))
+        })
+        .collect();
+    fs::create_dir_all(&export.directory).unwrap();
+    let save = |name: &str, image: Image| {
+        image
+            .write_png(&export.directory.join(name))
+            .unwrap_or_else(|error| panic!("{error}"));
+    };
+
+    let min_y = world.dimension().min_y();
+    let heights: Vec<u16> = surfaces
+        .iter()
+        .map(|surface| surface.map_or(0, |pos| (pos.y - min_y + 1) as u16))
+        .collect();
+    write_gray16_png(&export.directory.join("height.png"), size, &heights)
+        .unwrap_or_else(|error| panic!("{error}"));
+
+    let mut biome_counts = BTreeMap::new();
+    let biome_pixels = surfaces.iter().map(|surface| {
+        let biome = surface.and_then(|pos| world.biome_at(pos));
+        let Some(biome) = biome else {
+            return [0; 4];
+        };
+        *biome_counts.entry(biome.to_string()).or_insert(0) += 1;
+        let [r, g, b] = biome_color(&biome);
+        [r, g, b, 255]
+    });
+    let biomes = image(size, biome_pixels);
+    if biome_counts.is_empty() {
+        eprintln!("There are no biomes in the area");
+    } else {
+        save("biomes.png", biomes);
+        let mut legend = "biome,color,columns\n".to_owned();
+        for (biome, count) in &biome_counts {
+            let [r, g, b] = biome_color(biome);
+            writeln!(legend, "{biome},#{r:02x}{g:02x}{b:02x},{count}").unwrap();
+        }
+        let path = export.directory.join("biomes.csv");
+        fs::write(&path, legend)
+            .unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+    }
+
+    for (name, is_block_light) in
+        [("block_light.png", true), ("sky_light.png", false)]
+    {
+        let pixels = surfaces.iter().map(|surface| {
+            surface.map_or([0; 4], |pos| {
+                let pos = pos + IVec3::Y;
+                let light = if is_block_light {
+                    world.block_light_at(pos)
+                } else {
+                    world.sky_light_at(pos)
+                } * 17;
+                [light, light, light, 255]
+            })
+        });
+        save(name, image(size, pixels));
+    }
+
+    if let Some(seed) = world.seed() {
+        let pixels = columns.map(|column| {
+            if is_slime_chunk(seed, column >> 4_i32) {
+                SLIME_CHUNK_COLOR
+            } else {
+                [0; 4]
+            }
+        });
+        save("slime_chunks.png", image(size, pixels));
+    } else {
+        eprintln!("The seed isn't known, so slime chunks can't be found");
+    }
+}
+
+fn image(size: glam::UVec2, pixels: impl Iterator<Item = [u8; 4]>) -> Image {
+    Image {
+        width: size.x,
+        height: size.y,
+        pixels: pixels.flatten().collect(),
+    }
+}
+
+/// Saves values from 0 to 65535 as a grayscale PNG file.
+fn write_gray16_png(
+    path: &Path,
+    size: glam::UVec2,
+    values: &[u16],
+) -> Result<(), String> {
+    // PNG files store 16-bit values big-endian, and the encoder copies the
+    // pixels without swapping their bytes
+    let values: Vec<u16> = values.iter().map(|value| value.to_be()).collect();
+    let raster = Raster::<SGray16>::with_u16_buffer(size.x, size.y, values);
+    let file = File::create(path)
+        .map_err(|error| format!("{}: {error}", path.display()))?;
+    png_pong::Encoder::new(BufWriter::new(file))
+        .into_step_enc()
+        .still(&raster)
+        .map_err(|error| format!("{}: {error}", path.display()))
+}
+
+fn biome_color(biome: &str) -> [u8; 3] {
+    BIOME_COLORS
+        .iter()
+        .find(|(name, _)| *name == biome)
+        .map_or_else(
+            || {
+                // FNV-1a, so that the color is the same every time
+                let hash = biome.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+                    (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+                });
+                let [r, g, b, _] = hash.to_le_bytes();
+                [r, g, b]
+            },
+            |(_, color)| *color,
+        )
+}
+
+/// Whether slimes spawn underground in a chunk of a world generated from
+/// `seed`, the same way Minecraft decides.
+pub fn is_slime_chunk(seed: i64, chunk: IVec2) -> bool {
+    let (x, z) = (chunk.x, chunk.y);
+    // Some of the products overflow 32-bit integers, like in Java
+    let chunk_seed = seed
+        .wrapping_add(i64::from(x.wrapping_mul(x).wrapping_mul(0x4c1906)))
+        .wrapping_add(i64::from(x.wrapping_mul(0x5ac0db)))
+        .wrapping_add(i64::from(z.wrapping_mul(z)).wrapping_mul(0x4307a7))
+        .wrapping_add(i64::from(z.wrapping_mul(0x5f24f)))
+        ^ 0x3ad8025f;
+    JavaRandom::new(chunk_seed).next_int(10) == 0
+}
+
+/// The random number generator of `java.util.Random`.
+struct JavaRandom(i64);
+
+impl JavaRandom {
+    const MULTIPLIER: i64 = 0x5_deec_e66d;
+    const MASK: i64 = (1 << 48) - 1;
+
+    const fn new(seed: i64) -> Self {
+        Self((seed ^ Self::MULTIPLIER) & Self::MASK)
+    }
+
+    fn next(&mut self, bits: u32) -> i32 {
+        self.0 = self.0.wrapping_mul(Self::MULTIPLIER).wrapping_add(0xb)
+            & Self::MASK;
+        (self.0 >> (48 - bits)) as i32
+    }
+
+    /// A number from 0 up to `bound`, not included.
+    fn next_int(&mut self, bound: i32) -> i32 {
+        loop {
+            let bits = self.next(31);
+            let value = bits % bound;
+            // Rejects the last few numbers so that every value is as likely
+            if bits.wrapping_sub(value).wrapping_add(bound - 1) >= 0 {
+                return value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chunk::{Block, AIR},
+        test_util, Dimension,
+    };
+    use internment::Intern;
+    use pix::el::Pixel;
+    use std::ops::Range;
+
+    fn slime_chunks(
+        seed: i64,
+        xs: Range<i32>,
+        zs: Range<i32>,
+    ) -> Vec<(i32, i32)> {
+        zs.flat_map(|z| xs.clone().map(move |x| (x, z)))
+            .filter(|&(x, z)| is_slime_chunk(seed, IVec2::new(x, z)))
+            .collect()
+    }
+
+    #[test]
+    fn finds_slime_chunks() {
+        assert_eq!(
+            slime_chunks(12345, -4..4, -4..4),
+            [(-2, -4), (0, -2), (-4, 0), (3, 0), (-2, 1), (-1, 2)]
+        );
+        let seed = -4172144997902289642;
+        assert_eq!(
+            slime_chunks(seed, -4..4, -4..4),
+            [(-4, -4), (-3, -4), (1, 0), (3, 0), (1, 1), (-2, 3)]
+        );
+        // Far away, where the products overflow
+        assert_eq!(
+            slime_chunks(seed, 100_000..100_040, -100_000..-99_999),
+            [
+                (100_006, -100_000),
+                (100_018, -100_000),
+                (100_021, -100_000),
+                (100_034, -100_000),
+                (100_039, -100_000),
+            ]
+        );
+    }
+
+    /// A slope rising to the east from Y 0, with nothing in the last
+    /// column.
+    struct Slope(Dimension);
+
+    impl BlockSource for Slope {
+        fn block_at(&self, pos: IVec3) -> Option<Intern<Block>> {
+            (pos.x < 7).then(|| {
+                if (0..=pos.x * 10).contains(&pos.y) {
+                    Block::parse("stone")
+                } else {
+                    *AIR
+                }
+            })
+        }
+
+        fn dimension(&self) -> Dimension {
+            self.0
+        }
+    }
+
+    fn read_heights(path: &Path) -> Vec<u16> {
+        let file = File::open(path).unwrap();
+        let png_pong::PngRaster::Gray16(heights) = png_pong::Decoder::new(file)
+            .unwrap()
+            .into_steps()
+            .next()
+            .unwrap()
+            .unwrap()
+            .raster
+        else {
+            panic!("expected a 16-bit grayscale image");
+        };
+        heights
+            .pixels()
+            .iter()
+            .map(|pixel| u16::from_be(u16::from(pixel.one())))
+            .collect()
+    }
+
+    #[test]
+    fn saves_maps_of_the_area() {
+        let directory = test_util::temp_dir("data-maps");
+        let options = test_util::options(IVec2::new(0, 2)..IVec2::new(8, 5));
+        export_data_maps(
+            &Slope(Dimension::Overworld),
+            &options,
+            &DataMapExport {
+                directory: directory.clone(),
+            },
+        );
+        // Heights count from the bottom of the world at Y -64
+        let row = [65, 75, 85, 95, 105, 115, 125, 0];
+        assert_eq!(read_heights(&directory.join("height.png")), row.repeat(3));
+
+        let sky_light =
+            crate::texture::read_image(&directory.join("sky_light.png"))
+                .unwrap();
+        assert_eq!((sky_light.width, sky_light.height), (8, 3));
+        assert_eq!(sky_light.pixels[..4], [255; 4]);
+        assert_eq!(sky_light.pixels[28..32], [0; 4]);
+        // Without biomes or a seed there is nothing else to show
+        assert!(!directory.join("biomes.png").exists());
+        assert!(!directory.join("slime_chunks.png").exists());
+
+        // The Nether's bottom is at Y 0
+        let directory = test_util::temp_dir("data-maps-nether");
+        export_data_maps(
+            &Slope(Dimension::Nether),
+            &options,
+            &DataMapExport {
+                directory: directory.clone(),
+            },
+        );
+        let row = [1, 11, 21, 31, 41, 51, 61, 0];
+        assert_eq!(read_heights(&directory.join("height.png")), row.repeat(3));
+    }
+}
@@ -0,0 +1,535 @@
+use crate::{
+    block_source::BlockSource,
+    chunk::Block,
+    mesh::Mesh,
+    texture::{Atlas, Image, TILE_SIZE},
+    Options,
+};
+use glam::{Vec2, Vec3};
+use internment::Intern;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// Where and how to save the area as a 3D model.
+pub struct ModelExport {
+    /// A `.glb` file, or an `.obj` file which is saved along with an `.mtl`
+    /// file and the texture atlas as a `.png` of the same name.
+    pub path: PathBuf,
+    /// Whether to make a separate object for each kind of block.
+    pub split_by_block: bool,
+}
+
+/// How the transparency of a texture has to be handled.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Material {
+    Opaque,
+    /// Every pixel is either fully opaque or fully transparent.
+    Cutout,
+    Translucent,
+}
+
+impl Material {
+    const ALL: [Self; 3] = [Self::Opaque, Self::Cutout, Self::Translucent];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Opaque => "opaque",
+            Self::Cutout => "cutout",
+            Self::Translucent => "translucent",
+        }
+    }
+
+    /// The material needed by each tile of the atlas.
+    fn of_tiles(atlas: &Atlas, tile_count: usize) -> Vec<Self> {
+        let columns = atlas.width as usize / TILE_SIZE;
+        (0..tile_count)
+            .map(|index| {
+                let (x, y) = (index % columns, index / columns);
+                let alphas = (0..TILE_SIZE).flat_map(|row| {
+                    let start = ((y * TILE_SIZE + row) * atlas.width as usize
+                        + x * TILE_SIZE)
+                        * 4;
+                    atlas.pixels[start..][..TILE_SIZE * 4]
+                        .iter()
+                        .skip(3)
+                        .step_by(4)
+                });
+                alphas.fold(Self::Opaque, |material, &alpha| match alpha {
+                    255 => material,
+                    0 => material.max(Self::Cutout),
+                    _ => Self::Translucent,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The triangles of an object that use one material, with their own copy
+/// of the vertices they use.
+#[derive(Default)]
+struct Group {
+    positions: Vec<Vec3>,
+    /// Coordinates in the whole atlas.
+    uvs: Vec<Vec2>,
+    /// The light level of each vertex as a shade of grey.
+    colors: Vec<Vec3>,
+    indices: Vec<u32>,
+    /// The index in the group of each of the mesh's vertices that is used.
+    mesh_indices: HashMap<u32, u32>,
+}
+
+impl Group {
+    fn add_vertex(&mut self, mesh: &Mesh, index: u32, atlas_size: Vec2) {
+        let group_index =
+            *self.mesh_indices.entry(index).or_insert_with(|| {
+                let vertex = &mesh.vertices[index as usize];
+                let texture_index = vertex.texture_index as u32;
+                let tile = Vec2::new(
+                    (texture_index % atlas_size.x as u32) as f32,
+                    (texture_index / atlas_size.x as u32) as f32,
+                );
+                self.positions.push(vertex.pos);
+                self.uvs.push(
+                    (tile + vertex.uv.clamp(Vec2::ZERO, Vec2::ONE))
+                        / atlas_size,
+                );
+                self.colors.push(Vec3::splat(vertex.light_level));
+                self.positions.len() as u32 - 1
+            });
+        self.indices.push(group_index);
+    }
+}
+
+/// Saves the mesh of the area as a glTF or OBJ model, depending on the
+/// file extension.
+pub fn export_model(
+    world: &dyn BlockSource,
+    options: &Options,
+    export: &ModelExport,
+) {
+    let mesh = Mesh::build(world, options);
+    let atlas =
+        Atlas::build(&mesh.textures, &options.resource_pack_path, world.maps());
+    let materials = Material::of_tiles(&atlas, mesh.textures.len());
+    let atlas_size =
+        Vec2::new(atlas.width as f32, atlas.height as f32) / TILE_SIZE as f32;
+
+    let mut triangle_blocks: Vec<Option<Intern<Block>>> =
+        vec![None; mesh.indices.len() / 3];
+    if export.split_by_block {
+        for (block, indices) in &mesh.blocks {
+            triangle_blocks[indices.start / 3..indices.end / 3]
+                .fill(Some(*block));
+        }
+    }
+
+    // Grouped by object name and then by material
+    let mut groups: BTreeMap<(String, Material), Group> = BTreeMap::new();
+    for section in &mesh.sections {
+        for triangle in section.indices.clone().step_by(3) {
+            let object = match triangle_blocks[triangle / 3] {
+                Some(block) => block
+                    .name
+                    .strip_prefix("minecraft:")
+                    .unwrap_or(&block.name)
+                    .to_owned(),
+                None if export.split_by_block => "entities".to_owned(),
+                None => "area".to_owned(),
+            };
+            let indices = &mesh.indices[triangle..][..3];
+            let texture_index =
+                mesh.vertices[indices[0] as usize].texture_index as usize;
+            let group = groups
+                .entry((object, materials[texture_index]))
+                .or_default();
+            for &index in indices {
+                group.add_vertex(&mesh, index, atlas_size);
+            }
+        }
+    }
+
+    let atlas = Image {
+        width: atlas.width,
+        height: atlas.height,
+        pixels: atlas.pixels.into(),
+    };
+    let path = &export.path;
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("glb") => write_glb(path, &groups, &atlas),
+        Some("obj") => write_obj(path, &groups, &atlas),
+        _ => panic!("models can only be exported as `.glb` or `.obj` files"),
+    }
+    .unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+}
+
+fn write_obj(
+    path: &Path,
+    groups: &BTreeMap<(String, Material), Group>,
+    atlas: &Image,
+) -> io::Result<()> {
+    let mtl_path = path.with_extension("mtl");
+    let atlas_path = path.with_extension("png");
+    let file_name =
+        |path: &Path| path.file_name().unwrap().to_string_lossy().into_owned();
+
+    let mut obj = BufWriter::new(File::create(path)?);
+    writeln!(obj, "mtllib {}", file_name(&mtl_path))?;
+    let mut vertex_count = 0;
+    let mut last_object = None;
+    for ((object, material), group) in groups {
+        if last_object != Some(object) {
+            writeln!(obj, "o {object}")?;
+            last_object = Some(object);
+        }
+        writeln!(obj, "usemtl {}", material.name())?;
+        // Vertex colors after the position are an extension that Blender
+        // and most other programs understand
+        for (pos, color) in group.positions.iter().zip(&group.colors) {
+            writeln!(
+                obj,
+                "v {} {} {} {} {} {}",
+                pos.x, pos.y, pos.z, color.x, color.y, color.z
+            )?;
+        }
+        // OBJ texture coordinates start at the bottom
+        for uv in &group.uvs {
+            writeln!(obj, "vt {} {}", uv.x, 1.0 - uv.y)?;
+        }
+        for triangle in group.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| triangle[i] + vertex_count + 1);
+            writeln!(obj, "f {a}/{a} {b}/{b} {c}/{c}")?;
+        }
+        vertex_count += group.positions.len() as u32;
+    }
+    obj.flush()?;
+
+    let mut mtl = BufWriter::new(File::create(&mtl_path)?);
+    for material in Material::ALL {
+        writeln!(mtl, "newmtl {}", material.name())?;
+        writeln!(mtl, "Kd 1 1 1")?;
+        writeln!(mtl, "map_Kd {}", file_name(&atlas_path))?;
+        if material != Material::Opaque {
+            writeln!(mtl, "map_d {}", file_name(&atlas_path))?;
+        }
+        writeln!(mtl)?;
+    }
+    mtl.flush()?;
+
+    atlas.write_png(&atlas_path).map_err(io::Error::other)
+}
+
+/// The binary buffer of a glTF file and the views and accessors that
+/// describe its contents.
+#[derive(Default)]
+struct GltfBuffer {
+    bytes: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl GltfBuffer {
+    const ARRAY_BUFFER: u32 = 34962;
+    const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+    const FLOAT: u32 = 5126;
+    const UNSIGNED_INT: u32 = 5125;
+
+    fn view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        // Every view starts at a multiple of 4 bytes
+        self.bytes.resize(self.bytes.len().next_multiple_of(4), 0);
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": self.bytes.len(),
+            "byteLength": bytes.len(),
+        });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        self.bytes.extend_from_slice(bytes);
+        self.views.push(view);
+        self.views.len() - 1
+    }
+
+    fn floats<const N: usize>(&mut self, values: &[[f32; N]]) -> usize {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let view = self.view(&bytes, Some(Self::ARRAY_BUFFER));
+        let mut min = [f32::INFINITY; N];
+        let mut max = [f32::NEG_INFINITY; N];
+        for value in values {
+            for (i, &component) in value.iter().enumerate() {
+                min[i] = min[i].min(component);
+                max[i] = max[i].max(component);
+            }
+        }
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": Self::FLOAT,
+            "count": values.len(),
+            "type": format!("VEC{N}"),
+            "min": &min[..],
+            "max": &max[..],
+        }));
+        self.accessors.len() - 1
+    }
+
+    fn indices(&mut self, indices: &[u32]) -> usize {
+        let bytes: Vec<u8> = indices
+            .iter()
+            .flat_map(|index| index.to_le_bytes())
+            .collect();
+        let view = self.view(&bytes, Some(Self::ELEMENT_ARRAY_BUFFER));
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": Self::UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }));
+        self.accessors.len() - 1
+    }
+}
+
+fn write_glb(
+    path: &Path,
+    groups: &BTreeMap<(String, Material), Group>,
+    atlas: &Image,
+) -> io::Result<()> {
+    let mut buffer = GltfBuffer::default();
+
+    let mut meshes: Vec<Value> = Vec::new();
+    let mut last_object = None;
+    for ((object, material), group) in groups {
+        if last_object != Some(object) {
+            meshes.push(json!({ "name": object, "primitives": [] }));
+            last_object = Some(object);
+        }
+        let positions: Vec<[f32; 3]> =
+            group.positions.iter().map(|pos| pos.to_array()).collect();
+        let uvs: Vec<[f32; 2]> =
+            group.uvs.iter().map(|uv| uv.to_array()).collect();
+        let colors: Vec<[f32; 3]> =
+            group.colors.iter().map(|color| color.to_array()).collect();
+        let primitive = json!({
+            "attributes": {
+                "POSITION": buffer.floats(&positions),
+                "TEXCOORD_0": buffer.floats(&uvs),
+                "COLOR_0": buffer.floats(&colors),
+            },
+            "indices": buffer.indices(&group.indices),
+            "material": Material::ALL
+                .iter()
+                .position(|other| other == material)
+                .unwrap(),
+        });
+        meshes.last_mut().unwrap()["primitives"]
+            .as_array_mut()
+            .unwrap()
+            .push(primitive);
+    }
+
+    let mut png = Vec::new();
+    atlas.encode_png(&mut png).map_err(io::Error::other)?;
+    let image_view = buffer.view(&png, None);
+
+    let materials: Vec<Value> = Material::ALL
+        .iter()
+        .map(|material| {
+            let mut value = json!({
+                "name": material.name(),
+                "pbrMetallicRoughness": {
+                    "baseColorTexture": { "index": 0 },
+                    "metallicFactor": 0.0,
+                    "roughnessFactor": 1.0,
+                },
+            });
+            match material {
+                Material::Opaque => value["alphaMode"] = json!("OPAQUE"),
+                Material::Cutout => {
+                    value["alphaMode"] = json!("MASK");
+                    value["alphaCutoff"] = json!(0.5);
+                }
+                Material::Translucent => value["alphaMode"] = json!("BLEND"),
+            }
+            value
+        })
+        .collect();
+    let nodes: Vec<Value> = meshes
+        .iter()
+        .enumerate()
+        .map(|(index, mesh)| json!({ "name": mesh["name"], "mesh": index }))
+        .collect();
+
+    const NEAREST: u32 = 9728;
+    const CLAMP_TO_EDGE: u32 = 33071;
+    let mut gltf = json!({
+        "asset": { "version": "2.0", "generator": "mcrender" },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }],
+        "nodes": nodes,
+        "meshes": meshes,
+        "materials": materials,
+        "textures": [{ "sampler": 0, "source": 0 }],
+        "samplers": [{
+            "magFilter": NEAREST,
+            "minFilter": NEAREST,
+            "wrapS": CLAMP_TO_EDGE,
+            "wrapT": CLAMP_TO_EDGE,
+        }],
+        "images": [{ "bufferView": image_view, "mimeType": "image/png" }],
+        "accessors": buffer.accessors,
+        "bufferViews": buffer.views,
+        "buffers": [{ "byteLength": buffer.bytes.len() }],
+    });
+    // glTF doesn't allow empty arrays, which an empty area would leave
+    let is_empty = |value: &Value| value.as_array().is_some_and(Vec::is_empty);
+    gltf["scenes"][0]
+        .as_object_mut()
+        .unwrap()
+        .retain(|_, value| !is_empty(value));
+    gltf.as_object_mut()
+        .unwrap()
+        .retain(|_, value| !is_empty(value));
+
+    // Both chunks of the file are padded to a multiple of 4 bytes
+    let mut json = serde_json::to_vec(&gltf)?;
+    json.resize(json.len().next_multiple_of(4), b' ');
+    let mut bytes = buffer.bytes;
+    bytes.resize(bytes.len().next_multiple_of(4), 0);
+
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(b"glTF")?;
+    file.write_all(&2u32.to_le_bytes())?;
+    file.write_all(
+        &(12 + 8 + json.len() as u32 + 8 + bytes.len() as u32).to_le_bytes(),
+    )?;
+    file.write_all(&(json.len() as u32).to_le_bytes())?;
+    file.write_all(b"JSON")?;
+    file.write_all(&json)?;
+    file.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    file.write_all(b"BIN\0")?;
+    file.write_all(&bytes)?;
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block_source::Volume, test_util};
+    use glam::{IVec2, IVec3};
+    use std::fs;
+
+    /// Exports a row of blocks with air between them.
+    fn export(file_name: &str, blocks: &[&str]) -> PathBuf {
+        let mut volume = Volume::new(IVec3::new(8, 1, 8));
+        for (i, block) in blocks.iter().enumerate() {
+            volume
+                .set_block(IVec3::new(i as i32 * 2, 0, 1), Block::parse(block));
+        }
+        let mut options = test_util::options(IVec2::ZERO..IVec2::splat(8));
+        options.resource_pack_path = PathBuf::from("tests/resource-pack");
+        let path = test_util::temp_dir("model").join(file_name);
+        export_model(
+            &volume,
+            &options,
+            &ModelExport {
+                path: path.clone(),
+                split_by_block: false,
+            },
+        );
+        path
+    }
+
+    /// Blocks that are opaque, cutout and translucent.
+    const BLOCKS: [&str; 3] = ["stone", "glass", "blue_stained_glass"];
+
+    /// The JSON and binary chunks of a GLB file, after checking its header.
+    fn read_glb(path: &Path) -> (Value, Vec<u8>) {
+        let file = fs::read(path).unwrap();
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes(file[offset..offset + 4].try_into().unwrap())
+                as usize
+        };
+        assert_eq!(&file[..4], b"glTF");
+        assert_eq!(u32_at(4), 2);
+        assert_eq!(u32_at(8), file.len());
+        let json_length = u32_at(12);
+        assert_eq!(&file[16..20], b"JSON");
+        assert_eq!(json_length % 4, 0);
+        let bin_start = 20 + json_length;
+        let bin_length = u32_at(bin_start);
+        assert_eq!(&file[bin_start + 4..bin_start + 8], b"BIN\0");
+        assert_eq!(bin_length % 4, 0);
+        assert_eq!(bin_start + 8 + bin_length, file.len());
+        let json = serde_json::from_slice(&file[20..bin_start]).unwrap();
+        (json, file[bin_start + 8..].to_vec())
+    }
+
+    #[test]
+    fn saves_glb_files() {
+        let (json, bin) = read_glb(&export("area.glb", &BLOCKS));
+        assert!(
+            json["buffers"][0]["byteLength"].as_u64().unwrap() as usize
+                <= bin.len()
+        );
+        for view in json["bufferViews"].as_array().unwrap() {
+            assert_eq!(view["byteOffset"].as_u64().unwrap() % 4, 0);
+        }
+        let primitives = json["meshes"][0]["primitives"].as_array().unwrap();
+        let materials: Vec<&str> = primitives
+            .iter()
+            .map(|primitive| {
+                let index = primitive["material"].as_u64().unwrap() as usize;
+                json["materials"][index]["alphaMode"].as_str().unwrap()
+            })
+            .collect();
+        assert_eq!(materials, ["OPAQUE", "MASK", "BLEND"]);
+        // Each cube has 6 faces of 4 corners and 2 triangles
+        for primitive in primitives {
+            let count = |index: &Value| {
+                json["accessors"][index.as_u64().unwrap() as usize]["count"]
+                    .as_u64()
+                    .unwrap()
+            };
+            let attributes = &primitive["attributes"];
+            assert_eq!(count(&attributes["POSITION"]), 24);
+            assert_eq!(count(&attributes["TEXCOORD_0"]), 24);
+            assert_eq!(count(&primitive["indices"]), 36);
+        }
+    }
+
+    #[test]
+    fn leaves_empty_arrays_out_of_glb_files() {
+        let (json, _) = read_glb(&export("empty.glb", &[]));
+        for key in ["meshes", "nodes", "accessors"] {
+            assert!(json.get(key).is_none(), "{key}");
+        }
+        assert!(json["scenes"][0].get("nodes").is_none());
+        assert_eq!(json["bufferViews"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn saves_obj_files() {
+        let path = export("area.obj", &BLOCKS);
+        let obj = fs::read_to_string(&path).unwrap();
+        let count = |prefix: &str| {
+            obj.lines().filter(|line| line.starts_with(prefix)).count()
+        };
+        assert_eq!(count("v "), 3 * 24);
+        assert_eq!(count("vt "), 3 * 24);
+        assert_eq!(count("f "), 3 * 12);
+        let materials: Vec<&str> = obj
+            .lines()
+            .filter_map(|line| line.strip_prefix("usemtl "))
+            .collect();
+        assert_eq!(materials, ["opaque", "cutout", "translucent"]);
+        assert!(path.with_extension("mtl").exists());
+        assert!(path.with_extension("png").exists());
+    }
+}
@@ -0,0 +1,318 @@
+use crate::{
+    block_source::BlockSource,
+    chunk::{Block, AIR},
+};
+use glam::IVec3;
+use indexmap::IndexSet;
+use internment::Intern;
+use nbt::{Blob, Map, Value};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The data version written when no chunks were loaded, which is
+/// Minecraft 1.18.2.
+const DEFAULT_DATA_VERSION: i32 = 2975;
+/// The first data version whose items Litematica saves in its version 6
+/// format, which is Minecraft 1.20.5.
+const LITEMATICA_V6_DATA_VERSION: i32 = 3837;
+
+/// Where to save which blocks as a schematic.
+pub struct SchematicExport {
+    /// A Sponge `.schem` or Litematica `.litematic` file.
+    pub path: PathBuf,
+    /// The corner of the box to save with the lowest coordinates.
+    pub min: IVec3,
+    /// The opposite corner, which is also saved.
+    pub max: IVec3,
+}
+
+/// The blocks in a box, in the order Y, Z, X from the slowest to the
+/// fastest changing, as indices into a palette which starts with air.
+struct Selection<'a> {
+    size: IVec3,
+    palette: IndexSet<Intern<Block>>,
+    blocks: Vec<usize>,
+    /// Block entities and their positions relative to the box.
+    block_entities: Vec<(IVec3, &'a Map<String, Value>)>,
+}
+
+impl<'a> Selection<'a> {
+    fn read(world: &'a dyn BlockSource, min: IVec3, max: IVec3) -> Self {
+        let mut palette = IndexSet::from([*AIR]);
+        let mut blocks = Vec::new();
+        let mut block_entities = Vec::new();
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    let pos = IVec3::new(x, y, z);
+                    // Chunks that aren't loaded are left empty
+                    let block = world.block_at(pos).unwrap_or(*AIR);
+                    blocks.push(palette.insert_full(block).0);
+                    if let Some(block_entity) = world.block_entity_at(pos) {
+                        block_entities.push((pos - min, &block_entity.data));
+                    }
+                }
+            }
+        }
+        Self {
+            size: max - min + 1,
+            palette,
+            blocks,
+            block_entities,
+        }
+    }
+}
+
+/// Saves the blocks and block entities in a box as a schematic file.
+pub fn export_schematic(world: &dyn BlockSource, export: &SchematicExport) {
+    let path = &export.path;
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    let size = export.max - export.min + 1;
+    // Sponge schematics store their size as unsigned shorts
+    if extension == Some("schem") && size.max_element() > i32::from(u16::MAX) {
+        panic!(
+            "`.schem` files can be at most {} blocks wide, long and high",
+            u16::MAX
+        );
+    }
+
+    let selection = Selection::read(world, export.min, export.max);
+    let data_version = world.data_version().unwrap_or(DEFAULT_DATA_VERSION);
+    let name = export
+        .path
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+
+    let blob = match extension {
+        Some("schem") => sponge_schematic(&selection, data_version, export.min),
+        Some("litematic") => litematic(&selection, data_version, name),
+        _ => panic!(
+            "schematics can only be exported as `.schem` or `.litematic` files"
+        ),
+    };
+    let file = File::create(path)
+        .unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+    blob.to_gzip_writer(&mut BufWriter::new(file))
+        .unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+}
+
+/// Version 3 of the Sponge schematic format, which WorldEdit uses.
+fn sponge_schematic(
+    selection: &Selection,
+    data_version: i32,
+    offset: IVec3,
+) -> Blob {
+    // Palette indices are stored as variable length integers, 7 bits at a
+    // time starting with the lowest
+    let mut data = Vec::new();
+    for &index in &selection.blocks {
+        let mut index = index;
+        while index >= 0x80 {
+            data.push((index & 0x7f | 0x80) as u8 as i8);
+            index >>= 7;
+        }
+        data.push(index as u8 as i8);
+    }
+
+    let palette = selection
+        .palette
+        .iter()
+        .enumerate()
+        .map(|(index, block)| (block.to_string(), Value::Int(index as i32)))
+        .collect();
+    let block_entities = selection
+        .block_entities
+        .iter()
+        .map(|(pos, data)| {
+            let mut data = (*data).clone();
+            let id = data.remove("id").unwrap_or(Value::String(String::new()));
+            compound([
+                ("Pos", Value::IntArray(pos.to_array().to_vec())),
+                ("Id", id),
+                ("Data", Value::Compound(data)),
+            ])
+        })
+        .collect();
+
+    let size = selection.size;
+    let schematic = compound([
+        ("Version", Value::Int(3)),
+        ("DataVersion", Value::Int(data_version)),
+        ("Width", Value::Short(size.x as u16 as i16)),
+        ("Height", Value::Short(size.y as u16 as i16)),
+        ("Length", Value::Short(size.z as u16 as i16)),
+        ("Offset", Value::IntArray(offset.to_array().to_vec())),
+        (
+            "Blocks",
+            compound([
+                ("Palette", Value::Compound(palette)),
+                ("Data", Value::ByteArray(data)),
+                ("BlockEntities", Value::List(block_entities)),
+            ]),
+        ),
+    ]);
+    let mut blob = Blob::new();
+    blob.insert("Schematic", schematic).unwrap();
+    blob
+}
+
+/// The format of the Litematica mod, with the whole box as one region.
+fn litematic(selection: &Selection, data_version: i32, name: String) -> Blob {
+    // Indices are packed into longs with as few bits as possible, and can
+    // span two longs
+    let bits = (usize::BITS - (selection.palette.len() - 1).leading_zeros())
+        .max(2) as usize;
+    let mut block_states =
+        vec![0u64; (selection.blocks.len() * bits).div_ceil(64)];
+    for (i, &index) in selection.blocks.iter().enumerate() {
+        let (long, offset) = (i * bits / 64, i * bits % 64);
+        block_states[long] |= (index as u64) << offset;
+        if offset + bits > 64 {
+            block_states[long + 1] |= (index as u64) >> (64 - offset);
+        }
+    }
+
+    let palette = selection
+        .palette
+        .iter()
+        .map(|block| {
+            let mut entry = Map::from([(
+                "Name".to_owned(),
+                Value::String(block.name.to_string()),
+            )]);
+            if !block.properties.is_empty() {
+                let properties = block
+                    .properties
+                    .iter()
+                    .map(|(key, value)| {
+                        (key.clone(), Value::String(value.clone()))
+                    })
+                    .collect();
+                entry.insert(
+                    "Properties".to_owned(),
+                    Value::Compound(properties),
+                );
+            }
+            Value::Compound(entry)
+        })
+        .collect();
+    let tile_entities = selection
+        .block_entities
+        .iter()
+        .map(|(pos, data)| {
+            let mut data = (*data).clone();
+            data.insert("x".to_owned(), Value::Int(pos.x));
+            data.insert("y".to_owned(), Value::Int(pos.y));
+            data.insert("z".to_owned(), Value::Int(pos.z));
+            Value::Compound(data)
+        })
+        .collect();
+    let vector = |v: IVec3| {
+        compound([
+            ("x", Value::Int(v.x)),
+            ("y", Value::Int(v.y)),
+            ("z", Value::Int(v.z)),
+        ])
+    };
+
+    let region = compound([
+        ("Position", vector(IVec3::ZERO)),
+        ("Size", vector(selection.size)),
+        ("BlockStatePalette", Value::List(palette)),
+        (
+            "BlockStates",
+            Value::LongArray(
+                block_states.into_iter().map(|long| long as i64).collect(),
+            ),
+        ),
+        ("TileEntities", Value::List(tile_entities)),
+        ("Entities", Value::List(Vec::new())),
+        ("PendingBlockTicks", Value::List(Vec::new())),
+        ("PendingFluidTicks", Value::List(Vec::new())),
+    ]);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+    let total_blocks =
+        selection.blocks.iter().filter(|&&index| index != 0).count();
+    let metadata = compound([
+        ("Name", Value::String(name.clone())),
+        ("Author", Value::String(String::new())),
+        ("Description", Value::String(String::new())),
+        ("RegionCount", Value::Int(1)),
+        ("TotalBlocks", Value::Int(total_blocks as i32)),
+        ("TotalVolume", Value::Int(selection.blocks.len() as i32)),
+        ("TimeCreated", Value::Long(now)),
+        ("TimeModified", Value::Long(now)),
+        ("EnclosingSize", vector(selection.size)),
+    ]);
+
+    let mut blob = Blob::new();
+    let version = if data_version >= LITEMATICA_V6_DATA_VERSION {
+        6
+    } else {
+        5
+    };
+    blob.insert("Version", Value::Int(version)).unwrap();
+    blob.insert("SubVersion", Value::Int(1)).unwrap();
+    blob.insert("MinecraftDataVersion", Value::Int(data_version))
+        .unwrap();
+    blob.insert("Metadata", metadata).unwrap();
+    blob.insert("Regions", compound([(name.as_str(), region)]))
+        .unwrap();
+    blob
+}
+
+fn compound<const N: usize>(entries: [(&str, Value); N]) -> Value {
+    Value::Compound(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block_source::Volume, schematic::Schematic, test_util};
+
+    #[test]
+    fn saves_boxes_wider_than_a_short() {
+        let mut volume = Volume::new(IVec3::new(40_000, 1, 1));
+        volume.set_block(IVec3::new(39_999, 0, 0), Block::parse("stone"));
+        let path = test_util::temp_dir("schematic").join("wide.schem");
+        export_schematic(
+            &volume,
+            &SchematicExport {
+                path: path.clone(),
+                min: IVec3::ZERO,
+                max: IVec3::new(39_999, 0, 0),
+            },
+        );
+        let schematic = Schematic::load(&path);
+        assert_eq!(
+            schematic.block_at(IVec3::new(39_999, 0, 0)),
+            Some(Block::parse("stone"))
+        );
+        assert_eq!(schematic.block_at(IVec3::new(40_000, 0, 0)), None);
+    }
+
+    #[test]
+    #[should_panic = "at most 65535 blocks"]
+    fn refuses_boxes_too_big_for_sponge_schematics() {
+        export_schematic(
+            &Volume::new(IVec3::ONE),
+            &SchematicExport {
+                path: PathBuf::from("too_wide.schem"),
+                min: IVec3::ZERO,
+                max: IVec3::new(70_000, 0, 0),
+            },
+        );
+    }
+}
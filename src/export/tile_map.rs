@@ -0,0 +1,201 @@
+use super::with_renderer;
+use crate::{
+    block_source::BlockSource,
+    camera,
+    raster::SoftwareRenderer,
+    texture::{read_image, Image},
+    Options,
+};
+use glam::{IVec2, Vec2, Vec3};
+use std::{collections::HashSet, fs, path::PathBuf};
+
+/// The width and height of each tile in pixels.
+const TILE_SIZE: u32 = 256;
+
+/// Where to save a tile map.
+pub struct TileMapExport {
+    /// The directory to save the zoom levels of tiles to.
+    pub directory: PathBuf,
+}
+
+/// Renders the area as seen by the orthographic camera in the options as a
+/// pyramid of tiles, saved as `<zoom>/<x>/<y>.png` along with an
+/// `index.html` showing them as a zoomable map.
+///
+/// The most zoomed in tiles show as many blocks as the camera's view, and
+/// each zoom level below combines 4 tiles into one until a single tile
+/// shows everything. Every tile is a cropped part of the same projection,
+/// so neighbouring tiles line up exactly.
+pub fn export_tile_map(
+    world: &dyn BlockSource,
+    options: &Options,
+    export: &TileMapExport,
+) {
+    let camera = options.camera;
+    assert!(
+        camera.is_orthographic(),
+        "tile maps need an orthographic camera, such as `--isometric=ne`"
+    );
+
+    with_renderer(world, options, |renderer| {
+        let sections = renderer
+            .mesh
+            .sections
+            .iter()
+            .filter(|section| !section.indices.is_empty());
+        // The projection has to reach every block in front of and behind
+        // the camera
+        let view_distance = sections
+            .clone()
+            .flat_map(|section| corners(section.min, section.max))
+            .map(|corner| corner.distance(camera.position))
+            .fold(1.0, f32::max);
+        let renderer = SoftwareRenderer {
+            view_distance,
+            cave_culling: false,
+            lod_distance: None,
+            draw_sky: false,
+            ..*renderer
+        };
+        let view_proj = camera.view_proj(1.0, view_distance);
+
+        let (min, max) = sections
+            .flat_map(|section| corners(section.min, section.max))
+            .map(|corner| view_proj.project_point3(corner).truncate())
+            .fold(
+                (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+                |(min, max), point| (min.min(point), max.max(point)),
+            );
+        if min.x > max.x {
+            eprintln!("There's nothing to draw in the area");
+            return;
+        }
+        // Where a point is in units of the camera's view, with X going right
+        // and Y going down from the top left of the first tile
+        let to_tiles = |point: Vec2| (Vec2::new(point.x, -point.y) + 1.0) / 2.0;
+        let first_tile = to_tiles(Vec2::new(min.x, max.y)).floor();
+        let tile_count =
+            (to_tiles(Vec2::new(max.x, min.y)) - first_tile).as_ivec2() + 1;
+
+        let mut zoom_levels = 1;
+        while (tile_count - 1) >> (zoom_levels - 1) != IVec2::ZERO {
+            zoom_levels += 1;
+        }
+        let max_zoom = zoom_levels - 1;
+
+        let mut tiles = HashSet::new();
+        for y in 0..tile_count.y {
+            for x in 0..tile_count.x {
+                let tile = IVec2::new(x, y);
+                let offset = first_tile + tile.as_vec2();
+                let tile_min =
+                    Vec2::new(2.0 * offset.x - 1.0, -2.0 * offset.y - 1.0);
+                let image = renderer.render_view(
+                    &camera,
+                    camera::crop(view_proj, tile_min, tile_min + 2.0),
+                    TILE_SIZE,
+                    TILE_SIZE,
+                );
+                if image.pixels.chunks_exact(4).any(|pixel| pixel[3] != 0) {
+                    write_tile(export, max_zoom, tile, &image);
+                    tiles.insert(tile);
+                }
+            }
+            eprintln!("Rendered row {}/{} of tiles", y + 1, tile_count.y);
+        }
+
+        for zoom in (0..max_zoom).rev() {
+            let parents: HashSet<IVec2> =
+                tiles.iter().map(|&tile| tile >> 1_i32).collect();
+            for &parent in &parents {
+                let children = [0, 1].map(|y| {
+                    [0, 1].map(|x| {
+                        let child = parent * 2 + IVec2::new(x, y);
+                        tiles
+                            .contains(&child)
+                            .then(|| {
+                                read_image(&tile_path(export, zoom + 1, child))
+                            })
+                            .flatten()
+                    })
+                });
+                write_tile(export, zoom, parent, &merge(&children));
+            }
+            tiles = parents;
+        }
+
+        // The middle of the most detailed level's tiles in Leaflet's
+        // coordinates, which are pixels at zoom level 0
+        let center = (to_tiles((min + max) / 2.0) - first_tile)
+            * TILE_SIZE as f32
+            / (1 << max_zoom) as f32;
+        fs::write(
+            export.directory.join("index.html"),
+            include_str!("tile_map.html")
+                .replace("MAX_ZOOM", &max_zoom.to_string())
+                .replace("CENTER_X", &center.x.to_string())
+                .replace("CENTER_Y", &(-center.y).to_string()),
+        )
+        .unwrap();
+        eprintln!("Saved {zoom_levels} zoom levels of tiles");
+    });
+}
+
+fn corners(min: Vec3, max: Vec3) -> [Vec3; 8] {
+    [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+        Vec3::select(
+            glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+            max,
+            min,
+        )
+    })
+}
+
+fn tile_path(export: &TileMapExport, zoom: i32, tile: IVec2) -> PathBuf {
+    export
+        .directory
+        .join(format!("{zoom}/{}/{}.png", tile.x, tile.y))
+}
+
+fn write_tile(export: &TileMapExport, zoom: i32, tile: IVec2, image: &Image) {
+    let path = tile_path(export, zoom, tile);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    image
+        .write_png(&path)
+        .unwrap_or_else(|error| panic!("{error}"));
+}
+
+/// Shrinks a 2 by 2 grid of tiles into one, averaging each 2 by 2 square
+/// of pixels weighted by their opacity. Missing tiles are transparent.
+fn merge(children: &[[Option<Image>; 2]; 2]) -> Image {
+    let half = TILE_SIZE / 2;
+    let mut pixels = vec![0; (TILE_SIZE * TILE_SIZE * 4) as usize];
+    for (i, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i as u32 % TILE_SIZE, i as u32 / TILE_SIZE);
+        let Some(child) = &children[(y / half) as usize][(x / half) as usize]
+        else {
+            continue;
+        };
+        let mut sum = [0u32; 4];
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let source = child.pixel(x % half * 2 + dx, y % half * 2 + dy);
+            let alpha = u32::from(source[3]);
+            for channel in 0..3 {
+                sum[channel] += u32::from(source[channel]) * alpha;
+            }
+            sum[3] += alpha;
+        }
+        let weight = sum[3].max(1);
+        pixel.copy_from_slice(&[
+            (sum[0] / weight) as u8,
+            (sum[1] / weight) as u8,
+            (sum[2] / weight) as u8,
+            (sum[3] / 4) as u8,
+        ]);
+    }
+    Image {
+        width: TILE_SIZE,
+        height: TILE_SIZE,
+        pixels: pixels.into(),
+    }
+}
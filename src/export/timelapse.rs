@@ -0,0 +1,63 @@
+use super::with_renderer;
+use crate::{Options, World};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Where to save the frames of a timelapse.
+pub struct TimelapseExport {
+    /// The directory to save the frames to as numbered PNG files, one per
+    /// snapshot.
+    pub directory: PathBuf,
+}
+
+/// Renders each copy of a world in `snapshots_path`, such as daily backups,
+/// from the camera in the options, in the order of their folder names.
+///
+/// A snapshot is a folder with a `region` folder in it. Every region file is
+/// read, but the chunks of those that didn't change since the previous
+/// snapshot aren't deserialized again. The sky is drawn at the time saved in
+/// each snapshot unless the options set one.
+pub fn export_timelapse(
+    snapshots_path: &Path,
+    options: &Options,
+    export: &TimelapseExport,
+) {
+    let mut snapshots: Vec<PathBuf> = fs::read_dir(snapshots_path)
+        .unwrap_or_else(|error| panic!("{}: {error}", snapshots_path.display()))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.join("region").is_dir())
+        .collect();
+    snapshots.sort();
+    if snapshots.is_empty() {
+        panic!(
+            "{} has no folders with worlds in them",
+            snapshots_path.display()
+        );
+    }
+
+    fs::create_dir_all(&export.directory).unwrap();
+    let [width, height] = options.resolution;
+    let mut world: Option<World> = None;
+    for (frame, snapshot) in snapshots.iter().enumerate() {
+        let snapshot_world = match world.take() {
+            Some(world) => world.reload(snapshot, options),
+            None => World::new(snapshot, options),
+        };
+        with_renderer(&snapshot_world, options, |renderer| {
+            let path = export.directory.join(format!("frame_{frame:05}.png"));
+            renderer
+                .render(&options.camera, width, height)
+                .write_png(&path)
+                .unwrap_or_else(|error| panic!("{error}"));
+        });
+        eprintln!(
+            "Saved frame {}/{} of {}",
+            frame + 1,
+            snapshots.len(),
+            snapshot.display()
+        );
+        world = Some(snapshot_world);
+    }
+}
@@ -0,0 +1,251 @@
+//! Hides blocks as if they were air, to see caves, ores and the insides of
+//! buildings.
+
+use crate::{
+    block_source::BlockSource,
+    chunk::{Block, BlockEntity, BlockStates, Chunk, AIR},
+    entity::{Entity, MapColors},
+    world::Dimension,
+    Options,
+};
+use glam::{IVec2, IVec3};
+use internment::Intern;
+use std::{borrow::Cow, collections::HashMap};
+
+/// The blocks that x-ray mode hides unless others are chosen, which are
+/// those most of the ground is made of.
+pub const X_RAY_BLOCKS: &str = "stone,deepslate,tuff,granite,diorite,\
+    andesite,dirt,grass_block,gravel,sand,sandstone,netherrack,end_stone";
+
+/// A vertical plane along the X or Z axis that hides the blocks on one side
+/// of it.
+#[derive(Clone, Copy)]
+pub struct Slice {
+    /// `IVec3::X` or `IVec3::Z`, or one of their negatives, pointing towards
+    /// the hidden side.
+    pub direction: IVec3,
+    /// How far along the direction the first hidden blocks are.
+    pub distance: i32,
+}
+
+impl Slice {
+    /// Parses which blocks to keep, such as `x<20` or `z>=-5`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let error = || {
+            format!(
+                "expected `<x or z><comparison><coordinate>`, such as \
+                 `x<20`, instead of `{s}`"
+            )
+        };
+        let mut chars = s.chars();
+        let axis = match chars.next() {
+            Some('x' | 'X') => IVec3::X,
+            Some('z' | 'Z') => IVec3::Z,
+            _ => return Err(error()),
+        };
+        let rest = chars.as_str();
+        let (comparison, coordinate) = ["<=", ">=", "<", ">"]
+            .into_iter()
+            .find_map(|comparison| {
+                Some((comparison, rest.strip_prefix(comparison)?))
+            })
+            .ok_or_else(error)?;
+        let coordinate: i32 = coordinate.trim().parse().map_err(|_| error())?;
+        // Blocks are hidden where `pos.dot(direction) >= distance`
+        Ok(match comparison {
+            "<" => Self {
+                direction: axis,
+                distance: coordinate,
+            },
+            "<=" => Self {
+                direction: axis,
+                distance: coordinate + 1,
+            },
+            ">" => Self {
+                direction: -axis,
+                distance: -coordinate,
+            },
+            _ => Self {
+                direction: -axis,
+                distance: 1 - coordinate,
+            },
+        })
+    }
+
+    /// Whether a position is on the hidden side.
+    pub fn hides(&self, pos: IVec3) -> bool {
+        pos.dot(self.direction) >= self.distance
+    }
+}
+
+/// Another block source with the blocks that the options hide replaced by
+/// air, so that the faces next to them are drawn.
+pub struct Hidden<'a> {
+    pub world: &'a dyn BlockSource,
+    pub options: &'a Options,
+}
+
+impl BlockSource for Hidden<'_> {
+    fn block_at(&self, pos: IVec3) -> Option<Intern<Block>> {
+        let block = self.world.block_at(pos)?;
+        let hidden =
+            self.options.is_clipped(pos) || self.options.is_hidden(&block);
+        Some(if hidden { *AIR } else { block })
+    }
+
+    fn section_at(&self, pos: IVec3) -> Option<Cow<'_, BlockStates>> {
+        let mut section = self.world.section_at(pos)?;
+        let clipped: Vec<usize> = (0..4096)
+            .filter(|&offset| {
+                let offset = offset as i32;
+                self.options.is_clipped(
+                    pos * 16
+                        + IVec3::new(
+                            offset % 16,
+                            offset / 256,
+                            offset / 16 % 16,
+                        ),
+                )
+            })
+            .collect();
+        if !clipped.is_empty() {
+            let section = section.to_mut();
+            let air = section.palette.len() as u16;
+            section.palette.push(*AIR);
+            for offset in clipped {
+                section.data[offset] = air;
+            }
+        }
+        // Hiding blocks only needs a look at the palette
+        if section
+            .palette
+            .iter()
+            .any(|block| self.options.is_hidden(block))
+        {
+            for block in &mut section.to_mut().palette {
+                if self.options.is_hidden(block) {
+                    *block = *AIR;
+                }
+            }
+        }
+        Some(section)
+    }
+
+    fn block_entity_at(&self, pos: IVec3) -> Option<&BlockEntity> {
+        self.block_at(pos)
+            .is_some_and(|block| !block.is_air())
+            .then(|| self.world.block_entity_at(pos))?
+    }
+
+    fn block_light_at(&self, pos: IVec3) -> u8 {
+        self.world.block_light_at(pos)
+    }
+
+    fn sky_light_at(&self, pos: IVec3) -> u8 {
+        self.world.sky_light_at(pos)
+    }
+
+    fn light_at(&self, pos: IVec3) -> u8 {
+        self.world.light_at(pos)
+    }
+
+    fn biome_at(&self, pos: IVec3) -> Option<Intern<Box<str>>> {
+        self.world.biome_at(pos)
+    }
+
+    fn entities(&self) -> Box<dyn Iterator<Item = &Entity> + '_> {
+        self.world.entities()
+    }
+
+    fn maps(&self) -> &HashMap<i32, MapColors> {
+        self.world.maps()
+    }
+
+    fn dimension(&self) -> Dimension {
+        self.world.dimension()
+    }
+
+    fn day_time(&self) -> Option<i64> {
+        self.world.day_time()
+    }
+
+    fn data_version(&self) -> Option<i32> {
+        self.world.data_version()
+    }
+
+    fn seed(&self) -> Option<i64> {
+        self.world.seed()
+    }
+
+    fn chunk(&self, pos: IVec2) -> Option<&Chunk> {
+        self.world.chunk(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util, BlockFilter};
+    use glam::IVec2;
+
+    #[test]
+    fn parses_slices() {
+        for (slice, kept, hidden) in [
+            ("x<20", 19, 20),
+            ("x<=20", 20, 21),
+            ("z>-5", -4, -5),
+            ("Z>=-5", -5, -6),
+        ] {
+            let slice = Slice::parse(slice).unwrap();
+            let axis = slice.direction.abs();
+            assert!(!slice.hides(axis * kept), "{kept}");
+            assert!(slice.hides(axis * hidden), "{hidden}");
+        }
+        assert!(Slice::parse("y<20").is_err());
+        assert!(Slice::parse("x=20").is_err());
+    }
+
+    #[test]
+    fn hides_blocks_above_and_beside_the_planes() {
+        let world = |_pos: IVec3| Some(Block::parse("stone"));
+        let mut options = test_util::options(IVec2::ZERO..IVec2::new(16, 16));
+        options.max_y = Some(10);
+        options.slice = Some(Slice::parse("x<8").unwrap());
+        let hidden = Hidden {
+            world: &world,
+            options: &options,
+        };
+        let section = hidden.section_at(IVec3::ZERO).unwrap();
+        for offset in 0..4096 {
+            let pos = IVec3::new(offset % 16, offset / 256, offset / 16 % 16);
+            let block =
+                section.palette[usize::from(section.data[offset as usize])];
+            assert_eq!(block.is_air(), pos.y > 10 || pos.x >= 8, "{pos}");
+            assert_eq!(hidden.block_at(pos), Some(block));
+        }
+    }
+
+    #[test]
+    fn hides_blocks_in_the_x_ray_list() {
+        let world = |pos: IVec3| {
+            Some(if pos.y < 0 {
+                Block::parse("stone")
+            } else {
+                Block::parse("diamond_ore")
+            })
+        };
+        let mut options = test_util::options(IVec2::ZERO..IVec2::new(16, 16));
+        options.x_ray = Some(BlockFilter::parse(X_RAY_BLOCKS).unwrap());
+        let hidden = Hidden {
+            world: &world,
+            options: &options,
+        };
+        let section = hidden.section_at(IVec3::new(0, -1, 0)).unwrap();
+        assert!(section.palette.iter().all(|block| block.is_air()));
+        let section = hidden.section_at(IVec3::ZERO).unwrap();
+        assert!(section
+            .data
+            .iter()
+            .all(|&index| !section.palette[usize::from(index)].is_air()));
+    }
+}
@@ -0,0 +1,169 @@
+//! Loads Minecraft: Java Edition worlds and schematics and renders them,
+//! either in a window or without one to images, tile maps, models and
+//! schematics.
+//!
+//! Blocks are read through [`BlockSource`], which [`World`] implements for
+//! region files and [`Schematic`] for schematic files. [`Mesh::build`]
+//! turns them into triangles, which [`SoftwareRenderer`] draws on the CPU
+//! and, with the `window` feature, [`render`] draws on the GPU.
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+
+mod block_source;
+mod camera;
+mod camera_path;
+mod chunk;
+mod chunk_overlay;
+#[cfg(feature = "window")]
+mod controls;
+mod culling;
+mod diff;
+mod entity;
+pub mod export;
+mod hide;
+mod mesh;
+mod prune;
+mod raster;
+mod region;
+#[cfg(feature = "window")]
+mod render;
+mod schematic;
+#[cfg(feature = "window")]
+mod screenshot;
+mod search;
+#[cfg(feature = "window")]
+mod shader;
+mod sky;
+#[cfg(test)]
+mod test_util;
+mod texture;
+mod world;
+
+pub use block_source::{BlockSource, Volume};
+pub use camera::{
+    Camera, Projection, ISOMETRIC_DIRECTIONS, ISOMETRIC_PITCHES,
+    ORTHOGRAPHIC_HEIGHT,
+};
+pub use camera_path::CameraPath;
+pub use chunk::{
+    Biomes, Block, BlockEntity, BlockStates, Chunk, Heightmaps, Section,
+    Structures, AIR,
+};
+pub use chunk_overlay::{write_chunk_report, ChunkOverlay};
+#[cfg(feature = "window")]
+pub use controls::{Action, KeyBindings};
+pub use diff::{BlockChange, WorldDiff};
+pub use entity::{Entity, EntityCategories, Item, MapColors};
+pub use hide::{Slice, X_RAY_BLOCKS};
+pub use mesh::{
+    HighlightVertex, Highlights, Lod, Mesh, Section as MeshSection, Vertex,
+};
+pub use prune::{
+    prune, PruneOptions, PruneReport, RegionReport, PLAYER_BLOCKS,
+};
+pub use raster::SoftwareRenderer;
+#[cfg(feature = "window")]
+pub use render::render;
+pub use schematic::Schematic;
+pub use search::{BlockFilter, BlockStatistics};
+pub use sky::{Sky, SkyTextures};
+pub use texture::{Atlas, Image, Rect, Texture};
+pub use world::{Dimension, World};
+
+use glam::{IVec2, IVec3, Vec3};
+use std::{ops::Range, path::PathBuf};
+
+/// What to load and how to render it.
+pub struct Options {
+    /// A folder with the textures to use, laid out like Minecraft's.
+    pub resource_pack_path: PathBuf,
+    /// Where the camera starts.
+    pub camera: Camera,
+    /// Where the camera moves over time, if it moves.
+    pub camera_path: Option<CameraPath>,
+    /// The width and height of exported images.
+    pub resolution: [u32; 2],
+    /// How many times bigger than the window hi-res screenshots are.
+    pub screenshot_scale: u32,
+    /// The columns of blocks to load, from the first corner to the second,
+    /// not included.
+    pub area: Range<IVec2>,
+    /// Which kinds of entities to render.
+    pub entities: EntityCategories,
+    /// Whether to skip sections hidden behind terrain.
+    pub cave_culling: bool,
+    /// The distance in blocks beyond which chunks are drawn with less
+    /// detail, doubling the size of each cell at every multiple of it.
+    pub lod_distance: Option<f32>,
+    /// How far away terrain fades into the fog, by default far enough to see
+    /// the whole area.
+    pub view_distance: Option<f32>,
+    /// The time of day in ticks, instead of the time saved in the world.
+    pub time: Option<i64>,
+    /// Which blocks to draw outlines around, which can be seen through
+    /// everything else.
+    pub highlight: Option<BlockFilter>,
+    /// Whether to hide every block that isn't highlighted.
+    pub hide_unhighlighted: bool,
+    /// The height above which blocks are hidden, to see inside caves and
+    /// buildings.
+    pub max_y: Option<i32>,
+    /// A vertical plane that hides the blocks on one side of it.
+    pub slice: Option<Slice>,
+    /// Blocks to hide, to see the caves and ores inside them.
+    pub x_ray: Option<BlockFilter>,
+    /// Blocks changed since an older copy of the world, which are drawn
+    /// green if they were placed and red if they were broken.
+    pub changes: Option<WorldDiff>,
+    /// Which of the chunks' data to show over them as a heat map.
+    pub chunk_overlay: Option<ChunkOverlay>,
+    /// Which keys move the camera in the window.
+    #[cfg(feature = "window")]
+    pub key_bindings: KeyBindings,
+}
+
+impl Options {
+    /// How far away terrain fades into the fog.
+    pub fn view_distance(&self) -> f32 {
+        let area_size = (self.area.end - self.area.start).as_vec2();
+        self.view_distance.unwrap_or_else(|| {
+            Vec3::new(area_size.x, 384.0, area_size.y).length()
+        })
+    }
+
+    /// The time of day to render, which is noon if neither the options nor
+    /// the world say.
+    pub fn day_time(&self, world: &dyn BlockSource) -> i64 {
+        self.time.or_else(|| world.day_time()).unwrap_or(6000)
+    }
+
+    /// Whether any blocks are left out of the mesh.
+    pub fn hides_blocks(&self) -> bool {
+        (self.hide_unhighlighted && self.highlight.is_some())
+            || self.max_y.is_some()
+            || self.slice.is_some()
+            || self.x_ray.is_some()
+    }
+
+    /// Whether a kind of block is left out of the mesh, as if it were air.
+    pub fn is_hidden(&self, block: &Block) -> bool {
+        let unhighlighted = self.hide_unhighlighted
+            && self
+                .highlight
+                .as_ref()
+                .is_some_and(|filter| !filter.matches(block));
+        unhighlighted
+            || self
+                .x_ray
+                .as_ref()
+                .is_some_and(|filter| filter.matches(block))
+    }
+
+    /// Whether a position is cut away by the clip planes, and whatever is
+    /// there is left out of the mesh.
+    pub fn is_clipped(&self, pos: IVec3) -> bool {
+        self.max_y.is_some_and(|max_y| pos.y > max_y)
+            || self.slice.is_some_and(|slice| slice.hides(pos))
+    }
+}
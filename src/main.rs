@@ -1,36 +1,261 @@
 #![forbid(unsafe_code)]
 
-mod chunk;
-mod mesh;
-mod region;
-mod render;
-mod shader;
-mod world;
-
-use glam::{IVec2, Vec3};
+use glam::{IVec2, IVec3, Vec3, Vec3Swizzles};
+#[cfg(feature = "window")]
+use mcrender::KeyBindings;
+use mcrender::{
+    export::{
+        self, DataMapExport, FrameExport, ModelExport, SchematicExport,
+        TileMapExport, TimelapseExport,
+    },
+    write_chunk_report, BlockFilter, BlockSource, BlockStatistics, Camera,
+    CameraPath, ChunkOverlay, EntityCategories, Options, Projection,
+    PruneOptions, Schematic, Slice, World, WorldDiff, ISOMETRIC_DIRECTIONS,
+    ISOMETRIC_PITCHES, ORTHOGRAPHIC_HEIGHT, PLAYER_BLOCKS, X_RAY_BLOCKS,
+};
 use std::{
-    ops::Range,
+    fmt::Display,
     path::{Path, PathBuf},
+    str::FromStr,
 };
-use world::World;
-
-pub struct Options {
-    pub resource_pack_path: PathBuf,
-    pub camera_position: Vec3,
-    pub camera_pitch: f32,
-    pub camera_yaw: f32,
-    pub vfov: f32,
-    pub area: Range<IVec2>,
-}
 
 fn main() {
-    let mut args = std::env::args().skip(1);
+    let mut entities = EntityCategories::ALL;
+    let mut cave_culling = false;
+    let mut lod_distance = None;
+    let mut view_distance = None;
+    let mut time = None;
+    #[cfg(feature = "window")]
+    let mut key_bindings_path = None;
+    let mut camera_path = None;
+    let mut frames_directory = None;
+    let mut fps = 30.0;
+    let mut resolution = [1920, 1080];
+    let mut screenshot_scale = 4;
+    let mut image_path = None;
+    let mut tile_map_directory = None;
+    let mut model_path = None;
+    let mut split_by_block = false;
+    let mut schematic_path = None;
+    let mut selection = None;
+    let mut filter = None;
+    let mut highlight = None;
+    let mut hide_unhighlighted = false;
+    let mut max_y = None;
+    let mut slice = None;
+    let mut x_ray = None;
+    let mut statistics_path = None;
+    let mut maps_directory = None;
+    let mut older_world_path = None;
+    let mut diff_path = None;
+    let mut timelapse_directory = None;
+    let mut chunk_overlay = None;
+    let mut chunk_report_path = None;
+    let mut prune_path = None;
+    let mut dry_run = false;
+    // Five minutes of play by default
+    let mut min_inhabited_time = 5 * 60 * 20;
+    let mut keep_areas = Vec::new();
+    let mut keep_blocks = None;
+    let mut camera = Camera {
+        position: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: -5.0,
+        },
+        yaw: 0.0,
+        pitch: 0.0,
+        vfov: 1.0,
+        projection: Projection::Perspective,
+    };
+    let mut positional_args = Vec::new();
+    for arg in std::env::args().skip(1) {
+        let Some(flag) = arg.strip_prefix("--") else {
+            positional_args.push(arg);
+            continue;
+        };
+        let (name, value) = flag.split_once('=').unwrap_or((flag, ""));
+        match name {
+            "entities" => {
+                entities = EntityCategories::parse(value)
+                    .unwrap_or_else(|error| panic!("{error}"));
+            }
+            "cave-culling" => cave_culling = true,
+            "lod-distance" => lod_distance = Some(parse_option(name, value)),
+            "view-distance" => view_distance = Some(parse_option(name, value)),
+            #[cfg(feature = "window")]
+            "key-bindings" => key_bindings_path = Some(PathBuf::from(value)),
+            "time" => time = Some(parse_option(name, value)),
+            "camera-path" => {
+                camera_path = Some(
+                    CameraPath::load(Path::new(value))
+                        .unwrap_or_else(|error| panic!("{error}")),
+                );
+            }
+            "export-frames" => frames_directory = Some(PathBuf::from(value)),
+            "fps" => fps = parse_option(name, value),
+            "resolution" => {
+                let (width, height) =
+                    value.split_once('x').unwrap_or_else(|| {
+                        panic!("expected `--resolution=<width>x<height>`")
+                    });
+                resolution =
+                    [parse_option(name, width), parse_option(name, height)];
+            }
+            "screenshot-scale" => screenshot_scale = parse_option(name, value),
+            "export-image" => image_path = Some(PathBuf::from(value)),
+            "chunk-overlay" => {
+                chunk_overlay = Some(
+                    ChunkOverlay::parse(value)
+                        .unwrap_or_else(|error| panic!("{error}")),
+                );
+            }
+            "export-chunk-report" => {
+                chunk_report_path = Some(PathBuf::from(value));
+            }
+            "prune" => prune_path = Some(PathBuf::from(value)),
+            "dry-run" => dry_run = true,
+            "min-inhabited-time" => {
+                let seconds: i64 = parse_option(name, value);
+                min_inhabited_time = seconds * 20;
+            }
+            "keep" => {
+                let [x1, z1, x2, z2]: [i32; 4] = value
+                    .split(',')
+                    .map(|coord| parse_option(name, coord))
+                    .collect::<Vec<_>>()
+                    .try_into()
+                    .unwrap_or_else(|_| {
+                        panic!("expected `--keep=<x1>,<z1>,<x2>,<z2>`")
+                    });
+                keep_areas.push(
+                    IVec2::new(x1, z1).min(IVec2::new(x2, z2))
+                        ..IVec2::new(x1, z1).max(IVec2::new(x2, z2)) + 1,
+                );
+            }
+            "keep-blocks" => {
+                keep_blocks = Some(
+                    BlockFilter::parse(value)
+                        .unwrap_or_else(|error| panic!("{error}")),
+                );
+            }
+            "compare" => older_world_path = Some(PathBuf::from(value)),
+            "export-diff" => diff_path = Some(PathBuf::from(value)),
+            "export-maps" => maps_directory = Some(PathBuf::from(value)),
+            "export-model" => model_path = Some(PathBuf::from(value)),
+            "split-by-block" => split_by_block = true,
+            "export-schematic" => schematic_path = Some(PathBuf::from(value)),
+            "box" => {
+                let [x1, y1, z1, x2, y2, z2]: [i32; 6] = value
+                    .split(',')
+                    .map(|coord| parse_option(name, coord))
+                    .collect::<Vec<_>>()
+                    .try_into()
+                    .unwrap_or_else(|_| {
+                        panic!("expected `--box=<x1>,<y1>,<z1>,<x2>,<y2>,<z2>`")
+                    });
+                let (a, b) = (IVec3::new(x1, y1, z1), IVec3::new(x2, y2, z2));
+                selection = Some((a.min(b), a.max(b)));
+            }
+            "find" => {
+                filter = Some(
+                    BlockFilter::parse(value)
+                        .unwrap_or_else(|error| panic!("{error}")),
+                );
+            }
+            "highlight" => {
+                highlight = Some(
+                    BlockFilter::parse(value)
+                        .unwrap_or_else(|error| panic!("{error}")),
+                );
+            }
+            "only-highlighted" => hide_unhighlighted = true,
+            "max-y" => max_y = Some(parse_option(name, value)),
+            "slice" => {
+                slice = Some(
+                    Slice::parse(value)
+                        .unwrap_or_else(|error| panic!("{error}")),
+                );
+            }
+            "x-ray" => {
+                let blocks = if value.is_empty() {
+                    X_RAY_BLOCKS
+                } else {
+                    value
+                };
+                x_ray = Some(
+                    BlockFilter::parse(blocks)
+                        .unwrap_or_else(|error| panic!("{error}")),
+                );
+            }
+            "export-statistics" => statistics_path = Some(PathBuf::from(value)),
+            "export-timelapse" => {
+                timelapse_directory = Some(PathBuf::from(value));
+            }
+            "export-tile-map" => {
+                tile_map_directory = Some(PathBuf::from(value));
+            }
+            "camera" => {
+                let numbers: Vec<f32> = value
+                    .split(',')
+                    .map(|number| parse_option(name, number))
+                    .collect();
+                match *numbers {
+                    [x, y, z] => camera.position = Vec3::new(x, y, z),
+                    // The same angles as on Minecraft's debug screen
+                    [x, y, z, yaw, pitch] => {
+                        camera.position = Vec3::new(x, y, z);
+                        camera.yaw = -yaw.to_radians();
+                        camera.pitch = pitch.to_radians();
+                    }
+                    _ => panic!(
+                        "expected `--camera=<x>,<y>,<z>[,<yaw>,<pitch>]`"
+                    ),
+                }
+            }
+            "orthographic" => {
+                camera.projection = Projection::Orthographic {
+                    height: parse_option(name, value),
+                };
+            }
+            "isometric" => {
+                let (direction, pitch) = value.split_once(',').map_or(
+                    (value, ISOMETRIC_PITCHES[0]),
+                    |(direction, pitch)| (direction, parse_option(name, pitch)),
+                );
+                camera.yaw = ISOMETRIC_DIRECTIONS
+                    .iter()
+                    .find(|(name, _)| *name == direction)
+                    .unwrap_or_else(|| {
+                        panic!("unknown isometric direction `{direction}`")
+                    })
+                    .1;
+                camera.pitch = pitch.to_radians();
+                if camera.projection == Projection::Perspective {
+                    camera.projection = Projection::Orthographic {
+                        height: ORTHOGRAPHIC_HEIGHT,
+                    };
+                }
+            }
+            _ => panic!("unknown option `--{name}`"),
+        }
+    }
+
+    let mut args = positional_args.into_iter();
     let world_path = args.next().unwrap_or_else(|| "world".to_owned());
     let resource_pack_path = PathBuf::from(
         args.next().unwrap_or_else(|| "resource-pack".to_owned()),
     );
+    let schematic = Schematic::is_schematic(Path::new(&world_path))
+        .then(|| Schematic::load(Path::new(&world_path)));
+    // The area defaults to the box if there is one, or else the whole
+    // schematic
     let area = args.next().map_or_else(
-        || IVec2::new(0, 0)..IVec2::new(32, 16),
+        || match (selection, &schematic) {
+            (Some((min, max)), _) => min.xz()..max.xz() + 1,
+            (None, Some(schematic)) => IVec2::ZERO..schematic.volume.size.xz(),
+            (None, None) => IVec2::new(0, 0)..IVec2::new(32, 16),
+        },
         |s| {
             let [x1, z1, x2, z2]: [i32; 4] = s
                 .split(',')
@@ -41,21 +266,169 @@ fn main() {
             IVec2::new(x1, z1)..IVec2::new(x2, z2)
         },
     );
+    // Columns that aren't loaded would be exported as air
+    let area = match (selection, &schematic_path) {
+        (Some((min, max)), Some(_)) => {
+            area.start.min(min.xz())..area.end.max(max.xz() + 1)
+        }
+        _ => area,
+    };
+
+    let changes = older_world_path.map(|older_world_path| {
+        assert!(schematic.is_none(), "only worlds can be compared");
+        let diff = WorldDiff::compare(
+            &older_world_path,
+            Path::new(&world_path),
+            &area,
+        );
+        eprintln!(
+            "{} blocks changed in {} of {} chunks, {} chunks were generated \
+             and {} deleted",
+            diff.changes.len(),
+            diff.chunks_changed,
+            diff.chunks_compared,
+            diff.chunks_generated,
+            diff.chunks_deleted,
+        );
+        diff
+    });
 
     let options = Options {
         resource_pack_path,
-        camera_position: Vec3 {
-            x: 0.0,
-            y: 0.0,
-            z: -5.0,
-        },
-        camera_pitch: 0.0,
-        camera_yaw: 0.0,
-        vfov: 1.0,
+        camera,
+        camera_path,
+        resolution,
+        screenshot_scale,
         area,
+        entities,
+        cave_culling,
+        lod_distance,
+        view_distance,
+        time,
+        highlight,
+        hide_unhighlighted,
+        max_y,
+        slice,
+        x_ray,
+        changes,
+        chunk_overlay,
+        #[cfg(feature = "window")]
+        key_bindings: load_key_bindings(key_bindings_path),
+    };
+
+    if prune_path.is_some() || dry_run {
+        let options = PruneOptions {
+            min_inhabited_time,
+            keep_areas,
+            keep_blocks: keep_blocks
+                .unwrap_or_else(|| BlockFilter::parse(PLAYER_BLOCKS).unwrap()),
+        };
+        let output = if dry_run { None } else { prune_path.as_deref() };
+        println!(
+            "{}",
+            mcrender::prune(Path::new(&world_path), &options, output)
+        );
+        return;
+    }
+
+    // The world path is a folder of copies of the world to render
+    if let Some(directory) = timelapse_directory {
+        export::export_timelapse(
+            Path::new(&world_path),
+            &options,
+            &TimelapseExport { directory },
+        );
+        return;
+    }
+
+    let world: Box<dyn BlockSource> = match schematic {
+        Some(schematic) => Box::new(schematic),
+        None => Box::new(World::new(Path::new(&world_path), &options)),
     };
 
-    let world = World::new(Path::new(&world_path), &options);
+    if let Some(path) = chunk_report_path {
+        write_chunk_report(&*world, &options.area, &path)
+            .unwrap_or_else(|error| panic!("{error}"));
+    } else if let Some(path) = diff_path {
+        let changes = options.changes.as_ref().unwrap_or_else(|| {
+            panic!("exporting a diff needs an older world to `--compare` to")
+        });
+        changes
+            .save(&path)
+            .unwrap_or_else(|error| panic!("{error}"));
+    } else if let Some(path) = statistics_path {
+        let statistics =
+            BlockStatistics::collect(&*world, &options, filter.as_ref());
+        statistics
+            .save(&path)
+            .unwrap_or_else(|error| panic!("{error}"));
+    } else if let Some(path) = image_path {
+        export::export_image(&*world, &options, &path);
+    } else if let Some(path) = schematic_path {
+        let (min, max) = selection.unwrap_or_else(|| {
+            panic!("exporting a schematic needs a `--box` to export")
+        });
+        export::export_schematic(&*world, &SchematicExport { path, min, max });
+    } else if let Some(path) = model_path {
+        export::export_model(
+            &*world,
+            &options,
+            &ModelExport {
+                path,
+                split_by_block,
+            },
+        );
+    } else if let Some(directory) = maps_directory {
+        export::export_data_maps(
+            &*world,
+            &options,
+            &DataMapExport { directory },
+        );
+    } else if let Some(directory) = tile_map_directory {
+        export::export_tile_map(
+            &*world,
+            &options,
+            &TileMapExport { directory },
+        );
+    } else if let Some(directory) = frames_directory {
+        let camera_path = options.camera_path.as_ref().unwrap_or_else(|| {
+            panic!("exporting frames needs a `--camera-path`")
+        });
+        export::export_frames(
+            &*world,
+            &options,
+            camera_path,
+            &FrameExport { directory, fps },
+        );
+    } else {
+        #[cfg(feature = "window")]
+        mcrender::render(world, options);
+        #[cfg(not(feature = "window"))]
+        panic!(
+            "this build can't open a window, use an export option such as \
+             `--export-image`"
+        );
+    }
+}
+
+/// Key bindings are read from `key-bindings.txt` if there is one.
+#[cfg(feature = "window")]
+fn load_key_bindings(path: Option<PathBuf>) -> KeyBindings {
+    match path {
+        Some(path) => KeyBindings::load(&path),
+        None if Path::new("key-bindings.txt").exists() => {
+            KeyBindings::load(Path::new("key-bindings.txt"))
+        }
+        None => Ok(KeyBindings::default()),
+    }
+    .unwrap_or_else(|error| panic!("{error}"))
+}
 
-    render::render(world, options);
+fn parse_option<T: FromStr>(name: &str, value: &str) -> T
+where
+    T::Err: Display,
+{
+    value.parse().unwrap_or_else(|error| {
+        panic!("invalid value `{value}` for `--{name}`: {error}")
+    })
 }
@@ -1,531 +1,179 @@
+mod block_entity;
+mod cuboid;
+mod entity;
+mod highlight;
+mod lod;
+
+pub use highlight::{
+    highlight_alpha, HighlightVertex, Highlights, HIGHLIGHT_COLOR,
+};
+pub use lod::{Lod, LOD_SCALES};
+
 use crate::{
+    block_source::BlockSource,
     chunk::{Block, AIR},
-    Options, World,
+    culling::Visibility,
+    hide::Hidden,
+    texture::Texture,
+    Options,
 };
-use glam::{IVec3, Vec2, Vec3, Vec4, Vec4Swizzles};
+use glam::{Affine3A, IVec3, Vec2, Vec3, Vec4, Vec4Swizzles};
 use indexmap::IndexSet;
 use internment::Intern;
+use std::ops::Range;
 
 const TOP_LIGHT_LEVEL: f32 = 1.0;
 const FRONT_BACK_LIGHT_LEVEL: f32 = 0.85;
 const SIDE_LIGHT_LEVEL: f32 = 0.75;
 const BOTTOM_LIGHT_LEVEL: f32 = 0.6;
 
+/// The triangles of the faces of blocks and entities, ready to draw.
 pub struct Mesh {
+    /// The vertices of every face, grouped by section.
     pub vertices: Vec<Vertex>,
+    /// Triangles as indices into the vertices.
     pub indices: Vec<u32>,
-    pub texture_names: IndexSet<&'static str>,
+    /// The textures that vertices' texture indices refer to.
+    pub textures: IndexSet<Texture>,
+    /// The parts of the mesh in each section.
+    pub sections: Vec<Section>,
+    /// Simplified meshes of each chunk, if levels of detail are enabled.
+    pub lods: Vec<Lod>,
+    /// The block that each run of indices was made for, with neighbouring
+    /// blocks of the same kind sharing a run.
+    pub blocks: Vec<(Intern<Block>, Range<usize>)>,
+    /// Boxes around the blocks that match the highlight filter.
+    pub highlights: Highlights,
+}
+
+/// The part of a mesh that belongs to one 16x16x16 section of the world,
+/// so that it can be drawn or culled on its own.
+pub struct Section {
+    /// The position of the section in sections.
+    pub pos: IVec3,
+    /// The range of the whole mesh's vertices in the section.
+    pub vertices: Range<usize>,
+    /// Indices into the whole mesh's vertices.
+    pub indices: Range<usize>,
+    /// The corner of the bounding box of the section and all its vertices
+    /// with the lowest coordinates.
+    pub min: Vec3,
+    /// The opposite corner of the bounding box.
+    pub max: Vec3,
+    /// Which faces of the section can be seen from which others through
+    /// it, for cave culling.
+    pub(crate) visibility: Visibility,
 }
 
 impl Mesh {
-    pub fn build(world: &World, options: &Options) -> Self {
-        Self {
+    /// Builds the faces of every block in the area to render that are next
+    /// to a block they can be seen through.
+    pub fn build(world: &dyn BlockSource, options: &Options) -> Self {
+        let mesh = Self {
             vertices: Vec::new(),
             indices: Vec::new(),
-            texture_names: IndexSet::new(),
+            textures: IndexSet::new(),
+            sections: Vec::new(),
+            lods: Vec::new(),
+            blocks: Vec::new(),
+            highlights: Highlights::default(),
+        };
+        if options.hides_blocks() {
+            mesh.inner_build_impl(&Hidden { world, options }, options)
+        } else {
+            mesh.inner_build_impl(world, options)
         }
-        .inner_build_impl(world, options)
     }
 
-    fn inner_build_impl(mut self, world: &World, options: &Options) -> Self {
-        // TODO: perform greedy meshing
-        for y in -64..320 {
-            for z in options.area.start.y..options.area.end.y {
-                for x in options.area.start.x..options.area.end.x {
-                    let p = IVec3 { x, y, z };
-                    let Some(block) = world.block_at(p) else {
-                        continue;
-                    };
-                    match BlockModel::of(block) {
-                        BlockModel::None => {}
-                        BlockModel::SolidBlock
-                        | BlockModel::TransparentBlock => {
-                            let texture_index =
-                                self.block_front_side_texture_id(block);
-
-                            let v = |pos, u, v, light_level| Vertex {
-                                pos,
-                                uv: Vec2 { x: u, y: v },
-                                light_level,
-                                texture_index,
-                            };
-
-                            if !world
-                                .block_at(p - IVec3::X)
-                                .map_or(false, is_solid)
-                            {
-                                let p = p.as_vec3();
-                                let vertex_count = self.vertices.len() as u32;
-                                self.vertices.extend([
-                                    v(p, 0.0, 1.0, SIDE_LIGHT_LEVEL),
-                                    v(p + Vec3::Z, 1.0, 1.0, SIDE_LIGHT_LEVEL),
-                                    v(p + Vec3::Y, 0.0, 0.0, SIDE_LIGHT_LEVEL),
-                                    v(
-                                        p + Vec3::Z + Vec3::Y,
-                                        1.0,
-                                        0.0,
-                                        SIDE_LIGHT_LEVEL,
-                                    ),
-                                ]);
-                                self.indices.extend([
-                                    vertex_count,
-                                    vertex_count + 1,
-                                    vertex_count + 2,
-                                    vertex_count + 1,
-                                    vertex_count + 3,
-                                    vertex_count + 2,
-                                ]);
-                            }
-                            if !world
-                                .block_at(p + IVec3::X)
-                                .map_or(false, is_solid)
-                            {
-                                let p = p.as_vec3() + Vec3::X;
-                                let vertex_count = self.vertices.len() as u32;
-                                self.vertices.extend([
-                                    v(p, 0.0, 1.0, SIDE_LIGHT_LEVEL),
-                                    v(p + Vec3::Z, 1.0, 1.0, SIDE_LIGHT_LEVEL),
-                                    v(p + Vec3::Y, 0.0, 0.0, SIDE_LIGHT_LEVEL),
-                                    v(
-                                        p + Vec3::Z + Vec3::Y,
-                                        1.0,
-                                        0.0,
-                                        SIDE_LIGHT_LEVEL,
-                                    ),
-                                ]);
-                                self.indices.extend([
-                                    vertex_count,
-                                    vertex_count + 2,
-                                    vertex_count + 1,
-                                    vertex_count + 1,
-                                    vertex_count + 2,
-                                    vertex_count + 3,
-                                ]);
-                            }
-                            if !world
-                                .block_at(p - IVec3::Y)
-                                .map_or(false, is_solid)
-                            {
-                                let p = p.as_vec3();
-                                let vertex_count = self.vertices.len() as u32;
-                                self.vertices.extend([
-                                    v(p, 0.0, 1.0, BOTTOM_LIGHT_LEVEL),
-                                    v(
-                                        p + Vec3::X,
-                                        1.0,
-                                        1.0,
-                                        BOTTOM_LIGHT_LEVEL,
-                                    ),
-                                    v(
-                                        p + Vec3::Z,
-                                        0.0,
-                                        0.0,
-                                        BOTTOM_LIGHT_LEVEL,
-                                    ),
-                                    v(
-                                        p + Vec3::X + Vec3::Z,
-                                        1.0,
-                                        0.0,
-                                        BOTTOM_LIGHT_LEVEL,
-                                    ),
-                                ]);
-                                self.indices.extend([
-                                    vertex_count,
-                                    vertex_count + 1,
-                                    vertex_count + 2,
-                                    vertex_count + 1,
-                                    vertex_count + 3,
-                                    vertex_count + 2,
-                                ]);
-                            }
-                            if !world
-                                .block_at(p + IVec3::Y)
-                                .map_or(false, is_solid)
-                            {
-                                let texture_index =
-                                    self.block_top_texture_id(block);
-
-                                let v = |pos, u, v, light_level| Vertex {
-                                    pos,
-                                    uv: Vec2 { x: u, y: v },
-                                    light_level,
-                                    texture_index,
-                                };
-
-                                let p = p.as_vec3() + Vec3::Y;
-                                let vertex_count = self.vertices.len() as u32;
-                                self.vertices.extend([
-                                    v(p, 0.0, 1.0, TOP_LIGHT_LEVEL),
-                                    v(p + Vec3::X, 1.0, 1.0, TOP_LIGHT_LEVEL),
-                                    v(p + Vec3::Z, 0.0, 0.0, TOP_LIGHT_LEVEL),
-                                    v(
-                                        p + Vec3::X + Vec3::Z,
-                                        1.0,
-                                        0.0,
-                                        TOP_LIGHT_LEVEL,
-                                    ),
-                                ]);
-                                self.indices.extend([
-                                    vertex_count,
-                                    vertex_count + 2,
-                                    vertex_count + 1,
-                                    vertex_count + 1,
-                                    vertex_count + 2,
-                                    vertex_count + 3,
-                                ]);
-                            }
-                            if !world
-                                .block_at(p - IVec3::Z)
-                                .map_or(false, is_solid)
-                            {
-                                let p = p.as_vec3();
-                                let vertex_count = self.vertices.len() as u32;
-                                self.vertices.extend([
-                                    v(p, 0.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
-                                    v(
-                                        p + Vec3::X,
-                                        1.0,
-                                        1.0,
-                                        FRONT_BACK_LIGHT_LEVEL,
-                                    ),
-                                    v(
-                                        p + Vec3::Y,
-                                        0.0,
-                                        0.0,
-                                        FRONT_BACK_LIGHT_LEVEL,
-                                    ),
-                                    v(
-                                        p + Vec3::X + Vec3::Y,
-                                        1.0,
-                                        0.0,
-                                        FRONT_BACK_LIGHT_LEVEL,
-                                    ),
-                                ]);
-                                self.indices.extend([
-                                    vertex_count,
-                                    vertex_count + 2,
-                                    vertex_count + 1,
-                                    vertex_count + 1,
-                                    vertex_count + 2,
-                                    vertex_count + 3,
-                                ]);
-                            }
-                            if !world
-                                .block_at(p + IVec3::Z)
-                                .map_or(false, is_solid)
-                            {
-                                let p = p.as_vec3() + Vec3::Z;
-                                let vertex_count = self.vertices.len() as u32;
-                                self.vertices.extend([
-                                    v(p, 0.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
-                                    v(
-                                        p + Vec3::X,
-                                        1.0,
-                                        1.0,
-                                        FRONT_BACK_LIGHT_LEVEL,
-                                    ),
-                                    v(
-                                        p + Vec3::Y,
-                                        0.0,
-                                        0.0,
-                                        FRONT_BACK_LIGHT_LEVEL,
-                                    ),
-                                    v(
-                                        p + Vec3::X + Vec3::Y,
-                                        1.0,
-                                        0.0,
-                                        FRONT_BACK_LIGHT_LEVEL,
-                                    ),
-                                ]);
-                                self.indices.extend([
-                                    vertex_count,
-                                    vertex_count + 1,
-                                    vertex_count + 2,
-                                    vertex_count + 1,
-                                    vertex_count + 3,
-                                    vertex_count + 2,
-                                ]);
-                            }
-                        }
-                        BlockModel::Cross(texture_name) => {
-                            let texture_index =
-                                self.allocate_texture(texture_name) as f32;
-
-                            let v = |pos, u, v, light_level| Vertex {
-                                pos,
-                                uv: Vec2 { x: u, y: v },
-                                light_level,
-                                texture_index,
-                            };
-
-                            let p = p.as_vec3();
-                            let vertex_count = self.vertices.len() as u32;
-                            self.vertices.extend([
-                                v(p, 1.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
-                                v(
-                                    p + Vec3::X + Vec3::Z,
-                                    0.0,
-                                    1.0,
-                                    FRONT_BACK_LIGHT_LEVEL,
-                                ),
-                                v(
-                                    p + Vec3::Y,
-                                    1.0,
-                                    0.0,
-                                    FRONT_BACK_LIGHT_LEVEL,
-                                ),
-                                v(
-                                    p + Vec3::X + Vec3::Y + Vec3::Z,
-                                    0.0,
-                                    0.0,
-                                    FRONT_BACK_LIGHT_LEVEL,
-                                ),
-                                v(
-                                    p + Vec3::X,
-                                    1.0,
-                                    1.0,
-                                    FRONT_BACK_LIGHT_LEVEL,
-                                ),
-                                v(
-                                    p + Vec3::Z,
-                                    0.0,
-                                    1.0,
-                                    FRONT_BACK_LIGHT_LEVEL,
-                                ),
-                                v(
-                                    p + Vec3::X + Vec3::Y,
-                                    1.0,
-                                    0.0,
-                                    FRONT_BACK_LIGHT_LEVEL,
-                                ),
-                                v(
-                                    p + Vec3::Y + Vec3::Z,
-                                    0.0,
-                                    0.0,
-                                    FRONT_BACK_LIGHT_LEVEL,
-                                ),
-                            ]);
-                            self.indices.extend([
-                                vertex_count,
-                                vertex_count + 1,
-                                vertex_count + 3,
-                                vertex_count,
-                                vertex_count + 3,
-                                vertex_count + 2,
-                                vertex_count,
-                                vertex_count + 3,
-                                vertex_count + 1,
-                                vertex_count,
-                                vertex_count + 2,
-                                vertex_count + 3,
-                                vertex_count + 4,
-                                vertex_count + 5,
-                                vertex_count + 7,
-                                vertex_count + 4,
-                                vertex_count + 7,
-                                vertex_count + 6,
-                                vertex_count + 4,
-                                vertex_count + 7,
-                                vertex_count + 5,
-                                vertex_count + 4,
-                                vertex_count + 6,
-                                vertex_count + 7,
-                            ]);
-                        }
-                        BlockModel::FlatDirectional {
-                            texture_name,
-                            north,
-                            south,
-                            east,
-                            west,
-                            up,
-                            down,
-                        } => {
-                            let texture_index =
-                                self.allocate_texture(texture_name) as f32;
-
-                            let v = |pos, u, v, light_level| Vertex {
-                                pos,
-                                uv: Vec2 { x: u, y: v },
-                                light_level,
-                                texture_index,
-                            };
-
-                            let p = p.as_vec3();
-
-                            if north {
-                                let p = p + Vec3::Z * 0.0625;
-                                let vertex_count = self.vertices.len() as u32;
-                                self.vertices.extend([
-                                    v(p, 0.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
-                                    v(
-                                        p + Vec3::X,
-                                        1.0,
-                                        1.0,
-                                        FRONT_BACK_LIGHT_LEVEL,
-                                    ),
-                                    v(
-                                        p + Vec3::Y,
-                                        0.0,
-                                        0.0,
-                                        FRONT_BACK_LIGHT_LEVEL,
-                                    ),
-                                    v(
-                                        p + Vec3::X + Vec3::Y,
-                                        1.0,
-                                        0.0,
-                                        FRONT_BACK_LIGHT_LEVEL,
-                                    ),
-                                ]);
-                                self.indices.extend([
-                                    vertex_count,
-                                    vertex_count + 1,
-                                    vertex_count + 2,
-                                    vertex_count + 1,
-                                    vertex_count + 3,
-                                    vertex_count + 2,
-                                ]);
-                            }
-                            if south {
-                                let p = p + Vec3::Z * 0.9375;
-                                let vertex_count = self.vertices.len() as u32;
-                                self.vertices.extend([
-                                    v(p, 0.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
-                                    v(
-                                        p + Vec3::X,
-                                        1.0,
-                                        1.0,
-                                        FRONT_BACK_LIGHT_LEVEL,
-                                    ),
-                                    v(
-                                        p + Vec3::Y,
-                                        0.0,
-                                        0.0,
-                                        FRONT_BACK_LIGHT_LEVEL,
-                                    ),
-                                    v(
-                                        p + Vec3::X + Vec3::Y,
-                                        1.0,
-                                        0.0,
-                                        FRONT_BACK_LIGHT_LEVEL,
-                                    ),
-                                ]);
-                                self.indices.extend([
-                                    vertex_count,
-                                    vertex_count + 2,
-                                    vertex_count + 1,
-                                    vertex_count + 1,
-                                    vertex_count + 2,
-                                    vertex_count + 3,
-                                ]);
-                            }
-                            if east {
-                                let p = p + Vec3::X * 0.9375;
-                                let vertex_count = self.vertices.len() as u32;
-                                self.vertices.extend([
-                                    v(p, 0.0, 1.0, SIDE_LIGHT_LEVEL),
-                                    v(p + Vec3::Z, 1.0, 1.0, SIDE_LIGHT_LEVEL),
-                                    v(p + Vec3::Y, 0.0, 0.0, SIDE_LIGHT_LEVEL),
-                                    v(
-                                        p + Vec3::Z + Vec3::Y,
-                                        1.0,
-                                        0.0,
-                                        SIDE_LIGHT_LEVEL,
-                                    ),
-                                ]);
-                                self.indices.extend([
-                                    vertex_count,
-                                    vertex_count + 1,
-                                    vertex_count + 2,
-                                    vertex_count + 1,
-                                    vertex_count + 3,
-                                    vertex_count + 2,
-                                ]);
-                            }
-                            if west {
-                                let p = p + Vec3::X * 0.0625;
-                                let vertex_count = self.vertices.len() as u32;
-                                self.vertices.extend([
-                                    v(p, 0.0, 1.0, SIDE_LIGHT_LEVEL),
-                                    v(p + Vec3::Z, 1.0, 1.0, SIDE_LIGHT_LEVEL),
-                                    v(p + Vec3::Y, 0.0, 0.0, SIDE_LIGHT_LEVEL),
-                                    v(
-                                        p + Vec3::Z + Vec3::Y,
-                                        1.0,
-                                        0.0,
-                                        SIDE_LIGHT_LEVEL,
-                                    ),
-                                ]);
-                                self.indices.extend([
-                                    vertex_count,
-                                    vertex_count + 2,
-                                    vertex_count + 1,
-                                    vertex_count + 1,
-                                    vertex_count + 2,
-                                    vertex_count + 3,
-                                ]);
-                            }
-                            if up {
-                                let p = p + Vec3::Y * 0.9375;
-                                let vertex_count = self.vertices.len() as u32;
-                                self.vertices.extend([
-                                    v(p, 0.0, 1.0, BOTTOM_LIGHT_LEVEL),
-                                    v(
-                                        p + Vec3::X,
-                                        1.0,
-                                        1.0,
-                                        BOTTOM_LIGHT_LEVEL,
-                                    ),
-                                    v(
-                                        p + Vec3::Z,
-                                        0.0,
-                                        0.0,
-                                        BOTTOM_LIGHT_LEVEL,
-                                    ),
-                                    v(
-                                        p + Vec3::X + Vec3::Z,
-                                        1.0,
-                                        0.0,
-                                        TOP_LIGHT_LEVEL,
-                                    ),
-                                ]);
-                                self.indices.extend([
-                                    vertex_count,
-                                    vertex_count + 1,
-                                    vertex_count + 2,
-                                    vertex_count + 1,
-                                    vertex_count + 3,
-                                    vertex_count + 2,
-                                ]);
-                            }
-                            if down {
-                                let p = p + Vec3::Y * 0.0625;
-                                let vertex_count = self.vertices.len() as u32;
-                                self.vertices.extend([
-                                    v(p, 0.0, 1.0, TOP_LIGHT_LEVEL),
-                                    v(p + Vec3::X, 1.0, 1.0, TOP_LIGHT_LEVEL),
-                                    v(p + Vec3::Z, 0.0, 0.0, TOP_LIGHT_LEVEL),
-                                    v(
-                                        p + Vec3::X + Vec3::Z,
-                                        1.0,
-                                        0.0,
-                                        TOP_LIGHT_LEVEL,
-                                    ),
-                                ]);
-                                self.indices.extend([
-                                    vertex_count,
-                                    vertex_count + 2,
-                                    vertex_count + 1,
-                                    vertex_count + 1,
-                                    vertex_count + 2,
-                                    vertex_count + 3,
-                                ]);
+    fn inner_build_impl(
+        mut self,
+        world: &dyn BlockSource,
+        options: &Options,
+    ) -> Self {
+        let mut entities = entity::entities_by_section(world, options);
+        let area = &options.area;
+        let highlight = options.highlight.as_ref();
+        let section_range = |start: i32, end: i32| {
+            start.div_euclid(16)..=(end - 1).div_euclid(16)
+        };
+        for section_y in -4..20 {
+            for section_z in section_range(area.start.y, area.end.y) {
+                for section_x in section_range(area.start.x, area.end.x) {
+                    let pos = IVec3::new(section_x, section_y, section_z);
+                    let vertex_start = self.vertices.len();
+                    let index_start = self.indices.len();
+                    let min = (pos * 16).max(IVec3::new(
+                        area.start.x,
+                        i32::MIN,
+                        area.start.y,
+                    ));
+                    let max = (pos * 16 + 16).min(IVec3::new(
+                        area.end.x,
+                        i32::MAX,
+                        area.end.y,
+                    ));
+
+                    // TODO: perform greedy meshing
+                    // The section's blocks are read all at once, and only
+                    // their neighbours one at a time
+                    let blocks = world.section_at(pos).filter(|blocks| {
+                        !blocks.palette.iter().all(|block| block.is_air())
+                    });
+                    if let Some(blocks) = blocks {
+                        for y in min.y..max.y {
+                            for z in min.z..max.z {
+                                for x in min.x..max.x {
+                                    let p = IVec3 { x, y, z };
+                                    let offset = (p - pos * 16)
+                                        .dot(IVec3::new(1, 256, 16));
+                                    let block = blocks.palette[usize::from(
+                                        blocks.data[offset as usize],
+                                    )];
+                                    self.block(world, p, block);
+                                    if highlight
+                                        .is_some_and(|f| f.matches(&block))
+                                    {
+                                        self.highlights.add_box(
+                                            p.as_vec3(),
+                                            p.as_vec3() + 1.0,
+                                            HIGHLIGHT_COLOR,
+                                        );
+                                    }
+                                }
                             }
                         }
                     }
+                    for entity in entities.remove(&pos).unwrap_or_default() {
+                        self.entity(entity, options.entities);
+                    }
+
+                    let visibility = if options.cave_culling {
+                        Visibility::compute(|offset| {
+                            let p = pos * 16 + offset;
+                            min.cmple(p).all()
+                                && p.cmplt(max).all()
+                                && world.block_at(p).is_some_and(is_solid)
+                        })
+                    } else {
+                        Visibility::ALL
+                    };
+                    self.sections.push(Section {
+                        pos,
+                        vertices: vertex_start..self.vertices.len(),
+                        indices: index_start..self.indices.len(),
+                        min: (pos * 16).as_vec3(),
+                        max: (pos * 16 + 16).as_vec3(),
+                        visibility,
+                    });
                 }
             }
         }
+        if let Some(changes) = &options.changes {
+            changes.add_highlights(&mut self.highlights, area);
+        }
+        if let Some(overlay) = options.chunk_overlay {
+            overlay.add_highlights(world, area, &mut self.highlights);
+        }
 
         let light_at =
             |pos: IVec3| (f32::from(world.light_at(pos)) + 5.0) / 20.0;
@@ -557,19 +205,439 @@ impl Mesh {
             );
         }
 
+        if options.lod_distance.is_some() {
+            self.lods(world, options);
+        }
+
+        // Entities and block models can stick out of their section
+        for section in &mut self.sections {
+            for vertex in &self.vertices[section.vertices.clone()] {
+                section.min = section.min.min(vertex.pos);
+                section.max = section.max.max(vertex.pos);
+            }
+        }
         self
     }
 
+    fn block(
+        &mut self,
+        world: &dyn BlockSource,
+        p: IVec3,
+        block: Intern<Block>,
+    ) {
+        let index_start = self.indices.len();
+        match BlockModel::of(block) {
+            BlockModel::None => {}
+            BlockModel::SolidBlock | BlockModel::TransparentBlock => {
+                let texture_index = self.block_front_side_texture_id(block);
+
+                let v = |pos, u, v, light_level| Vertex {
+                    pos,
+                    uv: Vec2 { x: u, y: v },
+                    light_level,
+                    texture_index,
+                };
+
+                if !world.block_at(p - IVec3::X).is_some_and(is_solid) {
+                    let p = p.as_vec3();
+                    let vertex_count = self.vertices.len() as u32;
+                    self.vertices.extend([
+                        v(p, 0.0, 1.0, SIDE_LIGHT_LEVEL),
+                        v(p + Vec3::Z, 1.0, 1.0, SIDE_LIGHT_LEVEL),
+                        v(p + Vec3::Y, 0.0, 0.0, SIDE_LIGHT_LEVEL),
+                        v(p + Vec3::Z + Vec3::Y, 1.0, 0.0, SIDE_LIGHT_LEVEL),
+                    ]);
+                    self.indices.extend([
+                        vertex_count,
+                        vertex_count + 1,
+                        vertex_count + 2,
+                        vertex_count + 1,
+                        vertex_count + 3,
+                        vertex_count + 2,
+                    ]);
+                }
+                if !world.block_at(p + IVec3::X).is_some_and(is_solid) {
+                    let p = p.as_vec3() + Vec3::X;
+                    let vertex_count = self.vertices.len() as u32;
+                    self.vertices.extend([
+                        v(p, 0.0, 1.0, SIDE_LIGHT_LEVEL),
+                        v(p + Vec3::Z, 1.0, 1.0, SIDE_LIGHT_LEVEL),
+                        v(p + Vec3::Y, 0.0, 0.0, SIDE_LIGHT_LEVEL),
+                        v(p + Vec3::Z + Vec3::Y, 1.0, 0.0, SIDE_LIGHT_LEVEL),
+                    ]);
+                    self.indices.extend([
+                        vertex_count,
+                        vertex_count + 2,
+                        vertex_count + 1,
+                        vertex_count + 1,
+                        vertex_count + 2,
+                        vertex_count + 3,
+                    ]);
+                }
+                if !world.block_at(p - IVec3::Y).is_some_and(is_solid) {
+                    let p = p.as_vec3();
+                    let vertex_count = self.vertices.len() as u32;
+                    self.vertices.extend([
+                        v(p, 0.0, 1.0, BOTTOM_LIGHT_LEVEL),
+                        v(p + Vec3::X, 1.0, 1.0, BOTTOM_LIGHT_LEVEL),
+                        v(p + Vec3::Z, 0.0, 0.0, BOTTOM_LIGHT_LEVEL),
+                        v(p + Vec3::X + Vec3::Z, 1.0, 0.0, BOTTOM_LIGHT_LEVEL),
+                    ]);
+                    self.indices.extend([
+                        vertex_count,
+                        vertex_count + 1,
+                        vertex_count + 2,
+                        vertex_count + 1,
+                        vertex_count + 3,
+                        vertex_count + 2,
+                    ]);
+                }
+                if !world.block_at(p + IVec3::Y).is_some_and(is_solid) {
+                    let texture_index = self.block_top_texture_id(block);
+
+                    let v = |pos, u, v, light_level| Vertex {
+                        pos,
+                        uv: Vec2 { x: u, y: v },
+                        light_level,
+                        texture_index,
+                    };
+
+                    let p = p.as_vec3() + Vec3::Y;
+                    let vertex_count = self.vertices.len() as u32;
+                    self.vertices.extend([
+                        v(p, 0.0, 1.0, TOP_LIGHT_LEVEL),
+                        v(p + Vec3::X, 1.0, 1.0, TOP_LIGHT_LEVEL),
+                        v(p + Vec3::Z, 0.0, 0.0, TOP_LIGHT_LEVEL),
+                        v(p + Vec3::X + Vec3::Z, 1.0, 0.0, TOP_LIGHT_LEVEL),
+                    ]);
+                    self.indices.extend([
+                        vertex_count,
+                        vertex_count + 2,
+                        vertex_count + 1,
+                        vertex_count + 1,
+                        vertex_count + 2,
+                        vertex_count + 3,
+                    ]);
+                }
+                if !world.block_at(p - IVec3::Z).is_some_and(is_solid) {
+                    let p = p.as_vec3();
+                    let vertex_count = self.vertices.len() as u32;
+                    self.vertices.extend([
+                        v(p, 0.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
+                        v(p + Vec3::X, 1.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
+                        v(p + Vec3::Y, 0.0, 0.0, FRONT_BACK_LIGHT_LEVEL),
+                        v(
+                            p + Vec3::X + Vec3::Y,
+                            1.0,
+                            0.0,
+                            FRONT_BACK_LIGHT_LEVEL,
+                        ),
+                    ]);
+                    self.indices.extend([
+                        vertex_count,
+                        vertex_count + 2,
+                        vertex_count + 1,
+                        vertex_count + 1,
+                        vertex_count + 2,
+                        vertex_count + 3,
+                    ]);
+                }
+                if !world.block_at(p + IVec3::Z).is_some_and(is_solid) {
+                    let p = p.as_vec3() + Vec3::Z;
+                    let vertex_count = self.vertices.len() as u32;
+                    self.vertices.extend([
+                        v(p, 0.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
+                        v(p + Vec3::X, 1.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
+                        v(p + Vec3::Y, 0.0, 0.0, FRONT_BACK_LIGHT_LEVEL),
+                        v(
+                            p + Vec3::X + Vec3::Y,
+                            1.0,
+                            0.0,
+                            FRONT_BACK_LIGHT_LEVEL,
+                        ),
+                    ]);
+                    self.indices.extend([
+                        vertex_count,
+                        vertex_count + 1,
+                        vertex_count + 2,
+                        vertex_count + 1,
+                        vertex_count + 3,
+                        vertex_count + 2,
+                    ]);
+                }
+            }
+            BlockModel::Entity(model) => {
+                self.block_entity(model, p, world.block_entity_at(p));
+            }
+            BlockModel::Cross(texture_name) => {
+                let texture_index =
+                    self.allocate_texture(Texture::Block(texture_name)) as f32;
+
+                let v = |pos, u, v, light_level| Vertex {
+                    pos,
+                    uv: Vec2 { x: u, y: v },
+                    light_level,
+                    texture_index,
+                };
+
+                let p = p.as_vec3();
+                let vertex_count = self.vertices.len() as u32;
+                self.vertices.extend([
+                    v(p, 1.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
+                    v(p + Vec3::X + Vec3::Z, 0.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
+                    v(p + Vec3::Y, 1.0, 0.0, FRONT_BACK_LIGHT_LEVEL),
+                    v(
+                        p + Vec3::X + Vec3::Y + Vec3::Z,
+                        0.0,
+                        0.0,
+                        FRONT_BACK_LIGHT_LEVEL,
+                    ),
+                    v(p + Vec3::X, 1.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
+                    v(p + Vec3::Z, 0.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
+                    v(p + Vec3::X + Vec3::Y, 1.0, 0.0, FRONT_BACK_LIGHT_LEVEL),
+                    v(p + Vec3::Y + Vec3::Z, 0.0, 0.0, FRONT_BACK_LIGHT_LEVEL),
+                ]);
+                self.indices.extend([
+                    vertex_count,
+                    vertex_count + 1,
+                    vertex_count + 3,
+                    vertex_count,
+                    vertex_count + 3,
+                    vertex_count + 2,
+                    vertex_count,
+                    vertex_count + 3,
+                    vertex_count + 1,
+                    vertex_count,
+                    vertex_count + 2,
+                    vertex_count + 3,
+                    vertex_count + 4,
+                    vertex_count + 5,
+                    vertex_count + 7,
+                    vertex_count + 4,
+                    vertex_count + 7,
+                    vertex_count + 6,
+                    vertex_count + 4,
+                    vertex_count + 7,
+                    vertex_count + 5,
+                    vertex_count + 4,
+                    vertex_count + 6,
+                    vertex_count + 7,
+                ]);
+            }
+            BlockModel::FlatDirectional {
+                texture_name,
+                north,
+                south,
+                east,
+                west,
+                up,
+                down,
+            } => {
+                let texture_index =
+                    self.allocate_texture(Texture::Block(texture_name)) as f32;
+
+                let v = |pos, u, v, light_level| Vertex {
+                    pos,
+                    uv: Vec2 { x: u, y: v },
+                    light_level,
+                    texture_index,
+                };
+
+                let p = p.as_vec3();
+
+                if north {
+                    let p = p + Vec3::Z * 0.0625;
+                    let vertex_count = self.vertices.len() as u32;
+                    self.vertices.extend([
+                        v(p, 0.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
+                        v(p + Vec3::X, 1.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
+                        v(p + Vec3::Y, 0.0, 0.0, FRONT_BACK_LIGHT_LEVEL),
+                        v(
+                            p + Vec3::X + Vec3::Y,
+                            1.0,
+                            0.0,
+                            FRONT_BACK_LIGHT_LEVEL,
+                        ),
+                    ]);
+                    self.indices.extend([
+                        vertex_count,
+                        vertex_count + 1,
+                        vertex_count + 2,
+                        vertex_count + 1,
+                        vertex_count + 3,
+                        vertex_count + 2,
+                    ]);
+                }
+                if south {
+                    let p = p + Vec3::Z * 0.9375;
+                    let vertex_count = self.vertices.len() as u32;
+                    self.vertices.extend([
+                        v(p, 0.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
+                        v(p + Vec3::X, 1.0, 1.0, FRONT_BACK_LIGHT_LEVEL),
+                        v(p + Vec3::Y, 0.0, 0.0, FRONT_BACK_LIGHT_LEVEL),
+                        v(
+                            p + Vec3::X + Vec3::Y,
+                            1.0,
+                            0.0,
+                            FRONT_BACK_LIGHT_LEVEL,
+                        ),
+                    ]);
+                    self.indices.extend([
+                        vertex_count,
+                        vertex_count + 2,
+                        vertex_count + 1,
+                        vertex_count + 1,
+                        vertex_count + 2,
+                        vertex_count + 3,
+                    ]);
+                }
+                if east {
+                    let p = p + Vec3::X * 0.9375;
+                    let vertex_count = self.vertices.len() as u32;
+                    self.vertices.extend([
+                        v(p, 0.0, 1.0, SIDE_LIGHT_LEVEL),
+                        v(p + Vec3::Z, 1.0, 1.0, SIDE_LIGHT_LEVEL),
+                        v(p + Vec3::Y, 0.0, 0.0, SIDE_LIGHT_LEVEL),
+                        v(p + Vec3::Z + Vec3::Y, 1.0, 0.0, SIDE_LIGHT_LEVEL),
+                    ]);
+                    self.indices.extend([
+                        vertex_count,
+                        vertex_count + 1,
+                        vertex_count + 2,
+                        vertex_count + 1,
+                        vertex_count + 3,
+                        vertex_count + 2,
+                    ]);
+                }
+                if west {
+                    let p = p + Vec3::X * 0.0625;
+                    let vertex_count = self.vertices.len() as u32;
+                    self.vertices.extend([
+                        v(p, 0.0, 1.0, SIDE_LIGHT_LEVEL),
+                        v(p + Vec3::Z, 1.0, 1.0, SIDE_LIGHT_LEVEL),
+                        v(p + Vec3::Y, 0.0, 0.0, SIDE_LIGHT_LEVEL),
+                        v(p + Vec3::Z + Vec3::Y, 1.0, 0.0, SIDE_LIGHT_LEVEL),
+                    ]);
+                    self.indices.extend([
+                        vertex_count,
+                        vertex_count + 2,
+                        vertex_count + 1,
+                        vertex_count + 1,
+                        vertex_count + 2,
+                        vertex_count + 3,
+                    ]);
+                }
+                if up {
+                    let p = p + Vec3::Y * 0.9375;
+                    let vertex_count = self.vertices.len() as u32;
+                    self.vertices.extend([
+                        v(p, 0.0, 1.0, BOTTOM_LIGHT_LEVEL),
+                        v(p + Vec3::X, 1.0, 1.0, BOTTOM_LIGHT_LEVEL),
+                        v(p + Vec3::Z, 0.0, 0.0, BOTTOM_LIGHT_LEVEL),
+                        v(p + Vec3::X + Vec3::Z, 1.0, 0.0, TOP_LIGHT_LEVEL),
+                    ]);
+                    self.indices.extend([
+                        vertex_count,
+                        vertex_count + 1,
+                        vertex_count + 2,
+                        vertex_count + 1,
+                        vertex_count + 3,
+                        vertex_count + 2,
+                    ]);
+                }
+                if down {
+                    let p = p + Vec3::Y * 0.0625;
+                    let vertex_count = self.vertices.len() as u32;
+                    self.vertices.extend([
+                        v(p, 0.0, 1.0, TOP_LIGHT_LEVEL),
+                        v(p + Vec3::X, 1.0, 1.0, TOP_LIGHT_LEVEL),
+                        v(p + Vec3::Z, 0.0, 0.0, TOP_LIGHT_LEVEL),
+                        v(p + Vec3::X + Vec3::Z, 1.0, 0.0, TOP_LIGHT_LEVEL),
+                    ]);
+                    self.indices.extend([
+                        vertex_count,
+                        vertex_count + 2,
+                        vertex_count + 1,
+                        vertex_count + 1,
+                        vertex_count + 2,
+                        vertex_count + 3,
+                    ]);
+                }
+            }
+        }
+
+        let indices = index_start..self.indices.len();
+        if indices.is_empty() {
+            return;
+        }
+        match self.blocks.last_mut() {
+            Some((last_block, last_indices))
+                if *last_block == block
+                    && last_indices.end == indices.start =>
+            {
+                last_indices.end = indices.end;
+            }
+            _ => self.blocks.push((block, indices)),
+        }
+    }
+
     fn block_top_texture_id(&mut self, block: Intern<Block>) -> f32 {
-        self.allocate_texture(block_top_texture_name(block)) as f32
+        self.allocate_texture(Texture::Block(block_top_texture_name(block)))
+            as f32
     }
 
     fn block_front_side_texture_id(&mut self, block: Intern<Block>) -> f32 {
-        self.allocate_texture(block_front_side_texture_name(block)) as f32
+        self.allocate_texture(Texture::Block(block_front_side_texture_name(
+            block,
+        ))) as f32
     }
 
-    fn allocate_texture(&mut self, texture_name: &'static str) -> usize {
-        self.texture_names.insert_full(texture_name).0
+    fn allocate_texture(&mut self, texture: Texture) -> usize {
+        self.textures.insert_full(texture).0
+    }
+
+    /// Adds a quad whose corners are given counterclockwise as seen from the
+    /// front, each with its texture coordinates, after applying `transform`.
+    fn quad(
+        &mut self,
+        transform: Affine3A,
+        corners: [(Vec3, Vec2); 4],
+        texture: Texture,
+    ) {
+        let texture_index = self.allocate_texture(texture) as f32;
+        let corners =
+            corners.map(|(pos, uv)| (transform.transform_point3(pos), uv));
+        let normal = (corners[1].0 - corners[0].0)
+            .cross(corners[2].0 - corners[0].0)
+            .normalize_or_zero();
+        let light_level =
+            if normal.y.abs() >= normal.x.abs().max(normal.z.abs()) {
+                if normal.y > 0.0 {
+                    TOP_LIGHT_LEVEL
+                } else {
+                    BOTTOM_LIGHT_LEVEL
+                }
+            } else if normal.z.abs() >= normal.x.abs() {
+                FRONT_BACK_LIGHT_LEVEL
+            } else {
+                SIDE_LIGHT_LEVEL
+            };
+
+        let vertex_count = self.vertices.len() as u32;
+        self.vertices.extend(corners.map(|(pos, uv)| Vertex {
+            pos,
+            uv,
+            light_level,
+            texture_index,
+        }));
+        self.indices.extend([
+            vertex_count,
+            vertex_count + 1,
+            vertex_count + 2,
+            vertex_count,
+            vertex_count + 2,
+            vertex_count + 3,
+        ]);
     }
 }
 
@@ -611,6 +679,7 @@ enum BlockModel {
     SolidBlock,
     TransparentBlock,
     Cross(&'static str),
+    Entity(block_entity::Model),
     FlatDirectional {
         texture_name: &'static str,
         north: bool,
@@ -626,6 +695,8 @@ impl BlockModel {
     fn of(block: Intern<Block>) -> Self {
         if block == *AIR {
             Self::None
+        } else if let Some(model) = block_entity::Model::of(block) {
+            Self::Entity(model)
         } else {
             match &**block.name {
                 "minecraft:cave_air" => Self::None,
@@ -667,14 +738,140 @@ impl BlockModel {
     }
 }
 
+/// Leaks a string so that it can be used in a [`Texture`], only once for
+/// each distinct string.
+fn intern(s: String) -> &'static str {
+    Intern::<Box<str>>::from(s.into_boxed_str()).as_ref()
+}
+
 fn lerp(from: f32, to: f32, amount: f32) -> f32 {
     (to - from).mul_add(amount, from)
 }
 
+/// A corner of a face of the mesh, laid out the way the shaders read it.
 #[repr(C)]
 pub struct Vertex {
+    /// Where the corner is in the world.
     pub pos: Vec3,
+    /// Where the corner is within its texture, from 0 to 1.
     pub uv: Vec2,
+    /// How brightly the face is lit, which depends on which way it faces.
     pub light_level: f32,
+    /// The index of the face's texture in the mesh's textures.
     pub texture_index: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block_source::Volume, test_util, BlockFilter};
+    use glam::IVec2;
+
+    /// The number of quads in the mesh of some blocks.
+    fn faces(blocks: &[(IVec3, &str)]) -> usize {
+        let mut volume = Volume::new(IVec3::new(32, 16, 16));
+        for &(pos, block) in blocks {
+            volume.set_block(pos, Block::parse(block));
+        }
+        let options = test_util::options(IVec2::ZERO..IVec2::new(32, 16));
+        let mesh = Mesh::build(&volume, &options);
+        assert_eq!(mesh.indices.len() % 6, 0);
+        mesh.indices.len() / 6
+    }
+
+    #[test]
+    fn counts_faces_of_solid_blocks() {
+        assert_eq!(faces(&[]), 0);
+        assert_eq!(faces(&[(IVec3::new(3, 4, 5), "stone")]), 6);
+        assert_eq!(
+            faces(&[
+                (IVec3::new(3, 4, 5), "stone"),
+                (IVec3::new(3, 5, 5), "dirt")
+            ]),
+            10
+        );
+        assert_eq!(
+            faces(&[
+                (IVec3::new(3, 4, 5), "stone"),
+                (IVec3::new(4, 5, 5), "dirt")
+            ]),
+            12
+        );
+    }
+
+    #[test]
+    fn hides_faces_between_sections() {
+        assert_eq!(
+            faces(&[
+                (IVec3::new(15, 0, 0), "stone"),
+                (IVec3::new(16, 0, 0), "stone")
+            ]),
+            10
+        );
+    }
+
+    #[test]
+    fn only_draws_the_outside_of_a_cube() {
+        let cube: Vec<_> = (0..27)
+            .map(|i| (IVec3::new(i % 3, i / 9, i / 3 % 3) + 7, "stone"))
+            .collect();
+        assert_eq!(faces(&cube), 54);
+    }
+
+    #[test]
+    fn draws_faces_next_to_transparent_blocks() {
+        assert_eq!(
+            faces(&[
+                (IVec3::new(3, 4, 5), "stone"),
+                (IVec3::new(4, 4, 5), "spruce_leaves"),
+            ]),
+            11
+        );
+        // Two crossed planes, seen from both sides
+        assert_eq!(faces(&[(IVec3::new(3, 4, 5), "grass")]), 4);
+    }
+
+    #[test]
+    fn remembers_which_block_each_face_belongs_to() {
+        let volume = |pos: IVec3| {
+            Some(if pos == IVec3::new(1, 1, 1) {
+                Block::parse("stone")
+            } else if pos == IVec3::new(1, 2, 1) {
+                Block::parse("dirt")
+            } else {
+                *AIR
+            })
+        };
+        let options = test_util::options(IVec2::ZERO..IVec2::new(4, 4));
+        let mesh = Mesh::build(&volume, &options);
+        let blocks: Vec<_> = mesh
+            .blocks
+            .iter()
+            .map(|(block, indices)| (&**block.name, indices.len() / 6))
+            .collect();
+        assert_eq!(blocks, [("minecraft:stone", 5), ("minecraft:dirt", 5)]);
+    }
+
+    #[test]
+    fn highlights_matching_blocks() {
+        let volume = |pos: IVec3| {
+            Some(match pos.to_array() {
+                [1, 1, 1] => Block::parse("diamond_ore"),
+                [0..=3, 0..=3, 0..=3] => Block::parse("stone"),
+                _ => *AIR,
+            })
+        };
+        let mut options = test_util::options(IVec2::ZERO..IVec2::new(4, 4));
+        options.highlight = Some(BlockFilter::parse("diamond_ore").unwrap());
+        let mesh = Mesh::build(&volume, &options);
+        // The ore is buried, so only the outside of the stone is drawn
+        assert_eq!(mesh.indices.len() / 6, 6 * 16);
+        assert_eq!(mesh.highlights.indices.len() / 6, 6);
+        assert_eq!(mesh.highlights.vertices[0].pos, Vec3::ONE);
+
+        options.hide_unhighlighted = true;
+        let mesh = Mesh::build(&volume, &options);
+        assert_eq!(mesh.indices.len() / 6, 6);
+        assert_eq!(mesh.highlights.indices.len() / 6, 6);
+    }
+}
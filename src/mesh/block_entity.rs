@@ -0,0 +1,811 @@
+use super::{
+    cuboid::{cuboid, Cuboid, Face, Sheet},
+    intern, Mesh,
+};
+use crate::{
+    chunk::{Block, BlockEntity},
+    texture::{Rect, Texture},
+};
+use glam::{Affine3A, IVec3, Mat3, Vec2, Vec3};
+use internment::Intern;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_8, PI};
+
+/// Text on signs is drawn at this many model pixels per font pixel.
+const SIGN_TEXT_SCALE: f32 = 1.0 / 6.0;
+const HANGING_SIGN_TEXT_SCALE: f32 = 0.15;
+const SIGN_LINE_HEIGHT: f32 = 10.0;
+
+/// Built-in models for blocks that Minecraft draws with a block entity
+/// renderer instead of a block model.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Model {
+    Chest {
+        texture: &'static str,
+        half: ChestHalf,
+        yaw: f32,
+    },
+    Bed {
+        texture: &'static str,
+        head: bool,
+        yaw: f32,
+    },
+    Sign {
+        texture: &'static str,
+        kind: SignKind,
+        yaw: f32,
+    },
+    Banner {
+        color: [u8; 3],
+        wall: bool,
+        yaw: f32,
+    },
+    ShulkerBox {
+        texture: &'static str,
+        rotation: Mat3,
+    },
+    Head {
+        kind: HeadKind,
+        wall: bool,
+        yaw: f32,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ChestHalf {
+    Single,
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SignKind {
+    Standing,
+    Wall,
+    Hanging,
+    WallHanging,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum HeadKind {
+    Player,
+    Skeleton,
+    WitherSkeleton,
+    Zombie,
+    Creeper,
+    Piglin,
+    Dragon,
+}
+
+impl Model {
+    pub fn of(block: Intern<Block>) -> Option<Self> {
+        let name = block.name.strip_prefix("minecraft:")?;
+        let property = |key| block.properties.get(key).map(String::as_str);
+
+        if let Some(texture) = match name {
+            "chest" => Some("normal"),
+            "trapped_chest" => Some("trapped"),
+            "ender_chest" => Some("ender"),
+            _ => None,
+        } {
+            let half = match property("type") {
+                Some("left") => ChestHalf::Left,
+                Some("right") => ChestHalf::Right,
+                _ => ChestHalf::Single,
+            };
+            let texture = match half {
+                ChestHalf::Single => intern(format!("entity/chest/{texture}")),
+                ChestHalf::Left => {
+                    intern(format!("entity/chest/{texture}_left"))
+                }
+                ChestHalf::Right => {
+                    intern(format!("entity/chest/{texture}_right"))
+                }
+            };
+            return Some(Self::Chest {
+                texture,
+                half,
+                yaw: facing_yaw(block),
+            });
+        }
+
+        if let Some(color) = name.strip_suffix("_bed") {
+            return Some(Self::Bed {
+                texture: intern(format!("entity/bed/{color}")),
+                head: property("part") == Some("head"),
+                yaw: facing_yaw(block),
+            });
+        }
+
+        if let Some(wood) = name.strip_suffix("_wall_hanging_sign") {
+            return Some(Self::Sign {
+                texture: intern(format!("entity/signs/hanging/{wood}")),
+                kind: SignKind::WallHanging,
+                yaw: facing_yaw(block),
+            });
+        }
+        if let Some(wood) = name.strip_suffix("_hanging_sign") {
+            return Some(Self::Sign {
+                texture: intern(format!("entity/signs/hanging/{wood}")),
+                kind: SignKind::Hanging,
+                yaw: rotation_yaw(block),
+            });
+        }
+        if let Some(wood) = name.strip_suffix("_wall_sign") {
+            return Some(Self::Sign {
+                texture: intern(format!("entity/signs/{wood}")),
+                kind: SignKind::Wall,
+                yaw: facing_yaw(block),
+            });
+        }
+        if let Some(wood) = name.strip_suffix("_sign") {
+            return Some(Self::Sign {
+                texture: intern(format!("entity/signs/{wood}")),
+                kind: SignKind::Standing,
+                yaw: rotation_yaw(block),
+            });
+        }
+
+        if let Some(color) = name.strip_suffix("_wall_banner") {
+            return Some(Self::Banner {
+                color: dye_color(color)?,
+                wall: true,
+                yaw: facing_yaw(block),
+            });
+        }
+        if let Some(color) = name.strip_suffix("_banner") {
+            return Some(Self::Banner {
+                color: dye_color(color)?,
+                wall: false,
+                yaw: rotation_yaw(block),
+            });
+        }
+
+        if let Some(color) = name.strip_suffix("shulker_box") {
+            let texture = match color.strip_suffix('_') {
+                Some(color) => {
+                    intern(format!("entity/shulker/shulker_{color}"))
+                }
+                None => "entity/shulker/shulker",
+            };
+            let rotation = match property("facing") {
+                Some("down") => Mat3::from_rotation_x(PI),
+                Some("north") => Mat3::from_rotation_x(-FRAC_PI_2),
+                Some("south") => Mat3::from_rotation_x(FRAC_PI_2),
+                Some("east") => Mat3::from_rotation_z(-FRAC_PI_2),
+                Some("west") => Mat3::from_rotation_z(FRAC_PI_2),
+                _ => Mat3::IDENTITY,
+            };
+            return Some(Self::ShulkerBox { texture, rotation });
+        }
+
+        let (kind, wall) = match name {
+            "player_head" => (HeadKind::Player, false),
+            "player_wall_head" => (HeadKind::Player, true),
+            "skeleton_skull" => (HeadKind::Skeleton, false),
+            "skeleton_wall_skull" => (HeadKind::Skeleton, true),
+            "wither_skeleton_skull" => (HeadKind::WitherSkeleton, false),
+            "wither_skeleton_wall_skull" => (HeadKind::WitherSkeleton, true),
+            "zombie_head" => (HeadKind::Zombie, false),
+            "zombie_wall_head" => (HeadKind::Zombie, true),
+            "creeper_head" => (HeadKind::Creeper, false),
+            "creeper_wall_head" => (HeadKind::Creeper, true),
+            "piglin_head" => (HeadKind::Piglin, false),
+            "piglin_wall_head" => (HeadKind::Piglin, true),
+            "dragon_head" => (HeadKind::Dragon, false),
+            "dragon_wall_head" => (HeadKind::Dragon, true),
+            _ => return None,
+        };
+        Some(Self::Head {
+            kind,
+            wall,
+            yaw: if wall {
+                facing_yaw(block)
+            } else {
+                rotation_yaw(block)
+            },
+        })
+    }
+}
+
+/// The rotation around the Y axis of a block with a horizontal `facing`
+/// property, for models that face south when unrotated.
+fn facing_yaw(block: Intern<Block>) -> f32 {
+    match block.properties.get("facing").map(String::as_str) {
+        Some("west") => -FRAC_PI_2,
+        Some("north") => PI,
+        Some("east") => FRAC_PI_2,
+        _ => 0.0,
+    }
+}
+
+/// The rotation around the Y axis of a block with a 16-step `rotation`
+/// property, for models that face south when unrotated.
+fn rotation_yaw(block: Intern<Block>) -> f32 {
+    let rotation = block
+        .properties
+        .get("rotation")
+        .and_then(|rotation| rotation.parse::<u8>().ok())
+        .unwrap_or(0);
+    -f32::from(rotation) * FRAC_PI_8
+}
+
+const DYE_COLORS: [&str; 16] = [
+    "white",
+    "orange",
+    "magenta",
+    "light_blue",
+    "yellow",
+    "lime",
+    "pink",
+    "gray",
+    "light_gray",
+    "cyan",
+    "purple",
+    "blue",
+    "brown",
+    "green",
+    "red",
+    "black",
+];
+
+/// The color used to tint textures such as banner patterns.
+fn dye_color(name: &str) -> Option<[u8; 3]> {
+    Some(match name {
+        "white" => [0xf9, 0xff, 0xfe],
+        "orange" => [0xf9, 0x80, 0x1d],
+        "magenta" => [0xc7, 0x4e, 0xbd],
+        "light_blue" => [0x3a, 0xb3, 0xda],
+        "yellow" => [0xfe, 0xd8, 0x3d],
+        "lime" => [0x80, 0xc7, 0x1f],
+        "pink" => [0xf3, 0x8b, 0xaa],
+        "gray" => [0x47, 0x4f, 0x52],
+        "light_gray" => [0x9d, 0x9d, 0x97],
+        "cyan" => [0x16, 0x9c, 0x9c],
+        "purple" => [0x89, 0x32, 0xb8],
+        "blue" => [0x3c, 0x44, 0xaa],
+        "brown" => [0x83, 0x54, 0x32],
+        "green" => [0x5e, 0x7c, 0x16],
+        "red" => [0xb0, 0x2e, 0x26],
+        "black" => [0x1d, 0x1d, 0x21],
+        _ => return None,
+    })
+}
+
+/// The color of text written on signs with a dye.
+fn text_color(name: &str) -> [u8; 3] {
+    match name {
+        "white" => [0xff, 0xff, 0xff],
+        "orange" => [0xff, 0x68, 0x1f],
+        "magenta" => [0xff, 0x00, 0xff],
+        "light_blue" => [0x9a, 0xc0, 0xcd],
+        "yellow" => [0xff, 0xff, 0x00],
+        "lime" => [0xbf, 0xff, 0x00],
+        "pink" => [0xff, 0x69, 0xb4],
+        "gray" => [0x80, 0x80, 0x80],
+        "light_gray" => [0xd3, 0xd3, 0xd3],
+        "cyan" => [0x00, 0xff, 0xff],
+        "purple" => [0xa0, 0x20, 0xf0],
+        "blue" => [0x00, 0x00, 0xff],
+        "brown" => [0x8b, 0x45, 0x13],
+        "green" => [0x00, 0xff, 0x00],
+        "red" => [0xff, 0x00, 0x00],
+        _ => [0x00, 0x00, 0x00],
+    }
+}
+
+impl Mesh {
+    pub(super) fn block_entity(
+        &mut self,
+        model: Model,
+        pos: IVec3,
+        block_entity: Option<&BlockEntity>,
+    ) {
+        let origin = pos.as_vec3() + Vec3::new(0.5, 0.0, 0.5);
+
+        match model {
+            Model::Chest { texture, half, yaw } => {
+                let sheet = Sheet::new(texture, 64);
+                let transform = Affine3A::from_translation(origin)
+                    * Affine3A::from_rotation_y(yaw)
+                    * Affine3A::from_translation(Vec3::new(-0.5, 0.0, -0.5))
+                    * Affine3A::from_scale(Vec3::splat(1.0 / 16.0));
+                let (x, width, lock_x) = match half {
+                    ChestHalf::Single => (1.0, 14.0, 7.0),
+                    ChestHalf::Left => (1.0, 15.0, 15.0),
+                    ChestHalf::Right => (0.0, 15.0, 0.0),
+                };
+                let lock_width =
+                    if half == ChestHalf::Single { 2.0 } else { 1.0 };
+                for cuboid in [
+                    cuboid([0, 19], [x, 0.0, 1.0], [width, 10.0, 14.0]),
+                    cuboid([0, 0], [x, 9.0, 1.0], [width, 5.0, 14.0]),
+                    cuboid([0, 0], [lock_x, 6.0, 14.0], [lock_width, 4.0, 1.0]),
+                ] {
+                    self.cuboid(transform, sheet, cuboid, |_| true);
+                }
+            }
+            Model::Bed { texture, head, yaw } => {
+                let sheet = Sheet::new(texture, 64);
+                // Beds are modelled standing upright and then tipped over
+                let transform = Affine3A::from_translation(origin)
+                    * Affine3A::from_rotation_y(yaw + PI)
+                    * Affine3A::from_translation(Vec3::new(-0.5, 0.5625, -0.5))
+                    * Affine3A::from_rotation_x(FRAC_PI_2)
+                    * Affine3A::from_scale(Vec3::splat(1.0 / 16.0));
+                let (mattress_v, leg_v, leg_y) =
+                    if head { (0, 6, 0.0) } else { (22, 0, 13.0) };
+                self.cuboid(
+                    transform,
+                    sheet,
+                    cuboid([0, mattress_v], [0.0; 3], [16.0, 16.0, 6.0]),
+                    |_| true,
+                );
+                for (leg_x, v) in [(0.0, leg_v), (13.0, leg_v + 12)] {
+                    self.cuboid(
+                        transform,
+                        sheet,
+                        cuboid([50, v], [leg_x, leg_y, 6.0], [3.0; 3]),
+                        |_| true,
+                    );
+                }
+            }
+            Model::Sign { texture, kind, yaw } => {
+                self.sign(texture, kind, origin, yaw, block_entity);
+            }
+            Model::Banner { color, wall, yaw } => {
+                self.banner(color, wall, origin, yaw, block_entity);
+            }
+            Model::ShulkerBox { texture, rotation } => {
+                let sheet = Sheet::new(texture, 64);
+                let transform =
+                    Affine3A::from_translation(origin + Vec3::Y * 0.5)
+                        * Affine3A::from_mat3(rotation)
+                        * Affine3A::from_scale(Vec3::new(1.0, -1.0, -1.0))
+                        * Affine3A::from_translation(Vec3::NEG_Y)
+                        * Affine3A::from_scale(Vec3::splat(1.0 / 16.0));
+                for cuboid in [
+                    cuboid([0, 0], [-8.0, 8.0, -8.0], [16.0, 12.0, 16.0]),
+                    cuboid([0, 28], [-8.0, 16.0, -8.0], [16.0, 8.0, 16.0]),
+                ] {
+                    self.cuboid(transform, sheet, cuboid, |_| true);
+                }
+            }
+            Model::Head { kind, wall, yaw } => {
+                let (offset, scale) = match (wall, kind) {
+                    (false, HeadKind::Dragon) => (Vec3::ZERO, 0.75),
+                    (false, _) => (Vec3::ZERO, 1.0),
+                    (true, HeadKind::Dragon) => {
+                        (Vec3::new(0.0, 0.25, 0.25), 0.75)
+                    }
+                    (true, _) => (Vec3::new(0.0, 0.25, 0.25), 1.0),
+                };
+                // Heads are modelled upside down and facing north
+                let transform = Affine3A::from_translation(origin)
+                    * Affine3A::from_rotation_y(yaw + PI)
+                    * Affine3A::from_translation(offset)
+                    * Affine3A::from_rotation_z(PI)
+                    * Affine3A::from_scale(Vec3::splat(scale / 16.0));
+                let head = cuboid([0, 0], [-4.0, -8.0, -4.0], [8.0; 3]);
+                match kind {
+                    HeadKind::Player => {
+                        let sheet = Sheet::new("entity/player/wide/steve", 64);
+                        self.cuboid(transform, sheet, head, |_| true);
+                        self.cuboid(
+                            transform,
+                            sheet,
+                            Cuboid {
+                                uv: [32, 0],
+                                inflate: 0.25,
+                                ..head
+                            },
+                            |_| true,
+                        );
+                    }
+                    HeadKind::Skeleton => self.cuboid(
+                        transform,
+                        Sheet::new("entity/skeleton/skeleton", 64),
+                        head,
+                        |_| true,
+                    ),
+                    HeadKind::WitherSkeleton => self.cuboid(
+                        transform,
+                        Sheet::new("entity/skeleton/wither_skeleton", 64),
+                        head,
+                        |_| true,
+                    ),
+                    HeadKind::Zombie => self.cuboid(
+                        transform,
+                        Sheet::new("entity/zombie/zombie", 64),
+                        head,
+                        |_| true,
+                    ),
+                    HeadKind::Creeper => self.cuboid(
+                        transform,
+                        Sheet::new("entity/creeper/creeper", 64),
+                        head,
+                        |_| true,
+                    ),
+                    HeadKind::Piglin => self.cuboid(
+                        transform,
+                        Sheet::new("entity/piglin/piglin", 64),
+                        cuboid([0, 0], [-5.0, -8.0, -4.0], [10.0, 8.0, 8.0]),
+                        |_| true,
+                    ),
+                    HeadKind::Dragon => self.cuboid(
+                        transform,
+                        Sheet::new("entity/enderdragon/dragon", 256),
+                        cuboid([112, 30], [-8.0, -16.0, -8.0], [16.0; 3]),
+                        |_| true,
+                    ),
+                }
+            }
+        }
+    }
+
+    fn sign(
+        &mut self,
+        texture: &'static str,
+        kind: SignKind,
+        origin: Vec3,
+        yaw: f32,
+        block_entity: Option<&BlockEntity>,
+    ) {
+        let hanging = matches!(kind, SignKind::Hanging | SignKind::WallHanging);
+        let sheet = Sheet::new(texture, 64);
+        let (offset, scale) = match kind {
+            SignKind::Standing => (Vec3::new(0.0, 0.5, 0.0), 2.0 / 3.0),
+            SignKind::Wall => (Vec3::new(0.0, 0.1875, -0.4375), 2.0 / 3.0),
+            SignKind::Hanging | SignKind::WallHanging => {
+                (Vec3::new(0.0, 0.625, 0.0), 1.0)
+            }
+        };
+        // Signs are modelled upside down and facing north, so after flipping
+        // them the front faces south
+        let transform = Affine3A::from_translation(origin)
+            * Affine3A::from_rotation_y(yaw)
+            * Affine3A::from_translation(offset)
+            * Affine3A::from_scale(Vec3::new(scale, -scale, -scale) / 16.0);
+
+        let board = if hanging {
+            cuboid([0, 12], [-7.0, 0.0, -1.0], [14.0, 10.0, 2.0])
+        } else {
+            cuboid([0, 0], [-12.0, -14.0, -1.0], [24.0, 12.0, 2.0])
+        };
+        self.cuboid(transform, sheet, board, |_| true);
+        match kind {
+            SignKind::Standing => self.cuboid(
+                transform,
+                sheet,
+                cuboid([0, 14], [-1.0, -2.0, -1.0], [2.0, 14.0, 2.0]),
+                |_| true,
+            ),
+            SignKind::WallHanging => self.cuboid(
+                transform,
+                sheet,
+                cuboid([0, 0], [-8.0, -6.0, -2.0], [16.0, 2.0, 4.0]),
+                |_| true,
+            ),
+            SignKind::Wall | SignKind::Hanging => {}
+        }
+
+        let Some(block_entity) = block_entity else {
+            return;
+        };
+        let (text_scale, center_y) = if hanging {
+            (HANGING_SIGN_TEXT_SCALE, 5.0)
+        } else {
+            (SIGN_TEXT_SCALE, -8.0)
+        };
+        for (side, back) in sign_sides(block_entity) {
+            // Text space has X pointing right and Y pointing up as seen from
+            // the side being written on, which is just outside the board
+            let (z, flip) = if back { (1.01, -1.0) } else { (-1.01, 1.0) };
+            let text_transform = transform
+                * Affine3A::from_translation(Vec3::new(0.0, center_y, z))
+                * Affine3A::from_scale(
+                    Vec3::new(flip, -1.0, -flip) * text_scale,
+                );
+            self.sign_text(text_transform, &side);
+        }
+    }
+
+    fn sign_text(&mut self, transform: Affine3A, side: &SignSide) {
+        let color = side.color.map(|channel| {
+            if side.glowing {
+                channel
+            } else {
+                (f32::from(channel) * 0.4) as u8
+            }
+        });
+        let line_count = side.lines.len() as f32;
+        for (i, line) in side.lines.iter().enumerate() {
+            let width = line.chars().map(glyph_advance).sum::<f32>();
+            let mut x = -width / 2.0;
+            let y = (line_count / 2.0 - i as f32) * SIGN_LINE_HEIGHT - 1.0;
+            for c in line.chars() {
+                if let Some(index) = glyph_index(c) {
+                    let texture = Texture::Sprite {
+                        path: "font/ascii",
+                        rect: Rect {
+                            x: u16::from(index % 16) * 8,
+                            y: u16::from(index / 16) * 8,
+                            width: 8,
+                            height: 8,
+                            image_width: 128,
+                        },
+                        tint: Some(color),
+                    };
+                    self.quad(
+                        transform,
+                        [
+                            (Vec3::new(x, y - 8.0, 0.0), Vec2::new(0.0, 1.0)),
+                            (Vec3::new(x + 8.0, y - 8.0, 0.0), Vec2::ONE),
+                            (Vec3::new(x + 8.0, y, 0.0), Vec2::new(1.0, 0.0)),
+                            (Vec3::new(x, y, 0.0), Vec2::ZERO),
+                        ],
+                        texture,
+                    );
+                }
+                x += glyph_advance(c);
+            }
+        }
+    }
+
+    fn banner(
+        &mut self,
+        color: [u8; 3],
+        wall: bool,
+        origin: Vec3,
+        yaw: f32,
+        block_entity: Option<&BlockEntity>,
+    ) {
+        let sheet = Sheet::new("entity/banner_base", 64);
+        let offset = if wall {
+            Vec3::new(0.0, -0.479_166_7, -0.4375)
+        } else {
+            Vec3::new(0.0, 0.5, 0.0)
+        };
+        let transform = Affine3A::from_translation(origin)
+            * Affine3A::from_rotation_y(yaw)
+            * Affine3A::from_translation(offset)
+            * Affine3A::from_scale(Vec3::new(2.0, -2.0, -2.0) / 48.0);
+
+        if !wall {
+            self.cuboid(
+                transform,
+                sheet,
+                cuboid([44, 0], [-1.0, -30.0, -1.0], [2.0, 42.0, 2.0]),
+                |_| true,
+            );
+        }
+        self.cuboid(
+            transform,
+            sheet,
+            cuboid([0, 42], [-10.0, -32.0, -1.0], [20.0, 2.0, 2.0]),
+            |_| true,
+        );
+        let flag = cuboid([0, 0], [-10.0, -32.0, -2.0], [20.0, 40.0, 1.0]);
+        self.cuboid(transform, sheet, flag, |face| {
+            !matches!(face, Face::North | Face::South)
+        });
+
+        let layers = std::iter::once(("base".to_owned(), color))
+            .chain(block_entity.map(banner_patterns).unwrap_or_default());
+        for (i, (pattern, color)) in layers.enumerate() {
+            let sheet = Sheet {
+                tint: Some(color),
+                ..Sheet::new(intern(format!("entity/banner/{pattern}")), 64)
+            };
+            // Each layer is pushed out slightly so that they don't z-fight
+            let inflate = i as f32 * 0.01;
+            self.cuboid(transform, sheet, Cuboid { inflate, ..flag }, |face| {
+                matches!(face, Face::North | Face::South)
+            });
+        }
+    }
+}
+
+struct SignSide {
+    lines: Vec<String>,
+    color: [u8; 3],
+    glowing: bool,
+}
+
+/// The text on each side of a sign, along with whether it is the back side.
+fn sign_sides(block_entity: &BlockEntity) -> Vec<(SignSide, bool)> {
+    let side = |value: &nbt::Value| {
+        let nbt::Value::Compound(compound) = value else {
+            return None;
+        };
+        let Some(nbt::Value::List(messages)) = compound.get("messages") else {
+            return None;
+        };
+        Some(SignSide {
+            lines: messages.iter().map(text_component).collect(),
+            color: text_color(nbt_str(compound.get("color")).unwrap_or("")),
+            glowing: compound.get("has_glowing_text")
+                == Some(&nbt::Value::Byte(1)),
+        })
+    };
+
+    let data = &block_entity.data;
+    if data.contains_key("front_text") {
+        [("front_text", false), ("back_text", true)]
+            .into_iter()
+            .filter_map(|(key, back)| Some((side(data.get(key)?)?, back)))
+            .filter(|(side, _)| side.lines.iter().any(|line| !line.is_empty()))
+            .collect()
+    } else {
+        // Signs from before 1.20 only have text on the front
+        let lines = ["Text1", "Text2", "Text3", "Text4"]
+            .into_iter()
+            .map(|key| data.get(key).map(text_component).unwrap_or_default())
+            .collect::<Vec<_>>();
+        if lines.iter().all(String::is_empty) {
+            return Vec::new();
+        }
+        vec![(
+            SignSide {
+                lines,
+                color: text_color(nbt_str(data.get("Color")).unwrap_or("")),
+                glowing: data.get("GlowingText") == Some(&nbt::Value::Byte(1)),
+            },
+            false,
+        )]
+    }
+}
+
+fn nbt_str(value: Option<&nbt::Value>) -> Option<&str> {
+    match value? {
+        nbt::Value::String(s) => Some(s),
+        _ => None,
+    }
+}
+
+/// Flattens a text component, stored either as JSON or directly as NBT, to
+/// plain text.
+fn text_component(value: &nbt::Value) -> String {
+    match value {
+        nbt::Value::String(s) => match serde_json::from_str(s) {
+            Ok(json) => json_text_component(&json),
+            Err(_) => s.clone(),
+        },
+        nbt::Value::Compound(compound) => {
+            let mut text =
+                nbt_str(compound.get("text")).unwrap_or_default().to_owned();
+            if let Some(nbt::Value::List(extra)) = compound.get("extra") {
+                text.extend(extra.iter().map(text_component));
+            }
+            text
+        }
+        nbt::Value::List(components) => {
+            components.iter().map(text_component).collect()
+        }
+        _ => String::new(),
+    }
+}
+
+fn json_text_component(json: &serde_json::Value) -> String {
+    match json {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Object(object) => {
+            let mut text = object
+                .get("text")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or_default()
+                .to_owned();
+            if let Some(serde_json::Value::Array(extra)) = object.get("extra") {
+                text.extend(extra.iter().map(json_text_component));
+            }
+            text
+        }
+        serde_json::Value::Array(components) => {
+            components.iter().map(json_text_component).collect()
+        }
+        _ => String::new(),
+    }
+}
+
+/// The patterns on a banner from the bottom layer up, each with its color.
+fn banner_patterns(block_entity: &BlockEntity) -> Vec<(String, [u8; 3])> {
+    let (Some(nbt::Value::List(patterns)), legacy) =
+        (match block_entity.data.get("patterns") {
+            Some(patterns) => (Some(patterns), false),
+            None => (block_entity.data.get("Patterns"), true),
+        })
+    else {
+        return Vec::new();
+    };
+
+    patterns
+        .iter()
+        .filter_map(|pattern| {
+            let nbt::Value::Compound(pattern) = pattern else {
+                return None;
+            };
+            if legacy {
+                // Before 1.20.5, patterns were identified by short codes and
+                // colors by their index
+                let color = match pattern.get("Color")? {
+                    nbt::Value::Int(color) => *color,
+                    nbt::Value::Byte(color) => i32::from(*color),
+                    _ => return None,
+                };
+                let color = DYE_COLORS.get(usize::try_from(color).ok()?)?;
+                let name =
+                    legacy_banner_pattern(nbt_str(pattern.get("Pattern"))?)?;
+                Some((name.to_owned(), dye_color(color)?))
+            } else {
+                let name = nbt_str(pattern.get("pattern"))?;
+                let name = name.strip_prefix("minecraft:").unwrap_or(name);
+                let color = dye_color(nbt_str(pattern.get("color"))?)?;
+                Some((name.to_owned(), color))
+            }
+        })
+        .collect()
+}
+
+fn legacy_banner_pattern(code: &str) -> Option<&'static str> {
+    Some(match code {
+        "b" => "base",
+        "bl" => "square_bottom_left",
+        "br" => "square_bottom_right",
+        "tl" => "square_top_left",
+        "tr" => "square_top_right",
+        "bs" => "stripe_bottom",
+        "ts" => "stripe_top",
+        "ls" => "stripe_left",
+        "rs" => "stripe_right",
+        "cs" => "stripe_center",
+        "ms" => "stripe_middle",
+        "drs" => "stripe_downright",
+        "dls" => "stripe_downleft",
+        "ss" => "small_stripes",
+        "cr" => "cross",
+        "sc" => "straight_cross",
+        "bt" => "triangle_bottom",
+        "tt" => "triangle_top",
+        "bts" => "triangles_bottom",
+        "tts" => "triangles_top",
+        "ld" => "diagonal_left",
+        "rd" => "diagonal_up_right",
+        "lud" => "diagonal_up_left",
+        "rud" => "diagonal_right",
+        "mc" => "circle",
+        "mr" => "rhombus",
+        "vh" => "half_vertical",
+        "hh" => "half_horizontal",
+        "vhr" => "half_vertical_right",
+        "hhb" => "half_horizontal_bottom",
+        "bo" => "border",
+        "cbo" => "curly_border",
+        "gra" => "gradient",
+        "gru" => "gradient_up",
+        "bri" => "bricks",
+        "glb" => "globe",
+        "cre" => "creeper",
+        "sku" => "skull",
+        "flo" => "flower",
+        "moj" => "mojang",
+        "pig" => "piglin",
+        _ => return None,
+    })
+}
+
+/// The position of a character in the ASCII font texture.
+fn glyph_index(c: char) -> Option<u8> {
+    u8::try_from(c).ok().filter(|c| c.is_ascii_graphic())
+}
+
+/// How far the default font advances after drawing a character, in font
+/// pixels.
+fn glyph_advance(c: char) -> f32 {
+    match c {
+        '!' | '\'' | ',' | '.' | ':' | ';' | 'i' | '|' => 2.0,
+        '`' | 'l' => 3.0,
+        ' ' | 'I' | '[' | ']' | 't' => 4.0,
+        '"' | '(' | ')' | '*' | '<' | '>' | 'f' | 'k' | '{' | '}' => 5.0,
+        '@' | '~' => 7.0,
+        _ => 6.0,
+    }
+}
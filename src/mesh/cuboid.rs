@@ -0,0 +1,301 @@
+use super::Mesh;
+use crate::texture::{Rect, Texture};
+use glam::{Affine3A, Vec2, Vec3};
+
+/// A texture that entity model boxes are unwrapped onto.
+#[derive(Clone, Copy)]
+pub(super) struct Sheet {
+    pub path: &'static str,
+    pub image_width: u16,
+    pub tint: Option<[u8; 3]>,
+}
+
+impl Sheet {
+    pub const fn new(path: &'static str, image_width: u16) -> Self {
+        Self {
+            path,
+            image_width,
+            tint: None,
+        }
+    }
+
+    fn texture(self, x: u16, y: u16, width: u16, height: u16) -> Texture {
+        Texture::Sprite {
+            path: self.path,
+            rect: Rect {
+                x,
+                y,
+                width,
+                height,
+                image_width: self.image_width,
+            },
+            tint: self.tint,
+        }
+    }
+}
+
+/// An axis-aligned box of an entity model, in pixels, with the texture
+/// layout Minecraft uses for entity models.
+#[derive(Clone, Copy)]
+pub(super) struct Cuboid {
+    pub uv: [u16; 2],
+    pub from: Vec3,
+    pub size: Vec3,
+    pub inflate: f32,
+}
+
+pub(super) const fn cuboid(
+    uv: [u16; 2],
+    from: [f32; 3],
+    size: [f32; 3],
+) -> Cuboid {
+    Cuboid {
+        uv,
+        from: Vec3::from_array(from),
+        size: Vec3::from_array(size),
+        inflate: 0.0,
+    }
+}
+
+/// Which way a face of a cuboid points, before transformation.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Face {
+    Down,
+    Up,
+    West,
+    North,
+    East,
+    South,
+}
+
+impl Mesh {
+    /// Adds the faces of an entity model box for which `include` returns
+    /// true.
+    pub(super) fn cuboid(
+        &mut self,
+        transform: Affine3A,
+        sheet: Sheet,
+        cuboid: Cuboid,
+        include: impl Fn(Face) -> bool,
+    ) {
+        let Cuboid {
+            uv: [u, v],
+            from,
+            size,
+            inflate,
+        } = cuboid;
+        let min = from - inflate;
+        let max = from + size + inflate;
+        let corner = |x: bool, y: bool, z: bool| {
+            Vec3::new(
+                if x { max.x } else { min.x },
+                if y { max.y } else { min.y },
+                if z { max.z } else { min.z },
+            )
+        };
+        let [dx, dy, dz] = size.to_array().map(|n| n as u16);
+
+        let faces = [
+            (
+                Face::Down,
+                [
+                    corner(true, false, true),
+                    corner(false, false, true),
+                    corner(false, false, false),
+                    corner(true, false, false),
+                ],
+                [u + dz, v, u + dz + dx, v + dz],
+            ),
+            (
+                Face::Up,
+                [
+                    corner(true, true, false),
+                    corner(false, true, false),
+                    corner(false, true, true),
+                    corner(true, true, true),
+                ],
+                [u + dz + dx, v + dz, u + dz + dx + dx, v],
+            ),
+            (
+                Face::West,
+                [
+                    corner(false, false, false),
+                    corner(false, false, true),
+                    corner(false, true, true),
+                    corner(false, true, false),
+                ],
+                [u, v + dz, u + dz, v + dz + dy],
+            ),
+            (
+                Face::North,
+                [
+                    corner(true, false, false),
+                    corner(false, false, false),
+                    corner(false, true, false),
+                    corner(true, true, false),
+                ],
+                [u + dz, v + dz, u + dz + dx, v + dz + dy],
+            ),
+            (
+                Face::East,
+                [
+                    corner(true, false, true),
+                    corner(true, false, false),
+                    corner(true, true, false),
+                    corner(true, true, true),
+                ],
+                [u + dz + dx, v + dz, u + dz + dx + dz, v + dz + dy],
+            ),
+            (
+                Face::South,
+                [
+                    corner(false, false, true),
+                    corner(true, false, true),
+                    corner(true, true, true),
+                    corner(false, true, true),
+                ],
+                [u + dz + dx + dz, v + dz, u + dz + dx + dz + dx, v + dz + dy],
+            ),
+        ];
+
+        for (face, corners, [u1, v1, u2, v2]) in faces {
+            if !include(face) {
+                continue;
+            }
+            // Texture coordinates of the corners in the same order as
+            // Minecraft assigns them, flipped when the rectangle is
+            let (flip_u, flip_v) = (u1 > u2, v1 > v2);
+            let uv = |right: bool, bottom: bool| {
+                Vec2::new(
+                    f32::from(u8::from(right != flip_u)),
+                    f32::from(u8::from(bottom != flip_v)),
+                )
+            };
+            let texture = sheet.texture(
+                u1.min(u2),
+                v1.min(v2),
+                u1.abs_diff(u2).max(1),
+                v1.abs_diff(v2).max(1),
+            );
+            self.quad(
+                transform,
+                [
+                    (corners[0], uv(true, false)),
+                    (corners[1], uv(false, false)),
+                    (corners[2], uv(false, true)),
+                    (corners[3], uv(true, true)),
+                ],
+                texture,
+            );
+        }
+    }
+
+    /// Adds the faces of a box of a block model, in pixels, for which
+    /// `texture` returns a texture. Like in block models, each face shows the
+    /// part of its 16x16 texture that it covers.
+    pub(super) fn element(
+        &mut self,
+        transform: Affine3A,
+        from: Vec3,
+        to: Vec3,
+        texture: impl Fn(Face) -> Option<&'static str>,
+    ) {
+        let [x1, y1, z1] = from.to_array();
+        let [x2, y2, z2] = to.to_array();
+        let faces = [
+            (
+                Face::Down,
+                [[x1, y1, z1], [x2, y1, z1], [x2, y1, z2], [x1, y1, z2]],
+                [x1, 16.0 - z2, x2, 16.0 - z1],
+            ),
+            (
+                Face::Up,
+                [[x1, y2, z2], [x2, y2, z2], [x2, y2, z1], [x1, y2, z1]],
+                [x1, z1, x2, z2],
+            ),
+            (
+                Face::North,
+                [[x2, y1, z1], [x1, y1, z1], [x1, y2, z1], [x2, y2, z1]],
+                [16.0 - x2, 16.0 - y2, 16.0 - x1, 16.0 - y1],
+            ),
+            (
+                Face::South,
+                [[x1, y1, z2], [x2, y1, z2], [x2, y2, z2], [x1, y2, z2]],
+                [x1, 16.0 - y2, x2, 16.0 - y1],
+            ),
+            (
+                Face::West,
+                [[x1, y1, z1], [x1, y1, z2], [x1, y2, z2], [x1, y2, z1]],
+                [z1, 16.0 - y2, z2, 16.0 - y1],
+            ),
+            (
+                Face::East,
+                [[x2, y1, z2], [x2, y1, z1], [x2, y2, z1], [x2, y2, z2]],
+                [16.0 - z2, 16.0 - y2, 16.0 - z1, 16.0 - y1],
+            ),
+        ];
+
+        for (face, corners, [u1, v1, u2, v2]) in faces {
+            let Some(path) = texture(face) else {
+                continue;
+            };
+            let texture = Sheet::new(path, 16).texture(
+                u1 as u16,
+                v1 as u16,
+                ((u2 - u1) as u16).max(1),
+                ((v2 - v1) as u16).max(1),
+            );
+            self.quad(
+                transform,
+                [
+                    (Vec3::from_array(corners[0]), Vec2::new(0.0, 1.0)),
+                    (Vec3::from_array(corners[1]), Vec2::ONE),
+                    (Vec3::from_array(corners[2]), Vec2::new(1.0, 0.0)),
+                    (Vec3::from_array(corners[3]), Vec2::ZERO),
+                ],
+                texture,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block_source::Volume, test_util};
+    use glam::{IVec2, IVec3};
+
+    #[test]
+    fn unwraps_boxes_the_way_minecraft_does() {
+        let options = test_util::options(IVec2::ZERO..IVec2::ONE);
+        let mut mesh = Mesh::build(&Volume::new(IVec3::ONE), &options);
+        mesh.cuboid(
+            Affine3A::IDENTITY,
+            Sheet::new("entity/test", 64),
+            cuboid([8, 16], [0.0, 0.0, 0.0], [2.0, 3.0, 4.0]),
+            |face| face != Face::Down,
+        );
+        assert_eq!(mesh.vertices.len(), 20);
+        let rects: Vec<[u16; 4]> = mesh
+            .textures
+            .iter()
+            .map(|texture| {
+                let Texture::Sprite { rect, .. } = texture else {
+                    unreachable!();
+                };
+                [rect.x, rect.y, rect.width, rect.height]
+            })
+            .collect();
+        // Up, then the sides from west around to south
+        assert_eq!(
+            rects,
+            [
+                [14, 16, 2, 4],
+                [8, 20, 4, 3],
+                [12, 20, 2, 3],
+                [14, 20, 4, 3],
+                [18, 20, 2, 3],
+            ]
+        );
+    }
+}
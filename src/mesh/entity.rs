@@ -0,0 +1,486 @@
+use super::{
+    cuboid::{cuboid, Face, Sheet},
+    intern, Mesh,
+};
+use crate::{
+    block_source::BlockSource,
+    entity::{nbt_int, Entity, EntityCategories},
+    texture::{Rect, Texture},
+    Options,
+};
+use glam::{Affine3A, IVec3, Vec2, Vec3, Vec3Swizzles};
+use std::{
+    collections::HashMap,
+    f32::consts::{FRAC_PI_2, FRAC_PI_4, PI},
+};
+
+impl Mesh {
+    pub(super) fn entity(
+        &mut self,
+        entity: &Entity,
+        categories: EntityCategories,
+    ) {
+        let name = entity.id.strip_prefix("minecraft:").unwrap_or(&entity.id);
+        match name {
+            "item_frame" | "glow_item_frame" if categories.item_frames => {
+                self.item_frame(entity, name == "glow_item_frame");
+            }
+            "painting" if categories.paintings => {
+                self.painting(entity);
+            }
+            "armor_stand" if categories.armor_stands => {
+                self.armor_stand(entity);
+            }
+            "item_frame" | "glow_item_frame" | "painting" | "armor_stand" => {}
+            // Only living entities have health
+            _ if categories.mobs && entity.data.contains_key("Health") => {
+                self.mob(entity, name);
+            }
+            _ => {}
+        }
+    }
+
+    fn item_frame(&mut self, entity: &Entity, glowing: bool) {
+        let Some(block_pos) = tile_pos(entity) else {
+            return;
+        };
+        // The frame is modelled against the south side of its block, facing
+        // north
+        let rotation = match byte(entity, "Facing") {
+            Some(0) => Affine3A::from_rotation_x(-FRAC_PI_2),
+            Some(1) => Affine3A::from_rotation_x(FRAC_PI_2),
+            Some(3) => Affine3A::from_rotation_y(PI),
+            Some(4) => Affine3A::from_rotation_y(FRAC_PI_2),
+            Some(5) => Affine3A::from_rotation_y(-FRAC_PI_2),
+            _ => Affine3A::IDENTITY,
+        };
+        let transform =
+            Affine3A::from_translation(block_pos.as_vec3() + Vec3::splat(0.5))
+                * rotation
+                * Affine3A::from_translation(Vec3::splat(-0.5))
+                * Affine3A::from_scale(Vec3::splat(1.0 / 16.0));
+
+        let item = entity.item();
+        let map_id = item.as_ref().and_then(|item| item.map_id());
+        let frame_texture = if glowing {
+            "block/glow_item_frame"
+        } else {
+            "block/item_frame"
+        };
+        let (min, max) = if map_id.is_some() {
+            (1.0, 15.0)
+        } else {
+            (3.0, 13.0)
+        };
+        self.element(
+            transform,
+            Vec3::new(min, min, 15.5),
+            Vec3::new(max, max, 16.0),
+            |_| Some("block/birch_planks"),
+        );
+        for (from, to) in [
+            ([min - 1.0, min - 1.0], [max + 1.0, min]),
+            ([min - 1.0, max], [max + 1.0, max + 1.0]),
+            ([min - 1.0, min], [min, max]),
+            ([max, min], [max + 1.0, max]),
+        ] {
+            self.element(
+                transform,
+                Vec3::new(from[0], from[1], 15.0),
+                Vec3::new(to[0], to[1], 16.0),
+                |face| (face != Face::South).then_some(frame_texture),
+            );
+        }
+
+        if let Some(id) = map_id {
+            // Maps are split into tiles to keep their resolution
+            let tile_size = 16.0 / 8.0;
+            for y in 0..8 {
+                for x in 0..8 {
+                    let (x0, y0) = (
+                        16.0 - f32::from(x) * tile_size,
+                        16.0 - f32::from(y) * tile_size,
+                    );
+                    self.flat_quad(
+                        transform,
+                        Vec3::new(x0 - tile_size, y0 - tile_size, 14.9),
+                        Vec2::splat(tile_size),
+                        Texture::Map { id, x, y },
+                    );
+                }
+            }
+        } else if let Some(item) = item {
+            let item_rotation =
+                f32::from(byte(entity, "ItemRotation").unwrap_or(0))
+                    * FRAC_PI_4;
+            let transform = transform
+                * Affine3A::from_translation(Vec3::new(8.0, 8.0, 0.0))
+                * Affine3A::from_rotation_z(-item_rotation)
+                * Affine3A::from_translation(Vec3::new(-8.0, -8.0, 0.0));
+            self.flat_quad(
+                transform,
+                Vec3::new(4.0, 4.0, 14.9),
+                Vec2::splat(8.0),
+                Texture::Item(intern(item.name.to_owned())),
+            );
+        }
+    }
+
+    fn painting(&mut self, entity: &Entity) {
+        let variant = ["variant", "Motive"]
+            .into_iter()
+            .find_map(|key| match entity.data.get(key) {
+                Some(nbt::Value::String(variant)) => Some(variant.as_str()),
+                _ => None,
+            })
+            .unwrap_or("kebab");
+        let variant = variant.strip_prefix("minecraft:").unwrap_or(variant);
+        let (width, height) = painting_size(variant);
+        // Paintings are modelled facing north, centered on the origin
+        let yaw =
+            match byte(entity, "facing").or_else(|| byte(entity, "Facing")) {
+                Some(0) => PI,
+                Some(1) => FRAC_PI_2,
+                Some(3) => -FRAC_PI_2,
+                _ => 0.0,
+            };
+        let transform = Affine3A::from_translation(entity.pos().as_vec3())
+            * Affine3A::from_rotation_y(yaw)
+            * Affine3A::from_scale(Vec3::splat(1.0 / 16.0));
+
+        let path = intern(format!("painting/{variant}"));
+        let (half_width, half_height) =
+            (f32::from(width) * 8.0, f32::from(height) * 8.0);
+        let back = Texture::Sprite {
+            path: "painting/back",
+            rect: Rect {
+                x: 0,
+                y: 0,
+                width: 16,
+                height: 16,
+                image_width: 16,
+            },
+            tint: None,
+        };
+        self.stretched_box(
+            transform,
+            Vec3::new(-half_width, -half_height, -0.5),
+            Vec3::new(half_width, half_height, 0.5),
+            |face| (face != Face::North).then_some(back),
+        );
+        // The front is split into one tile per block
+        for y in 0..height {
+            for x in 0..width {
+                let texture = Texture::Sprite {
+                    path,
+                    rect: Rect {
+                        x: x * 16,
+                        y: y * 16,
+                        width: 16,
+                        height: 16,
+                        image_width: width * 16,
+                    },
+                    tint: None,
+                };
+                // Seen from the north, X increases to the left
+                let corner = Vec3::new(
+                    half_width - f32::from(x + 1) * 16.0,
+                    half_height - f32::from(y + 1) * 16.0,
+                    -0.5,
+                );
+                self.flat_quad(transform, corner, Vec2::splat(16.0), texture);
+            }
+        }
+    }
+
+    fn armor_stand(&mut self, entity: &Entity) {
+        let sheet = Sheet::new("entity/armorstand/wood", 64);
+        let scale = if byte(entity, "Small") == Some(1) {
+            0.5
+        } else {
+            1.0
+        };
+        let transform = living_entity_transform(entity, scale);
+
+        let mut cuboids = vec![
+            cuboid([0, 0], [-1.0, -6.0, -1.0], [2.0, 7.0, 2.0]),
+            cuboid([0, 26], [-6.0, 0.0, -1.5], [12.0, 3.0, 3.0]),
+            cuboid([8, 0], [-2.9, 12.0, -1.0], [2.0, 11.0, 2.0]),
+            cuboid([40, 16], [0.9, 12.0, -1.0], [2.0, 11.0, 2.0]),
+            cuboid([16, 0], [-3.0, 3.0, -1.0], [2.0, 7.0, 2.0]),
+            cuboid([48, 16], [1.0, 3.0, -1.0], [2.0, 7.0, 2.0]),
+            cuboid([0, 48], [-4.0, 10.0, -1.0], [8.0, 2.0, 2.0]),
+        ];
+        if byte(entity, "ShowArms") == Some(1) {
+            cuboids.extend([
+                cuboid([24, 0], [-7.0, 0.0, -1.0], [2.0, 12.0, 2.0]),
+                cuboid([32, 16], [5.0, 0.0, -1.0], [2.0, 12.0, 2.0]),
+            ]);
+        }
+        if byte(entity, "NoBasePlate") != Some(1) {
+            cuboids.push(cuboid(
+                [0, 32],
+                [-6.0, 23.0, -6.0],
+                [12.0, 1.0, 12.0],
+            ));
+        }
+        for cuboid in cuboids {
+            self.cuboid(transform, sheet, cuboid, |_| true);
+        }
+    }
+
+    /// Mobs are drawn as a box the size of their hitbox, showing their face
+    /// on every side.
+    fn mob(&mut self, entity: &Entity, name: &str) {
+        let (texture, width, height) = mob_appearance(name);
+        let transform = living_entity_transform(entity, 1.0);
+        let (half_width, height) = (width * 8.0, height * 16.0);
+        self.stretched_box(
+            transform,
+            Vec3::new(-half_width, 24.0 - height, -half_width),
+            Vec3::new(half_width, 24.0, half_width),
+            |_| Some(texture),
+        );
+    }
+
+    /// Adds a single quad facing north in the XY plane.
+    fn flat_quad(
+        &mut self,
+        transform: Affine3A,
+        corner: Vec3,
+        size: Vec2,
+        texture: Texture,
+    ) {
+        self.quad(
+            transform,
+            [
+                (corner + Vec3::X * size.x, Vec2::new(0.0, 1.0)),
+                (corner, Vec2::ONE),
+                (corner + Vec3::Y * size.y, Vec2::new(1.0, 0.0)),
+                (corner + size.extend(0.0), Vec2::ZERO),
+            ],
+            texture,
+        );
+    }
+
+    /// Adds a box with each face showing the whole of its texture.
+    fn stretched_box(
+        &mut self,
+        transform: Affine3A,
+        from: Vec3,
+        to: Vec3,
+        texture: impl Fn(Face) -> Option<Texture>,
+    ) {
+        let size = to - from;
+        let center = (from + to) / 2.0;
+        // Each face is a north facing quad rotated into place
+        for (face, rotation, face_size, depth) in [
+            (Face::North, Affine3A::IDENTITY, size.xy(), size.z),
+            (
+                Face::South,
+                Affine3A::from_rotation_y(PI),
+                size.xy(),
+                size.z,
+            ),
+            (
+                Face::West,
+                Affine3A::from_rotation_y(FRAC_PI_2),
+                size.zy(),
+                size.x,
+            ),
+            (
+                Face::East,
+                Affine3A::from_rotation_y(-FRAC_PI_2),
+                size.zy(),
+                size.x,
+            ),
+            (
+                Face::Up,
+                Affine3A::from_rotation_x(FRAC_PI_2),
+                size.xz(),
+                size.y,
+            ),
+            (
+                Face::Down,
+                Affine3A::from_rotation_x(-FRAC_PI_2),
+                size.xz(),
+                size.y,
+            ),
+        ] {
+            let Some(texture) = texture(face) else {
+                continue;
+            };
+            self.flat_quad(
+                transform * Affine3A::from_translation(center) * rotation,
+                (-face_size / 2.0).extend(-depth / 2.0),
+                face_size,
+                texture,
+            );
+        }
+    }
+}
+
+/// The entities that should be drawn, grouped by the section they are in.
+pub(super) fn entities_by_section<'a>(
+    world: &'a dyn BlockSource,
+    options: &Options,
+) -> HashMap<IVec3, Vec<&'a Entity>> {
+    let mut sections = HashMap::<_, Vec<_>>::new();
+    for entity in world.entities() {
+        let pos = entity.pos().as_vec3();
+        if !(options.area.start.x as f32..options.area.end.x as f32)
+            .contains(&pos.x)
+            || !(options.area.start.y as f32..options.area.end.y as f32)
+                .contains(&pos.z)
+            || entity.data.get("Invisible") == Some(&nbt::Value::Byte(1))
+        {
+            continue;
+        }
+        let section = pos.floor().as_ivec3() >> 4_i32;
+        sections.entry(section).or_default().push(entity);
+    }
+    sections
+}
+
+/// The transformation Minecraft uses for living entity models, which are
+/// modelled upside down and facing north, with their feet at Y = 24.
+fn living_entity_transform(entity: &Entity, scale: f32) -> Affine3A {
+    let yaw = entity.rotation().x.to_radians();
+    Affine3A::from_translation(entity.pos().as_vec3())
+        * Affine3A::from_rotation_y(PI - yaw)
+        * Affine3A::from_scale(Vec3::new(-scale, -scale, scale))
+        * Affine3A::from_translation(Vec3::Y * -1.501)
+        * Affine3A::from_scale(Vec3::splat(1.0 / 16.0))
+}
+
+/// The block that a hanging entity is attached in.
+fn tile_pos(entity: &Entity) -> Option<IVec3> {
+    let int = |key| nbt_int(entity.data.get(key)?);
+    // Older versions store the position as three ints
+    match (int("TileX"), int("TileY"), int("TileZ")) {
+        (Some(x), Some(y), Some(z)) => Some(IVec3::new(x, y, z)),
+        _ => None,
+    }
+    .or_else(|| {
+        // Int arrays come back as byte arrays or lists once flattened
+        let pos: Vec<i32> = match entity.data.get("block_pos")? {
+            nbt::Value::IntArray(pos) => pos.clone(),
+            nbt::Value::ByteArray(pos) => {
+                pos.iter().copied().map(i32::from).collect()
+            }
+            nbt::Value::List(pos) => {
+                pos.iter().map(nbt_int).collect::<Option<_>>()?
+            }
+            _ => return None,
+        };
+        Some(IVec3::from_slice(pos.get(..3)?))
+    })
+}
+
+fn byte(entity: &Entity, key: &str) -> Option<i8> {
+    match entity.data.get(key)? {
+        nbt::Value::Byte(n) => Some(*n),
+        _ => None,
+    }
+}
+
+/// The size of a painting in blocks.
+fn painting_size(variant: &str) -> (u16, u16) {
+    match variant {
+        "pool" | "courbet" | "sea" | "sunset" | "creebet" => (2, 1),
+        "wanderer" | "graham" | "prairie_ride" => (1, 2),
+        "match" | "bust" | "stage" | "void" | "skull_and_roses" | "wither"
+        | "earth" | "wind" | "water" | "fire" | "baroque" | "humble" => (2, 2),
+        "fighters" | "changing" | "finding" | "lowmist" | "passage" => (4, 2),
+        "bouquet" | "cavebird" | "cotan" | "endboss" | "fern" | "owlemons"
+        | "sunflowers" | "tides" => (3, 3),
+        "backyard" | "pond" => (3, 4),
+        "skeleton" | "donkey_kong" => (4, 3),
+        "pointer" | "pigscene" | "burning_skull" | "unpacked" | "orb" => (4, 4),
+        _ => (1, 1),
+    }
+}
+
+/// The texture shown on each side of a mob's box, along with its width and
+/// height in blocks.
+fn mob_appearance(name: &str) -> (Texture, f32, f32) {
+    let face = |path, x, y, width, height, image_width| Texture::Sprite {
+        path,
+        rect: Rect {
+            x,
+            y,
+            width,
+            height,
+            image_width,
+        },
+        tint: None,
+    };
+    match name {
+        "zombie" => (face("entity/zombie/zombie", 8, 8, 8, 8, 64), 0.6, 1.95),
+        "husk" => (face("entity/zombie/husk", 8, 8, 8, 8, 64), 0.6, 1.95),
+        "drowned" => (face("entity/zombie/drowned", 8, 8, 8, 8, 64), 0.6, 1.95),
+        "skeleton" => {
+            (face("entity/skeleton/skeleton", 8, 8, 8, 8, 64), 0.6, 1.99)
+        }
+        "stray" => (face("entity/skeleton/stray", 8, 8, 8, 8, 64), 0.6, 1.99),
+        "creeper" => (face("entity/creeper/creeper", 8, 8, 8, 8, 64), 0.6, 1.7),
+        "spider" => (face("entity/spider/spider", 40, 12, 8, 8, 64), 1.4, 0.9),
+        "enderman" => {
+            (face("entity/enderman/enderman", 8, 8, 8, 8, 64), 0.6, 2.9)
+        }
+        "villager" => {
+            (face("entity/villager/villager", 8, 8, 8, 10, 64), 0.6, 1.95)
+        }
+        "witch" => (face("entity/witch", 8, 8, 8, 10, 64), 0.6, 1.95),
+        "iron_golem" => (
+            face("entity/iron_golem/iron_golem", 8, 8, 8, 10, 128),
+            1.4,
+            2.7,
+        ),
+        "cow" => (face("entity/cow/cow", 6, 6, 8, 8, 64), 0.9, 1.4),
+        "pig" => (face("entity/pig/pig", 8, 8, 8, 8, 64), 0.9, 0.9),
+        "sheep" => (face("entity/sheep/sheep", 8, 8, 6, 6, 64), 0.9, 1.3),
+        "chicken" => (face("entity/chicken", 3, 3, 4, 6, 64), 0.4, 0.7),
+        "wolf" => (face("entity/wolf/wolf", 4, 4, 6, 6, 64), 0.6, 0.85),
+        "cat" => (face("entity/cat/tabby", 5, 5, 5, 4, 64), 0.6, 0.7),
+        "slime" => (face("entity/slime/slime", 8, 8, 8, 8, 64), 1.04, 1.04),
+        // Other mobs show their spawn egg instead
+        _ => (Texture::Item(intern(format!("{name}_spawn_egg"))), 0.6, 1.8),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nbt::{Blob, Value};
+    use std::io::Cursor;
+
+    fn entity(data: &[(&str, Value)]) -> Entity {
+        let mut blob = Blob::new();
+        blob.insert("id", "minecraft:item_frame").unwrap();
+        blob.insert("Pos", Value::List(vec![Value::Double(0.5); 3]))
+            .unwrap();
+        for (key, value) in data {
+            blob.insert(*key, value.clone()).unwrap();
+        }
+        let mut bytes = Vec::new();
+        blob.to_writer(&mut bytes).unwrap();
+        nbt::from_reader(Cursor::new(bytes)).unwrap()
+    }
+
+    #[test]
+    fn reads_the_block_of_hanging_entities() {
+        let old = entity(&[
+            ("TileX", Value::Int(100_000)),
+            ("TileY", Value::Int(-2)),
+            ("TileZ", Value::Int(3)),
+        ]);
+        assert_eq!(tile_pos(&old), Some(IVec3::new(100_000, -2, 3)));
+        let new = entity(&[("block_pos", Value::IntArray(vec![4, 5, -6]))]);
+        assert_eq!(tile_pos(&new), Some(IVec3::new(4, 5, -6)));
+        let far =
+            entity(&[("block_pos", Value::IntArray(vec![400, 5, -70000]))]);
+        assert_eq!(tile_pos(&far), Some(IVec3::new(400, 5, -70000)));
+        assert_eq!(tile_pos(&entity(&[])), None);
+    }
+}
@@ -0,0 +1,79 @@
+use glam::{Vec2, Vec3};
+
+/// The color of blocks that match the highlight filter.
+pub const HIGHLIGHT_COLOR: Vec3 = Vec3::new(1.0, 0.8, 0.1);
+/// How far from the edges of a face its outline reaches, as a fraction of
+/// the face.
+const OUTLINE_WIDTH: f32 = 0.08;
+const OUTLINE_ALPHA: f32 = 0.9;
+/// How strongly the inside of a face glows.
+const GLOW_ALPHA: f32 = 0.25;
+
+/// Boxes drawn on top of everything else, with nothing hiding them, to show
+/// where blocks are through terrain.
+#[derive(Default)]
+pub struct Highlights {
+    /// The corners of every face of the boxes.
+    pub vertices: Vec<HighlightVertex>,
+    /// Triangles as indices into the vertices.
+    pub indices: Vec<u32>,
+}
+
+/// A corner of a face of a highlight box, laid out the way the shaders
+/// read it.
+#[repr(C)]
+pub struct HighlightVertex {
+    /// Where the corner is in the world.
+    pub pos: Vec3,
+    /// The position within the face, from 0 to 1.
+    pub uv: Vec2,
+    /// The color of the box.
+    pub color: Vec3,
+}
+
+impl Highlights {
+    /// Adds a box from `min` to `max`, whose faces are drawn from both sides.
+    pub fn add_box(&mut self, min: Vec3, max: Vec3, color: Vec3) {
+        let corner = |x: bool, y: bool, z: bool| {
+            Vec3::select(glam::BVec3::new(x, y, z), max, min)
+        };
+        let faces = [
+            [(0, 0, 0), (0, 0, 1), (0, 1, 1), (0, 1, 0)],
+            [(1, 0, 0), (1, 1, 0), (1, 1, 1), (1, 0, 1)],
+            [(0, 0, 0), (1, 0, 0), (1, 0, 1), (0, 0, 1)],
+            [(0, 1, 0), (0, 1, 1), (1, 1, 1), (1, 1, 0)],
+            [(0, 0, 0), (0, 1, 0), (1, 1, 0), (1, 0, 0)],
+            [(0, 0, 1), (1, 0, 1), (1, 1, 1), (0, 1, 1)],
+        ];
+        let uvs = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y];
+        for face in faces {
+            let vertex_count = self.vertices.len() as u32;
+            self.vertices.extend(face.into_iter().zip(uvs).map(
+                |((x, y, z), uv)| HighlightVertex {
+                    pos: corner(x == 1, y == 1, z == 1),
+                    uv,
+                    color,
+                },
+            ));
+            self.indices.extend([
+                vertex_count,
+                vertex_count + 1,
+                vertex_count + 2,
+                vertex_count,
+                vertex_count + 2,
+                vertex_count + 3,
+            ]);
+        }
+    }
+}
+
+/// How opaque a point on a face of a highlight is, with a solid outline
+/// around a faint glow. The highlight shader does the same.
+pub fn highlight_alpha(uv: Vec2) -> f32 {
+    let edge_distance = uv.min(1.0 - uv).min_element();
+    if edge_distance < OUTLINE_WIDTH {
+        OUTLINE_ALPHA
+    } else {
+        GLOW_ALPHA
+    }
+}
@@ -0,0 +1,213 @@
+use super::{block_top_texture_name, Mesh};
+use crate::{
+    block_source::BlockSource,
+    texture::{read_image, Texture},
+    Options,
+};
+use glam::{Affine3A, IVec2, Vec2, Vec3};
+use std::{collections::HashMap, ops::Range, path::Path};
+
+/// How many blocks wide the cells of each level of detail are.
+pub const LOD_SCALES: [i32; 3] = [2, 4, 8];
+
+/// A simplified mesh of one chunk, made of one box per `scale`x`scale`
+/// cell of columns.
+pub struct Lod {
+    /// The position of the chunk in chunks.
+    pub chunk: IVec2,
+    /// How many blocks wide the cells are.
+    pub scale: i32,
+    /// The range of the whole mesh's vertices in the simplified chunk.
+    pub vertices: Range<usize>,
+    /// The range of the whole mesh's indices in the simplified chunk.
+    pub indices: Range<usize>,
+    /// The corner of the bounding box with the lowest coordinates.
+    pub min: Vec3,
+    /// The opposite corner of the bounding box.
+    pub max: Vec3,
+}
+
+/// The surface of a cell of columns.
+#[derive(Clone, Copy)]
+struct Cell {
+    /// The Y coordinate of the highest block.
+    max: i32,
+    /// The Y coordinate of the lowest surface block.
+    min: i32,
+    color: [u8; 3],
+}
+
+struct LodBuilder<'a> {
+    world: &'a dyn BlockSource,
+    options: &'a Options,
+    cells: HashMap<(IVec2, i32), Option<Cell>>,
+    /// The average color of each block texture.
+    colors: HashMap<&'static str, [u8; 3]>,
+}
+
+impl Mesh {
+    /// Builds the simplified meshes of every chunk in the area at every
+    /// level of detail.
+    pub(super) fn lods(&mut self, world: &dyn BlockSource, options: &Options) {
+        let mut builder = LodBuilder {
+            world,
+            options,
+            cells: HashMap::new(),
+            colors: HashMap::new(),
+        };
+        let area = &options.area;
+        for chunk_z in area.start.y >> 4..=(area.end.y - 1) >> 4 {
+            for chunk_x in area.start.x >> 4..=(area.end.x - 1) >> 4 {
+                let chunk = IVec2::new(chunk_x, chunk_z);
+                for scale in LOD_SCALES {
+                    self.lod(&mut builder, chunk, scale);
+                }
+            }
+        }
+    }
+
+    fn lod(&mut self, builder: &mut LodBuilder, chunk: IVec2, scale: i32) {
+        let vertex_start = self.vertices.len();
+        let index_start = self.indices.len();
+        for z in (0..16).step_by(scale as usize) {
+            for x in (0..16).step_by(scale as usize) {
+                let origin = chunk * 16 + IVec2::new(x, z);
+                let Some(cell) = builder.cell(origin, scale) else {
+                    continue;
+                };
+                let texture = Texture::Color(cell.color);
+                let [x0, z0] = origin.as_vec2().to_array();
+                let [x1, z1] = (origin + scale).as_vec2().to_array();
+                let top = (cell.max + 1) as f32;
+                self.lod_quad(
+                    [
+                        Vec3::new(x0, top, z0),
+                        Vec3::new(x0, top, z1),
+                        Vec3::new(x1, top, z1),
+                        Vec3::new(x1, top, z0),
+                    ],
+                    texture,
+                );
+
+                // Walls go down to the neighbouring cell, or further at the
+                // edges of the chunk so that no gaps open up next to chunks
+                // drawn at a different level of detail
+                for (offset, from, to) in [
+                    (IVec2::NEG_X, Vec2::new(x0, z0), Vec2::new(x0, z1)),
+                    (IVec2::X, Vec2::new(x1, z1), Vec2::new(x1, z0)),
+                    (IVec2::NEG_Y, Vec2::new(x1, z0), Vec2::new(x0, z0)),
+                    (IVec2::Y, Vec2::new(x0, z1), Vec2::new(x1, z1)),
+                ] {
+                    let neighbour_origin = origin + offset * scale;
+                    let neighbour = builder.cell(neighbour_origin, scale);
+                    let bottom = match neighbour {
+                        Some(neighbour)
+                            if neighbour_origin >> 4_i32 == chunk =>
+                        {
+                            neighbour.max + 1
+                        }
+                        Some(neighbour) => cell.min.min(neighbour.min),
+                        None => cell.min,
+                    } as f32;
+                    if bottom >= top {
+                        continue;
+                    }
+                    self.lod_quad(
+                        [
+                            Vec3::new(from.x, bottom, from.y),
+                            Vec3::new(to.x, bottom, to.y),
+                            Vec3::new(to.x, top, to.y),
+                            Vec3::new(from.x, top, from.y),
+                        ],
+                        texture,
+                    );
+                }
+            }
+        }
+
+        let (min, max) = self.vertices[vertex_start..].iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), vertex| (min.min(vertex.pos), max.max(vertex.pos)),
+        );
+        self.lods.push(Lod {
+            chunk,
+            scale,
+            vertices: vertex_start..self.vertices.len(),
+            indices: index_start..self.indices.len(),
+            min,
+            max,
+        });
+    }
+
+    fn lod_quad(&mut self, corners: [Vec3; 4], texture: Texture) {
+        self.quad(
+            Affine3A::IDENTITY,
+            [
+                (corners[0], Vec2::new(0.0, 1.0)),
+                (corners[1], Vec2::ONE),
+                (corners[2], Vec2::new(1.0, 0.0)),
+                (corners[3], Vec2::ZERO),
+            ],
+            texture,
+        );
+    }
+}
+
+impl LodBuilder<'_> {
+    fn cell(&mut self, origin: IVec2, scale: i32) -> Option<Cell> {
+        if let Some(&cell) = self.cells.get(&(origin, scale)) {
+            return cell;
+        }
+
+        let area = &self.options.area;
+        let mut cell: Option<Cell> = None;
+        let mut color_sum = [0u32; 3];
+        let mut count = 0;
+        for z in origin.y..origin.y + scale {
+            for x in origin.x..origin.x + scale {
+                if !(area.start.x..area.end.x).contains(&x)
+                    || !(area.start.y..area.end.y).contains(&z)
+                {
+                    continue;
+                }
+                let Some((y, block)) = self.world.surface_at(x, z) else {
+                    continue;
+                };
+                let color = self.color(block_top_texture_name(block));
+                for (sum, channel) in color_sum.iter_mut().zip(color) {
+                    *sum += u32::from(channel);
+                }
+                count += 1;
+                cell = Some(cell.map_or(
+                    Cell {
+                        max: y,
+                        min: y,
+                        color,
+                    },
+                    |cell| Cell {
+                        max: cell.max.max(y),
+                        min: cell.min.min(y),
+                        ..cell
+                    },
+                ));
+            }
+        }
+        // Colors are rounded so that similar cells share an atlas tile
+        let cell = cell.map(|cell| Cell {
+            color: color_sum.map(|sum| (sum / count) as u8 & !7),
+            ..cell
+        });
+        self.cells.insert((origin, scale), cell);
+        cell
+    }
+
+    fn color(&mut self, texture_name: &'static str) -> [u8; 3] {
+        let resource_pack: &Path = &self.options.resource_pack_path;
+        *self.colors.entry(texture_name).or_insert_with(|| {
+            read_image(&resource_pack.join(format!(
+                "assets/minecraft/textures/block/{texture_name}.png"
+            )))
+            .map_or([128; 3], |image| image.average_color())
+        })
+    }
+}
@@ -0,0 +1,430 @@
+//! Removes chunks that players hardly spent any time in from a world, so
+//! that Minecraft generates them again when someone gets near them, to
+//! shrink worlds full of chunks that were explored once.
+
+use crate::{chunk::Block, region, search::BlockFilter};
+use glam::IVec2;
+use internment::Intern;
+use serde::Deserialize;
+use std::{
+    fmt, fs,
+    io::Cursor,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// Blocks that hardly ever generate on their own, so that a chunk with any
+/// of them was most likely built in. Chunks where one does generate, such
+/// as jungle temples with their pistons, are kept too, which is harmless.
+pub const PLAYER_BLOCKS: &str = "beacon,enchanting_table,nether_portal,\
+    hopper,repeater,comparator,piston,sticky_piston,observer,dispenser,\
+    dropper,note_block,jukebox,crafter,shulker_box,white_shulker_box,\
+    orange_shulker_box,magenta_shulker_box,light_blue_shulker_box,\
+    yellow_shulker_box,lime_shulker_box,pink_shulker_box,gray_shulker_box,\
+    light_gray_shulker_box,cyan_shulker_box,purple_shulker_box,\
+    blue_shulker_box,brown_shulker_box,green_shulker_box,red_shulker_box,\
+    black_shulker_box,white_bed,orange_bed,magenta_bed,light_blue_bed,\
+    yellow_bed,lime_bed,pink_bed,gray_bed,light_gray_bed,cyan_bed,\
+    purple_bed,blue_bed,brown_bed,green_bed,red_bed,black_bed";
+/// The folders of a dimension with region files, whose chunks have to be
+/// removed together.
+const REGION_FOLDERS: [&str; 3] = ["region", "entities", "poi"];
+
+/// Which chunks to keep.
+pub struct PruneOptions {
+    /// Chunks that players spent at least this many ticks near are kept.
+    pub min_inhabited_time: i64,
+    /// Chunks that overlap any of these areas, by block coordinates from the
+    /// first corner to the second, not included, are kept.
+    pub keep_areas: Vec<Range<IVec2>>,
+    /// Chunks with any of these blocks are kept.
+    pub keep_blocks: BlockFilter,
+}
+
+/// What pruning removed, or would remove, from each region.
+#[derive(Default)]
+pub struct PruneReport {
+    /// One entry per region file, in the order of their names.
+    pub regions: Vec<RegionReport>,
+}
+
+/// What pruning removed, or would remove, from a region.
+pub struct RegionReport {
+    /// The name of the region file, such as `r.0.-1.mca`.
+    pub name: String,
+    /// How many chunks the region had.
+    pub chunks: u32,
+    /// How many of them are kept.
+    pub kept: u32,
+    /// The size of the region file before.
+    pub bytes_before: u64,
+    /// The size of the region file after, which is 0 if it is removed.
+    pub bytes_after: u64,
+}
+
+/// Just the parts of a chunk that decide whether it is kept, which are
+/// much faster to read than the whole chunk.
+#[derive(Deserialize)]
+struct ChunkSummary {
+    #[serde(default, rename = "InhabitedTime")]
+    inhabited_time: i64,
+    #[serde(default)]
+    sections: Vec<SectionSummary>,
+}
+
+#[derive(Deserialize)]
+struct SectionSummary {
+    block_states: Option<PaletteSummary>,
+}
+
+#[derive(Deserialize)]
+struct PaletteSummary {
+    palette: Vec<Intern<Block>>,
+}
+
+/// Finds the chunks of a dimension's folder to remove, and writes the
+/// folder's `region`, `entities` and `poi` folders without them to
+/// `output` if there is one. Regions with no chunks left aren't written.
+/// Nothing in the world's folder is changed, so `output` can't be in it.
+pub fn prune(
+    world_path: &Path,
+    options: &PruneOptions,
+    output: Option<&Path>,
+) -> PruneReport {
+    if let Some(output) = output {
+        if resolve(output).starts_with(resolve(world_path)) {
+            panic!(
+                "can't prune {} into {}, which is in the world's folder",
+                world_path.display(),
+                output.display()
+            );
+        }
+    }
+
+    let mut region_paths: Vec<PathBuf> =
+        fs::read_dir(world_path.join("region"))
+            .unwrap_or_else(|error| {
+                panic!("{}: {error}", world_path.join("region").display())
+            })
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension().is_some_and(|extension| extension == "mca")
+            })
+            .collect();
+    region_paths.sort();
+
+    let mut report = PruneReport::default();
+    for path in region_paths {
+        let file_name = path.file_name().unwrap();
+        let location = region::parse_file_name(file_name);
+        let file = fs::read(&path)
+            .unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+        // Empty regions, which Minecraft sometimes leaves, have no chunks
+        if file.len() < 8192 {
+            continue;
+        }
+        let chunks: Vec<IVec2> = (0..32)
+            .flat_map(|z| {
+                (0..32).map(move |x| location * 32 + IVec2::new(x, z))
+            })
+            .filter(|pos| region::chunk_bytes(&file, pos.x, pos.y).is_some())
+            .collect();
+        let kept: Vec<IVec2> = chunks
+            .iter()
+            .copied()
+            .filter(|&pos| {
+                let bytes = region::chunk_bytes(&file, pos.x, pos.y).unwrap();
+                keeps(options, pos, bytes)
+            })
+            .collect();
+
+        let mut bytes_after = 0;
+        for folder in REGION_FOLDERS {
+            let path = world_path.join(folder).join(file_name);
+            let Ok(file) = fs::read(&path) else {
+                continue;
+            };
+            let chunks: Vec<(IVec2, u32, &[u8])> = kept
+                .iter()
+                .filter_map(|&pos| {
+                    let bytes = region::chunk_bytes(&file, pos.x, pos.y)?;
+                    let timestamp =
+                        region::chunk_timestamp(&file, pos.x, pos.y);
+                    Some((pos, timestamp, bytes))
+                })
+                .collect();
+            if chunks.is_empty() {
+                continue;
+            }
+            let pruned = region::build_region(&chunks);
+            if folder == "region" {
+                bytes_after = pruned.len() as u64;
+            }
+            let Some(output) = output else {
+                continue;
+            };
+            let directory = output.join(folder);
+            fs::create_dir_all(&directory).unwrap();
+            let output_path = directory.join(file_name);
+            fs::write(&output_path, pruned).unwrap_or_else(|error| {
+                panic!("{}: {error}", output_path.display())
+            });
+            // Chunks too big for region files are stored next to them
+            for &(pos, _, bytes) in &chunks {
+                if bytes[0] & 128 != 0 {
+                    let name = format!("c.{}.{}.mcc", pos.x, pos.y);
+                    fs::copy(
+                        world_path.join(folder).join(&name),
+                        directory.join(&name),
+                    )
+                    .unwrap_or_else(|error| panic!("{name}: {error}"));
+                }
+            }
+        }
+
+        report.regions.push(RegionReport {
+            name: file_name.to_string_lossy().into_owned(),
+            chunks: chunks.len() as u32,
+            kept: kept.len() as u32,
+            bytes_before: file.len() as u64,
+            bytes_after,
+        });
+    }
+    report
+}
+
+/// The absolute path of a file or folder with every symbolic link resolved,
+/// even if it doesn't exist yet.
+fn resolve(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path)
+        .unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+    path.ancestors()
+        .find_map(|ancestor| {
+            let resolved = ancestor.canonicalize().ok()?;
+            Some(resolved.join(path.strip_prefix(ancestor).unwrap()))
+        })
+        .unwrap_or(path)
+}
+
+/// Whether to keep a chunk, from its stored bytes.
+fn keeps(options: &PruneOptions, pos: IVec2, bytes: &[u8]) -> bool {
+    let min = pos * 16;
+    let max = min + 16;
+    if options
+        .keep_areas
+        .iter()
+        .any(|area| min.cmplt(area.end).all() && area.start.cmplt(max).all())
+    {
+        return true;
+    }
+    // Chunks that can't be read here are kept to be safe
+    if bytes[0] != 2 {
+        return true;
+    }
+    let Ok(chunk) =
+        nbt::from_zlib_reader::<_, ChunkSummary>(&mut Cursor::new(&bytes[1..]))
+    else {
+        return true;
+    };
+    chunk.inhabited_time >= options.min_inhabited_time
+        || chunk
+            .sections
+            .iter()
+            .filter_map(|section| section.block_states.as_ref())
+            .flat_map(|block_states| &block_states.palette)
+            .any(|block| options.keep_blocks.matches(block))
+}
+
+/// A table of how many chunks each region keeps, and how much smaller the
+/// world is without the rest.
+impl fmt::Display for PruneReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<16} {:>6} {:>6} {:>10} {:>10}",
+            "region", "chunks", "kept", "before", "after"
+        )?;
+        for region in &self.regions {
+            writeln!(
+                f,
+                "{:<16} {:>6} {:>6} {:>10} {:>10}",
+                region.name,
+                region.chunks,
+                region.kept,
+                megabytes(region.bytes_before),
+                megabytes(region.bytes_after),
+            )?;
+        }
+        let sum = |value: fn(&RegionReport) -> u64| {
+            self.regions.iter().map(value).sum::<u64>()
+        };
+        write!(
+            f,
+            "\n{} of {} chunks kept, {} of {} regions left, {} instead of {}",
+            sum(|region| region.kept.into()),
+            sum(|region| region.chunks.into()),
+            self.regions.iter().filter(|region| region.kept > 0).count(),
+            self.regions.len(),
+            megabytes(sum(|region| region.bytes_after)),
+            megabytes(sum(|region| region.bytes_before)),
+        )
+    }
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chunk::{Chunk, AIR},
+        test_util::{self, TestChunk},
+    };
+    use nbt::Value;
+
+    /// A world with a region of chunks along the X axis that each have the
+    /// given inhabited time and block, with an entity region for the first
+    /// two.
+    fn world(chunks: &[(i64, &str)]) -> PathBuf {
+        let palettes: Vec<[&str; 1]> =
+            chunks.iter().map(|&(_, block)| [block]).collect();
+        let chunks: Vec<_> = chunks
+            .iter()
+            .zip(&palettes)
+            .enumerate()
+            .map(|(x, (&(inhabited_time, _), palette))| TestChunk {
+                pos: IVec2::new(x as i32, 0),
+                palette,
+                indices: &[],
+                fields: vec![("InhabitedTime", Value::Long(inhabited_time))],
+            })
+            .collect();
+        let directory = test_util::world("prune", &chunks);
+        test_util::write_region(
+            &directory.join("entities/r.0.0.mca"),
+            &[
+                (IVec2::new(0, 0), test_util::chunk(&[])),
+                (IVec2::new(1, 0), test_util::chunk(&[])),
+            ],
+        );
+        // A region with nothing worth keeping
+        test_util::write_region(
+            &directory.join("region/r.1.0.mca"),
+            &[(IVec2::ZERO, test_util::chunk(&[]))],
+        );
+        directory
+    }
+
+    fn options() -> PruneOptions {
+        PruneOptions {
+            min_inhabited_time: 100,
+            keep_areas: vec![IVec2::new(70, 0)..IVec2::new(75, 1)],
+            keep_blocks: BlockFilter::parse(PLAYER_BLOCKS).unwrap(),
+        }
+    }
+
+    #[test]
+    fn removes_chunks_no_one_spent_time_in() {
+        let world = world(&[
+            (1000, "minecraft:stone"),
+            (10, "minecraft:stone"),
+            (10, "minecraft:hopper"),
+            (0, "minecraft:stone"),
+            // Kept by the keep area
+            (0, "minecraft:stone"),
+        ]);
+        let output = test_util::temp_dir("pruned");
+        let report = prune(&world, &options(), Some(&output));
+
+        let regions: Vec<_> = report
+            .regions
+            .iter()
+            .map(|region| (region.name.as_str(), region.chunks, region.kept))
+            .collect();
+        assert_eq!(regions, [("r.0.0.mca", 5, 3), ("r.1.0.mca", 1, 0)]);
+        assert!(!output.join("region/r.1.0.mca").exists());
+
+        let file = fs::read(output.join("region/r.0.0.mca")).unwrap();
+        assert_eq!(file.len() as u64, report.regions[0].bytes_after);
+        let kept: Vec<bool> = (0..5)
+            .map(|x| region::read_chunk::<Chunk>(&file, x, 0).is_some())
+            .collect();
+        assert_eq!(kept, [true, false, true, false, true]);
+        let chunk: Chunk = region::read_chunk(&file, 2, 0).unwrap();
+        assert_eq!(chunk.inhabited_time, 10);
+        // The kept chunks are packed into the sectors after the header
+        let sectors: Vec<u8> =
+            file[..12].iter().skip(2).step_by(4).copied().collect();
+        assert_eq!(sectors, [2, 0, 2 + file[3]]);
+
+        let entities = fs::read(output.join("entities/r.0.0.mca")).unwrap();
+        let kept: Vec<bool> = (0..2)
+            .map(|x| region::chunk_bytes(&entities, x, 0).is_some())
+            .collect();
+        assert_eq!(kept, [true, false]);
+    }
+
+    #[test]
+    fn dry_runs_write_nothing() {
+        let world = world(&[(0, "minecraft:stone"), (1000, "minecraft:dirt")]);
+        let report = prune(&world, &options(), None);
+        assert_eq!(report.regions[0].kept, 1);
+        assert!(report.regions[0].bytes_after < report.regions[0].bytes_before);
+        assert!(report.to_string().ends_with(
+            "1 of 3 chunks kept, 1 of 2 regions left, 0.0 MB instead of 0.0 MB"
+        ));
+    }
+
+    #[test]
+    fn skips_empty_regions() {
+        let world = world(&[(1000, "minecraft:stone")]);
+        fs::write(world.join("entities/r.0.0.mca"), []).unwrap();
+        fs::write(world.join("region/r.2.0.mca"), []).unwrap();
+        let output = test_util::temp_dir("pruned-empty");
+        let report = prune(&world, &options(), Some(&output));
+        let names: Vec<_> =
+            report.regions.iter().map(|region| &region.name).collect();
+        assert_eq!(names, ["r.0.0.mca", "r.1.0.mca"]);
+        assert!(output.join("region/r.0.0.mca").exists());
+        assert!(!output.join("entities/r.0.0.mca").exists());
+    }
+
+    #[test]
+    #[should_panic = "which is in the world's folder"]
+    fn refuses_to_write_into_the_world() {
+        let world = world(&[(0, "minecraft:stone")]);
+        prune(&world, &options(), Some(&world.join("pruned")));
+    }
+
+    #[test]
+    fn keeps_beds_and_shulker_boxes_of_every_color() {
+        let filter = BlockFilter::parse(PLAYER_BLOCKS).unwrap();
+        for color in [
+            "white",
+            "orange",
+            "magenta",
+            "light_blue",
+            "yellow",
+            "lime",
+            "pink",
+            "gray",
+            "light_gray",
+            "cyan",
+            "purple",
+            "blue",
+            "brown",
+            "green",
+            "red",
+            "black",
+        ] {
+            for block in
+                [format!("{color}_bed"), format!("{color}_shulker_box")]
+            {
+                assert!(filter.matches(&Block::parse(&block)), "{block}");
+            }
+        }
+        assert!(!filter.matches(&AIR));
+    }
+}
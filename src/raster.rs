@@ -0,0 +1,493 @@
+use crate::{
+    camera::Camera,
+    culling::{self, Frustum},
+    mesh::{highlight_alpha, HighlightVertex, Mesh},
+    sky::{Sky, SkyTextures},
+    texture::{Atlas, Image, TILE_SIZE},
+};
+use glam::{Mat4, Vec2, Vec3, Vec4, Vec4Swizzles};
+use std::thread;
+
+#[cfg(test)]
+mod golden;
+
+/// Draws a mesh on the CPU the same way the shaders do, for rendering
+/// images without a window.
+pub struct SoftwareRenderer<'a> {
+    /// The triangles to draw.
+    pub mesh: &'a Mesh,
+    /// The textures of the mesh.
+    pub atlas: &'a Atlas,
+    /// The colors to draw the sky and fog with.
+    pub sky: &'a Sky,
+    /// The sun and the moon.
+    pub sky_textures: &'a SkyTextures,
+    /// How far away terrain fades into the fog.
+    pub view_distance: f32,
+    /// Whether to skip sections hidden behind terrain.
+    pub cave_culling: bool,
+    /// The distance beyond which chunks are drawn with less detail.
+    pub lod_distance: Option<f32>,
+    /// Whether to fill the background with the sky instead of leaving it
+    /// transparent.
+    pub draw_sky: bool,
+}
+
+/// A vertex after projection, with its attributes divided by W so that
+/// they can be interpolated linearly across the screen.
+#[derive(Clone, Copy)]
+struct ScreenVertex {
+    /// X and Y in pixels and Z in normalized device coordinates.
+    pos: Vec3,
+    inverse_w: f32,
+    uv: Vec2,
+    light_level: f32,
+    world_pos: Vec3,
+}
+
+struct ScreenTriangle {
+    vertices: [ScreenVertex; 3],
+    texture_index: usize,
+}
+
+struct ScreenHighlight {
+    vertices: [ScreenVertex; 3],
+    color: Vec3,
+}
+
+impl SoftwareRenderer<'_> {
+    /// Draws the mesh as seen by a camera onto a new image.
+    pub fn render(&self, camera: &Camera, width: u32, height: u32) -> Image {
+        let view_proj =
+            camera.view_proj(width as f32 / height as f32, self.view_distance);
+        self.render_view(camera, view_proj, width, height)
+    }
+
+    /// Renders with a projection other than the camera's own, such as one
+    /// cropped to part of its view.
+    pub fn render_view(
+        &self,
+        camera: &Camera,
+        view_proj: Mat4,
+        width: u32,
+        height: u32,
+    ) -> Image {
+        let triangles = self.project(camera, view_proj, width, height);
+        let highlights = self.project_highlights(view_proj, width, height);
+
+        let mut pixels = vec![0; width as usize * height as usize * 4];
+        let threads = thread::available_parallelism().map_or(1, usize::from);
+        let rows_per_band = (height as usize).div_ceil(threads).max(1);
+        thread::scope(|scope| {
+            for (band, band_pixels) in pixels
+                .chunks_mut(rows_per_band * width as usize * 4)
+                .enumerate()
+            {
+                let (triangles, highlights) = (&triangles, &highlights);
+                scope.spawn(move || {
+                    self.draw_band(
+                        triangles,
+                        highlights,
+                        view_proj,
+                        camera,
+                        band_pixels,
+                        width,
+                        height,
+                        band * rows_per_band,
+                    );
+                });
+            }
+        });
+
+        Image {
+            width,
+            height,
+            pixels: pixels.into(),
+        }
+    }
+
+    /// Projects the visible triangles to the screen.
+    fn project(
+        &self,
+        camera: &Camera,
+        view_proj: Mat4,
+        width: u32,
+        height: u32,
+    ) -> Vec<ScreenTriangle> {
+        let mesh = self.mesh;
+        let frustum = Frustum::new(view_proj);
+        let (sections, lods) = culling::draw_list(
+            &mesh.sections,
+            &mesh.lods,
+            camera.position,
+            &frustum,
+            self.cave_culling && !camera.is_orthographic(),
+            self.lod_distance,
+        );
+        let index_ranges = sections
+            .into_iter()
+            .map(|index| mesh.sections[index].indices.clone())
+            .chain(
+                lods.into_iter()
+                    .map(|index| mesh.lods[index].indices.clone()),
+            );
+
+        let size = Vec2::new(width as f32, height as f32);
+        let mut triangles = Vec::new();
+        for range in index_ranges {
+            for triangle in mesh.indices[range].chunks_exact(3) {
+                let vertices = [0, 1, 2].map(|i| {
+                    let vertex = &mesh.vertices[triangle[i] as usize];
+                    (view_proj * vertex.pos.extend(1.0), vertex)
+                });
+                for clipped in clip_near(vertices) {
+                    let screen = clipped.map(|(clip_pos, vertex)| {
+                        let uv_light = vertex
+                            .get(|v| v.uv.extend(v.light_level).extend(0.0));
+                        let world_pos = vertex.get(|v| v.pos.extend(0.0));
+                        screen_vertex(
+                            clip_pos,
+                            uv_light.xy(),
+                            uv_light.z,
+                            world_pos.xyz(),
+                            size,
+                        )
+                    });
+                    // Faces are counterclockwise from the front, which is
+                    // clockwise once Y points down
+                    let [a, b, c] = screen.map(|vertex| vertex.pos.truncate());
+                    if (b - a).perp_dot(c - a) >= 0.0 {
+                        continue;
+                    }
+                    triangles.push(ScreenTriangle {
+                        vertices: screen,
+                        texture_index: mesh.vertices[triangle[0] as usize]
+                            .texture_index
+                            as usize,
+                    });
+                }
+            }
+        }
+        triangles
+    }
+
+    /// Projects the faces of highlights to the screen, both the front and
+    /// the back of them.
+    fn project_highlights(
+        &self,
+        view_proj: Mat4,
+        width: u32,
+        height: u32,
+    ) -> Vec<ScreenHighlight> {
+        let highlights = &self.mesh.highlights;
+        let size = Vec2::new(width as f32, height as f32);
+        let mut triangles = Vec::new();
+        for triangle in highlights.indices.chunks_exact(3) {
+            let vertices = [0, 1, 2].map(|i| {
+                let vertex: &HighlightVertex =
+                    &highlights.vertices[triangle[i] as usize];
+                (view_proj * vertex.pos.extend(1.0), vertex)
+            });
+            for clipped in clip_near(vertices) {
+                triangles.push(ScreenHighlight {
+                    vertices: clipped.map(|(clip_pos, vertex)| {
+                        let uv = vertex.get(|v| v.uv.extend(0.0).extend(0.0));
+                        let world_pos = vertex.get(|v| v.pos.extend(0.0));
+                        screen_vertex(
+                            clip_pos,
+                            uv.xy(),
+                            1.0,
+                            world_pos.xyz(),
+                            size,
+                        )
+                    }),
+                    color: vertices[0].1.color,
+                });
+            }
+        }
+        triangles
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_band(
+        &self,
+        triangles: &[ScreenTriangle],
+        highlights: &[ScreenHighlight],
+        view_proj: Mat4,
+        camera: &Camera,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+        first_row: usize,
+    ) {
+        let width = width as usize;
+        let rows = pixels.len() / 4 / width;
+        let mut depth = vec![f32::INFINITY; width * rows];
+        let [fog_start, fog_end] =
+            self.sky.fog_distances(camera, self.view_distance);
+
+        for triangle in triangles {
+            rasterize(
+                &triangle.vertices,
+                width,
+                first_row,
+                rows,
+                |index, weights, z| {
+                    if z >= depth[index] {
+                        return;
+                    }
+                    let uv_light =
+                        interpolate(&triangle.vertices, weights, |v| {
+                            v.uv.extend(v.light_level).extend(0.0)
+                        });
+                    let world_pos =
+                        interpolate(&triangle.vertices, weights, |v| {
+                            v.world_pos.extend(0.0)
+                        });
+
+                    let texel =
+                        self.sample(triangle.texture_index, uv_light.xy());
+                    if texel[3] == 0 {
+                        return;
+                    }
+                    depth[index] = z;
+                    let color = Vec3::new(
+                        f32::from(texel[0]),
+                        f32::from(texel[1]),
+                        f32::from(texel[2]),
+                    ) / 255.0
+                        * uv_light.z;
+                    let fog = smoothstep(
+                        fog_start,
+                        fog_end,
+                        world_pos.xyz().distance(camera.position),
+                    );
+                    write_pixel(
+                        &mut pixels[index * 4..][..4],
+                        color.lerp(self.sky.fog_color, fog),
+                    );
+                },
+            );
+        }
+
+        if self.draw_sky {
+            self.draw_sky_band(
+                &depth, view_proj, pixels, width, height, first_row,
+            );
+        }
+
+        // Highlights are drawn over everything, without a depth test
+        for highlight in highlights {
+            rasterize(
+                &highlight.vertices,
+                width,
+                first_row,
+                rows,
+                |index, weights, _z| {
+                    let uv = interpolate(&highlight.vertices, weights, |v| {
+                        v.uv.extend(0.0).extend(0.0)
+                    });
+                    blend_pixel(
+                        &mut pixels[index * 4..][..4],
+                        highlight.color,
+                        highlight_alpha(uv.xy()),
+                    );
+                },
+            );
+        }
+    }
+
+    /// Fills the sky in wherever nothing was drawn.
+    fn draw_sky_band(
+        &self,
+        depth: &[f32],
+        view_proj: Mat4,
+        pixels: &mut [u8],
+        width: usize,
+        height: u32,
+        first_row: usize,
+    ) {
+        let inverse_view_proj = view_proj.inverse();
+        for (index, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+            if depth[index] != f32::INFINITY {
+                continue;
+            }
+            let x = index % width;
+            let y = first_row + index / width;
+            let ndc = Vec2::new(
+                (x as f32 + 0.5) / width as f32 * 2.0 - 1.0,
+                1.0 - (y as f32 + 0.5) / height as f32 * 2.0,
+            );
+            let near = inverse_view_proj.project_point3(ndc.extend(-1.0));
+            let far = inverse_view_proj.project_point3(ndc.extend(1.0));
+            let direction = (far - near).normalize();
+            write_pixel(pixel, self.sky.color(direction, self.sky_textures));
+        }
+    }
+
+    /// Looks up a texel in a tile of the atlas like the fragment shader.
+    fn sample(&self, texture_index: usize, uv: Vec2) -> [u8; 4] {
+        let atlas = self.atlas;
+        let columns = atlas.width as usize / TILE_SIZE;
+        let texel = (uv.clamp(Vec2::ZERO, Vec2::splat(0.999))
+            * TILE_SIZE as f32)
+            .as_uvec2();
+        let x = texture_index % columns * TILE_SIZE + texel.x as usize;
+        let y = texture_index / columns * TILE_SIZE + texel.y as usize;
+        let start = (y * atlas.width as usize + x) * 4;
+        atlas.pixels[start..][..4].try_into().unwrap()
+    }
+}
+
+/// Clips a triangle against the near plane, returning up to two
+/// triangles.
+fn clip_near<'a, V>(
+    vertices: [(Vec4, &'a V); 3],
+) -> Vec<[(Vec4, ClippedVertex<'a, V>); 3]> {
+    let inside = |pos: Vec4| pos.z >= -pos.w;
+    let mut polygon = Vec::with_capacity(4);
+    for i in 0..3 {
+        let (from_pos, from) = vertices[i];
+        let (to_pos, to) = vertices[(i + 1) % 3];
+        if inside(from_pos) {
+            polygon.push((from_pos, ClippedVertex::Original(from)));
+        }
+        if inside(from_pos) != inside(to_pos) {
+            let t = (from_pos.z + from_pos.w)
+                / ((from_pos.z + from_pos.w) - (to_pos.z + to_pos.w));
+            polygon.push((
+                from_pos.lerp(to_pos, t),
+                ClippedVertex::Between(from, to, t),
+            ));
+        }
+    }
+    (2..polygon.len())
+        .map(|i| [polygon[0], polygon[i - 1], polygon[i]])
+        .collect()
+}
+
+/// A vertex of a triangle or a point on one of its edges.
+enum ClippedVertex<'a, V> {
+    Original(&'a V),
+    Between(&'a V, &'a V, f32),
+}
+
+impl<V> Clone for ClippedVertex<'_, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for ClippedVertex<'_, V> {}
+
+impl<V> ClippedVertex<'_, V> {
+    /// An attribute of the vertex, interpolated along the edge if it is on
+    /// one.
+    fn get(self, attribute: impl Fn(&V) -> Vec4) -> Vec4 {
+        match self {
+            Self::Original(vertex) => attribute(vertex),
+            Self::Between(from, to, t) => {
+                attribute(from).lerp(attribute(to), t)
+            }
+        }
+    }
+}
+
+fn screen_vertex(
+    clip_pos: Vec4,
+    uv: Vec2,
+    light_level: f32,
+    world_pos: Vec3,
+    screen_size: Vec2,
+) -> ScreenVertex {
+    let inverse_w = 1.0 / clip_pos.w;
+    let ndc = clip_pos.xyz() * inverse_w;
+    ScreenVertex {
+        pos: Vec3::new(
+            (ndc.x + 1.0) * 0.5 * screen_size.x,
+            (1.0 - ndc.y) * 0.5 * screen_size.y,
+            ndc.z,
+        ),
+        inverse_w,
+        uv: uv * inverse_w,
+        light_level: light_level * inverse_w,
+        world_pos: world_pos * inverse_w,
+    }
+}
+
+/// Calls `f` with the index within the band, the barycentric weights and
+/// the depth of every pixel in a band of rows whose center is inside a
+/// triangle and between the near and far planes.
+fn rasterize(
+    vertices: &[ScreenVertex; 3],
+    width: usize,
+    first_row: usize,
+    rows: usize,
+    mut f: impl FnMut(usize, Vec3, f32),
+) {
+    let [a, b, c] = vertices.map(|vertex| vertex.pos);
+    let min = a.min(b).min(c);
+    let max = a.max(b).max(c);
+    let x_range = (min.x.floor().max(0.0) as usize)
+        ..(max.x.ceil().max(0.0) as usize).min(width);
+    let y_range = (min.y.floor().max(first_row as f32) as usize)
+        ..(max.y.ceil().max(0.0) as usize).min(first_row + rows);
+    let area = (b - a).truncate().perp_dot((c - a).truncate());
+
+    for y in y_range {
+        for x in x_range.clone() {
+            let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let weights = Vec3::new(
+                (c - b).truncate().perp_dot(p - b.truncate()),
+                (a - c).truncate().perp_dot(p - c.truncate()),
+                (b - a).truncate().perp_dot(p - a.truncate()),
+            ) / area;
+            if weights.cmplt(Vec3::ZERO).any() {
+                continue;
+            }
+            let z = weights.dot(Vec3::new(a.z, b.z, c.z));
+            if (-1.0..=1.0).contains(&z) {
+                f((y - first_row) * width + x, weights, z);
+            }
+        }
+    }
+}
+
+/// Interpolates an attribute of a triangle's vertices at a point inside it,
+/// undoing the division by W to interpolate in perspective.
+fn interpolate(
+    vertices: &[ScreenVertex; 3],
+    weights: Vec3,
+    attribute: impl Fn(&ScreenVertex) -> Vec4,
+) -> Vec4 {
+    let [a, b, c] = vertices;
+    let inverse_w =
+        weights.dot(Vec3::new(a.inverse_w, b.inverse_w, c.inverse_w));
+    (attribute(a) * weights.x
+        + attribute(b) * weights.y
+        + attribute(c) * weights.z)
+        / inverse_w
+}
+
+fn write_pixel(pixel: &mut [u8], color: Vec3) {
+    let [r, g, b] = (color.clamp(Vec3::ZERO, Vec3::ONE) * 255.0)
+        .round()
+        .to_array()
+        .map(|channel| channel as u8);
+    pixel.copy_from_slice(&[r, g, b, 255]);
+}
+
+/// Draws a color over a pixel, which may be transparent.
+fn blend_pixel(pixel: &mut [u8], color: Vec3, alpha: f32) {
+    let [r, g, b, a] = [0, 1, 2, 3].map(|i| f32::from(pixel[i]) / 255.0);
+    let below = Vec3::new(r, g, b) * a * (1.0 - alpha);
+    let blended_alpha = alpha + a * (1.0 - alpha);
+    let blended = (color * alpha + below) / blended_alpha;
+    write_pixel(pixel, blended);
+    pixel[3] = (blended_alpha * 255.0).round() as u8;
+}
+
+fn smoothstep(from: f32, to: f32, x: f32) -> f32 {
+    let t = ((x - from) / (to - from)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
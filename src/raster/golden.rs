@@ -0,0 +1,382 @@
+//! Renders canonical scenes and compares them with reference images in
+//! `tests/golden`, so that changes to the mesher or the renderer that
+//! alter the output are caught.
+//!
+//! The scenes use the made-up textures in `tests/resource-pack`, since
+//! Minecraft's own can't be redistributed. Run the tests with
+//! `UPDATE_GOLDEN_IMAGES=1` to replace the reference images after an
+//! intended change.
+
+use super::SoftwareRenderer;
+use crate::{
+    block_source::BlockSource,
+    camera::{Camera, Projection},
+    chunk::{Block, AIR},
+    mesh::Mesh,
+    sky::{Sky, SkyTextures},
+    test_util,
+    texture::{read_image, Atlas, Image},
+    BlockChange, BlockFilter, Options, Slice, WorldDiff,
+};
+use glam::{IVec2, IVec3, Vec3, Vec3Swizzles};
+use internment::Intern;
+use std::{fs, path::Path};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+/// How different a pixel's color has to be, from 0 to 255, to be noticed.
+const PIXEL_THRESHOLD: f32 = 4.0;
+/// The fraction of pixels that may differ noticeably, for differences in
+/// rounding on other platforms.
+const MAX_DIFFERENT_PIXELS: f32 = 0.002;
+
+fn render(world: &dyn BlockSource, camera: Camera) -> Image {
+    render_with_options(world, camera, &options())
+}
+
+fn options() -> Options {
+    test_util::options(IVec2::splat(-8)..IVec2::splat(8))
+}
+
+fn render_with_options(
+    world: &dyn BlockSource,
+    camera: Camera,
+    options: &Options,
+) -> Image {
+    let resource_pack = Path::new("tests/resource-pack");
+    let mesh = Mesh::build(world, options);
+    let atlas = Atlas::build(&mesh.textures, resource_pack, world.maps());
+    let renderer = SoftwareRenderer {
+        mesh: &mesh,
+        atlas: &atlas,
+        sky: &Sky::new(world.dimension(), options.day_time(world)),
+        sky_textures: &SkyTextures::load(resource_pack),
+        view_distance: 64.0,
+        cave_culling: false,
+        lod_distance: None,
+        draw_sky: false,
+    };
+    renderer.render(&camera, WIDTH, HEIGHT)
+}
+
+/// A camera at `position` looking at `target`.
+fn camera(position: Vec3, target: Vec3) -> Camera {
+    let direction = target - position;
+    Camera {
+        position,
+        yaw: direction.x.atan2(direction.z),
+        pitch: (-direction.y).atan2(direction.x.hypot(direction.z)),
+        vfov: 1.0,
+        projection: Projection::Perspective,
+    }
+}
+
+/// Compares an image with its reference, saving it along with a picture of
+/// the differences in `target/golden` if they don't match.
+fn assert_matches_reference(name: &str, image: &Image) {
+    let reference_path = Path::new("tests/golden").join(format!("{name}.png"));
+    if std::env::var_os("UPDATE_GOLDEN_IMAGES").is_some() {
+        fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+        image.write_png(&reference_path).unwrap();
+        return;
+    }
+    let Some(reference) = read_image(&reference_path) else {
+        panic!(
+            "{} is missing, run the tests with `UPDATE_GOLDEN_IMAGES=1` to \
+             create it",
+            reference_path.display()
+        );
+    };
+    assert_eq!(
+        (image.width, image.height),
+        (reference.width, reference.height),
+        "{name} is a different size than its reference"
+    );
+
+    let differences = difference(image, &reference);
+    let different_pixels =
+        differences.iter().filter(|&&d| d > PIXEL_THRESHOLD).count();
+    let fraction = different_pixels as f32 / differences.len() as f32;
+    if fraction > MAX_DIFFERENT_PIXELS {
+        let directory = Path::new("target/golden");
+        fs::create_dir_all(directory).unwrap();
+        image
+            .write_png(&directory.join(format!("{name}.png")))
+            .unwrap();
+        Image {
+            width: image.width,
+            height: image.height,
+            pixels: differences
+                .iter()
+                .flat_map(|&d| {
+                    let d = (d * 4.0).min(255.0) as u8;
+                    [d, 0, 0, 255]
+                })
+                .collect(),
+        }
+        .write_png(&directory.join(format!("{name}-diff.png")))
+        .unwrap();
+        panic!(
+            "{:.2}% of the pixels of {name} differ from its reference, see \
+             {}",
+            fraction * 100.0,
+            directory.display()
+        );
+    }
+}
+
+/// How noticeable the difference is at each pixel. Both images are blurred
+/// a little first so that edges which moved by less than a pixel count for
+/// less, and colors are compared by their luma and chroma, in which people
+/// see differences in brightness the most.
+fn difference(a: &Image, b: &Image) -> Vec<f32> {
+    let (a, b) = (blur(a), blur(b));
+    a.iter()
+        .zip(&b)
+        .map(|(a, b)| {
+            let d = *a - *b;
+            let luma = d.x.mul_add(0.299, d.y.mul_add(0.587, d.z * 0.114));
+            let chroma_blue = (d.z - luma) * 0.564;
+            let chroma_red = (d.x - luma) * 0.713;
+            Vec3::new(luma, chroma_blue * 0.5, chroma_red * 0.5)
+                .length()
+                .max((a.w - b.w).abs())
+        })
+        .collect()
+}
+
+/// Averages each pixel with its neighbours, with colors premultiplied by
+/// alpha so that transparent pixels don't add their color.
+fn blur(image: &Image) -> Vec<glam::Vec4> {
+    let (width, height) = (image.width as i32, image.height as i32);
+    let pixel = |x: i32, y: i32| {
+        let [r, g, b, a] = image
+            .pixel(x.clamp(0, width - 1) as u32, y.clamp(0, height - 1) as u32)
+            .map(f32::from);
+        glam::Vec4::new(r * a / 255.0, g * a / 255.0, b * a / 255.0, a)
+    };
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let mut sum = pixel(x, y) * 4.0;
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                sum += pixel(x + dx, y + dy);
+            }
+            sum / 8.0
+        })
+        .collect()
+}
+
+#[test]
+fn every_material() {
+    let blocks = [
+        "stone",
+        "oak_planks",
+        "glass",
+        "blue_stained_glass",
+        "ice",
+        "spruce_leaves",
+    ]
+    .map(Block::parse);
+    let world = |pos: IVec3| {
+        Some(match pos.to_array() {
+            [x @ -6..=5, 0, 0] if x.rem_euclid(2) == 0 => {
+                blocks[((x + 6) / 2) as usize]
+            }
+            [_, -1, _] => Block::parse("dirt"),
+            _ => *AIR,
+        })
+    };
+    let image = render(
+        &world,
+        camera(Vec3::new(0.5, 4.0, -7.0), Vec3::new(0.0, 0.5, 0.5)),
+    );
+    assert_matches_reference("every_material", &image);
+}
+
+#[test]
+fn vines_in_every_direction() {
+    let vine =
+        |direction: &str| Block::parse(&format!("vine[{direction}=true]"));
+    let glow_lichen = Block::parse("glow_lichen[down=true,north=true]");
+    let world = |pos: IVec3| {
+        Some(match pos.to_array() {
+            // A pillar of stone with vines on every side
+            [0, 0..=2, 0] => Block::parse("stone"),
+            [-1, 0..=2, 0] => vine("east"),
+            [1, 0..=2, 0] => vine("west"),
+            [0, 0..=2, -1] => vine("south"),
+            [0, 0..=2, 1] => vine("north"),
+            // Hanging from a ledge
+            [2..=3, 3, -1..=1] => Block::parse("stone"),
+            [2..=3, 2, -1..=1] => vine("up"),
+            [-3, 0, -2] => glow_lichen,
+            [_, -1, _] => Block::parse("dirt"),
+            _ => *AIR,
+        })
+    };
+    let image = render(
+        &world,
+        camera(Vec3::new(-3.0, 1.2, -6.0), Vec3::new(1.0, 1.8, 0.5)),
+    );
+    assert_matches_reference("vines_in_every_direction", &image);
+}
+
+#[test]
+fn cross_plants() {
+    let plants = [
+        "grass",
+        "fern",
+        "dead_bush",
+        "brown_mushroom",
+        "red_mushroom",
+    ]
+    .map(Block::parse);
+    let world = |pos: IVec3| {
+        Some(match pos.to_array() {
+            [x @ -2..=2, 0, z @ -1..=1] => {
+                plants[(x + 2 + z + 1).rem_euclid(5) as usize]
+            }
+            [_, -1, _] => Block::parse("dirt"),
+            _ => *AIR,
+        })
+    };
+    let image = render(
+        &world,
+        camera(Vec3::new(0.5, 2.5, -3.5), Vec3::new(0.5, 0.3, 0.5)),
+    );
+    assert_matches_reference("cross_plants", &image);
+}
+
+/// A hollow box of stone with a hole in its roof, lit by a light source in
+/// a corner and by the sky through the hole.
+struct LitCave;
+
+impl BlockSource for LitCave {
+    fn block_at(&self, pos: IVec3) -> Option<Intern<Block>> {
+        let inside = pos.cmpgt(IVec3::new(-6, 0, -6)).all()
+            && pos.cmplt(IVec3::new(6, 5, 6)).all();
+        let hole = pos.y == 5 && (1..=2).contains(&pos.x) && pos.z == 0;
+        let outside = pos.abs().max_element() > 6 || pos.y > 5 || pos.y < 0;
+        Some(if inside || hole || outside {
+            *AIR
+        } else {
+            Block::parse("stone")
+        })
+    }
+
+    fn light_at(&self, pos: IVec3) -> u8 {
+        let torch = IVec3::new(-4, 1, 4);
+        let block_light = 14 - distance(pos, torch).min(14);
+        let sky = IVec3::new(pos.x.clamp(1, 2), pos.y.max(5), 0);
+        let sky_light = if pos.y > 5 {
+            15
+        } else {
+            15 - distance(pos, sky).min(15)
+        };
+        (block_light + sky_light) as u8
+    }
+}
+
+/// How many blocks light has to travel from one position to another.
+fn distance(a: IVec3, b: IVec3) -> i32 {
+    let d = (a - b).abs();
+    d.x + d.y + d.z
+}
+
+#[test]
+fn lit_cave() {
+    let image = render(
+        &LitCave,
+        camera(Vec3::new(4.5, 3.5, -4.5), Vec3::new(-3.0, 1.0, 3.0)),
+    );
+    assert_matches_reference("lit_cave", &image);
+}
+
+/// Planks and glass buried in stone, highlighted through it, and on their
+/// own with the stone hidden.
+#[test]
+fn highlighted_blocks() {
+    let world = |pos: IVec3| {
+        Some(match pos.to_array() {
+            [-2 | 1, 1, 0] | [0, 2, 1] => Block::parse("oak_planks"),
+            [2, 3, -1] => Block::parse("glass"),
+            [-3..=3, 0..=4, -2..=2] => Block::parse("stone"),
+            _ => *AIR,
+        })
+    };
+    let camera = camera(Vec3::new(2.0, 8.0, -8.0), Vec3::new(0.0, 2.0, 0.0));
+    let mut options = options();
+    options.highlight = Some(BlockFilter::parse("oak_planks,glass").unwrap());
+    let image = render_with_options(&world, camera, &options);
+    assert_matches_reference("highlighted_blocks", &image);
+
+    options.hide_unhighlighted = true;
+    let image = render_with_options(&world, camera, &options);
+    assert_matches_reference("only_highlighted_blocks", &image);
+}
+
+/// A hollow box of planks around a pillar, cut open from above and from
+/// the side, so that the faces inside it show.
+#[test]
+fn cutaway() {
+    let world = |pos: IVec3| {
+        Some(match pos.to_array() {
+            [0, 0..=3, 0] => Block::parse("stone"),
+            [-3..=3, 0..=4, -3..=3] if pos.abs().max_element() == 3 => {
+                Block::parse("oak_planks")
+            }
+            [_, 0 | 4, _] if pos.xz().abs().max_element() <= 3 => {
+                Block::parse("oak_planks")
+            }
+            [_, -1, _] => Block::parse("dirt"),
+            _ => *AIR,
+        })
+    };
+    let mut options = options();
+    options.max_y = Some(2);
+    options.slice = Some(Slice::parse("z<1").unwrap());
+    let image = render_with_options(
+        &world,
+        camera(Vec3::new(4.0, 7.0, 7.0), Vec3::new(0.0, 1.0, 0.0)),
+        &options,
+    );
+    assert_matches_reference("cutaway", &image);
+}
+
+/// A wall with a block broken out of it and another put on top, as if
+/// compared with an older copy of the world.
+#[test]
+fn changed_blocks() {
+    let world = |pos: IVec3| {
+        Some(match pos.to_array() {
+            [0, 1, 0] => *AIR,
+            [1, 3, 0] => Block::parse("glass"),
+            [-2..=2, 0..=2, 0] => Block::parse("stone"),
+            [_, -1, _] => Block::parse("dirt"),
+            _ => *AIR,
+        })
+    };
+    let mut options = options();
+    options.changes = Some(WorldDiff {
+        changes: vec![
+            BlockChange {
+                pos: [0, 1, 0],
+                before: Block::parse("stone"),
+                after: *AIR,
+            },
+            BlockChange {
+                pos: [1, 3, 0],
+                before: *AIR,
+                after: Block::parse("glass"),
+            },
+        ],
+        ..WorldDiff::default()
+    });
+    let image = render_with_options(
+        &world,
+        camera(Vec3::new(2.0, 4.0, -6.0), Vec3::new(0.0, 1.5, 0.0)),
+        &options,
+    );
+    assert_matches_reference("changed_blocks", &image);
+}
@@ -1,27 +1,46 @@
-use crate::{chunk::Chunk, Options};
+use crate::Options;
 use glam::IVec2;
+use serde::de::DeserializeOwned;
 use std::{
-    collections::HashMap,
+    collections::{hash_map::DefaultHasher, HashMap},
     ffi::OsStr,
     fs::{self, DirEntry},
+    hash::{Hash, Hasher},
+    io::Cursor,
     ops::Range,
     path::Path,
 };
 
-pub struct Region {
-    pub chunks: [[Option<Chunk>; 32]; 32],
+/// The contents of a region file, which stores 32x32 chunks. `T` is what
+/// each chunk is deserialized as, depending on which folder the region file
+/// is in.
+pub struct Region<T> {
+    pub chunks: Box<[[Option<T>; 32]; 32]>,
+    /// The area whose chunks were loaded.
+    area: Range<IVec2>,
+    /// A hash of the region file, to tell whether it changed.
+    file_hash: u64,
 }
 
-impl Region {
+impl<T: DeserializeOwned> Region<T> {
+    /// Loads every region file in `directory` that overlaps the area to
+    /// render. A missing directory is treated as empty.
+    ///
+    /// Regions in `previous` that were loaded for the same area from files
+    /// with the same contents are reused instead of having their chunks
+    /// deserialized again. The files are still read to tell.
     pub fn load_all(
-        world_path: &Path,
+        directory: &Path,
         options: &Options,
+        mut previous: HashMap<IVec2, Self>,
     ) -> HashMap<IVec2, Self> {
-        fs::read_dir(world_path.join("region"))
-            .unwrap()
+        let Ok(entries) = fs::read_dir(directory) else {
+            return HashMap::new();
+        };
+        entries
             .map(Result::unwrap)
             .map(|entry| {
-                let location = Self::parse_file_name(&entry.file_name());
+                let location = parse_file_name(&entry.file_name());
                 (entry, location)
             })
             .filter(|(_entry, location)| {
@@ -31,46 +50,242 @@ impl Region {
                     && location.y * 512 < options.area.end.y
             })
             .map(|(entry, location)| {
-                Self::load(&entry, options.area.clone(), location)
+                let previous = previous.remove(&location);
+                Self::load(&entry, options.area.clone(), location, previous)
             })
             .collect()
     }
 
-    fn parse_file_name(file_name: &OsStr) -> IVec2 {
-        let mut coordinates = file_name
-            .to_str()
-            .unwrap()
-            .split('.')
-            .skip(1)
-            .map(str::parse)
-            .map(Result::unwrap);
-        IVec2 {
-            x: coordinates.next().unwrap(),
-            y: coordinates.next().unwrap(),
-        }
-    }
-
     fn load(
         entry: &DirEntry,
         area: Range<IVec2>,
         location: IVec2,
+        previous: Option<Self>,
     ) -> (IVec2, Self) {
         let file = fs::read(entry.path()).unwrap();
+        let mut hasher = DefaultHasher::new();
+        file.hash(&mut hasher);
+        let file_hash = hasher.finish();
+        if let Some(previous) = previous.filter(|previous| {
+            previous.area == area && previous.file_hash == file_hash
+        }) {
+            return (location, previous);
+        }
 
-        // This should really use `array::from_fn`, but that would overflow
-        // the stack
-        let mut chunks: [[Option<Chunk>; 32]; 32] = Default::default();
+        // The chunks are built on the heap because they would overflow the
+        // stack, especially on threads other than the main one
+        let mut chunks: Box<[[Option<T>; 32]; 32]> = (0..32)
+            .map(|_| Default::default())
+            .collect::<Box<[_]>>()
+            .try_into()
+            .unwrap_or_else(|_| unreachable!());
         for (chunk_z, column) in chunks.iter_mut().enumerate() {
             for (chunk_x, chunk) in column.iter_mut().enumerate() {
-                *chunk = Chunk::load(
-                    &file,
-                    area.clone(),
-                    chunk_x as i32 + location.x * 32,
-                    chunk_z as i32 + location.y * 32,
-                );
+                let x = chunk_x as i32 + location.x * 32;
+                let z = chunk_z as i32 + location.y * 32;
+                if x * 16 + 15 < area.start.x
+                    || x * 16 >= area.end.x
+                    || z * 16 + 15 < area.start.y
+                    || z * 16 >= area.end.y
+                {
+                    continue;
+                }
+                *chunk = read_chunk(&file, x, z);
             }
         }
 
-        (location, Self { chunks })
+        (
+            location,
+            Self {
+                chunks,
+                area,
+                file_hash,
+            },
+        )
+    }
+}
+
+/// The position of a region from the name of its file, such as `r.-1.2.mca`.
+pub fn parse_file_name(file_name: &OsStr) -> IVec2 {
+    let mut coordinates = file_name
+        .to_str()
+        .unwrap()
+        .split('.')
+        .skip(1)
+        .map(str::parse)
+        .map(Result::unwrap);
+    IVec2 {
+        x: coordinates.next().unwrap(),
+        y: coordinates.next().unwrap(),
+    }
+}
+
+/// Deserializes the chunk at the given chunk coordinates from the contents
+/// of a region file, if it has been generated.
+pub fn read_chunk<T: DeserializeOwned>(
+    file: &[u8],
+    x: i32,
+    z: i32,
+) -> Option<T> {
+    let data = chunk_bytes(file, x, z)?;
+    let compression_scheme = data[0];
+    assert_eq!(
+        compression_scheme, 2,
+        "only zlib chunk compression is supported"
+    );
+    Some(nbt::from_zlib_reader(&mut Cursor::new(&data[1..])).unwrap())
+}
+
+/// The stored bytes of the chunk at the given chunk coordinates, which are
+/// the compression scheme followed by the compressed chunk, if it has been
+/// generated. Files too short for a header, which Minecraft sometimes
+/// leaves empty, have no chunks.
+pub fn chunk_bytes(file: &[u8], x: i32, z: i32) -> Option<&[u8]> {
+    if file.len() < 8192 {
+        return None;
+    }
+    let x = x.rem_euclid(32) as usize;
+    let z = z.rem_euclid(32) as usize;
+    let locations: &[[u8; 4]] = bytemuck::cast_slice(&file[..4096]);
+    let raw_location = locations[z * 32 + x];
+    let data_offset = u32::from_be_bytes([
+        0,
+        raw_location[0],
+        raw_location[1],
+        raw_location[2],
+    ]) as usize
+        * 4096;
+    if data_offset == 0 {
+        return None;
+    }
+    let payload = &file[data_offset..];
+    let length = u32::from_be_bytes(bytemuck::cast_slice(payload)[0]) as usize;
+    Some(&payload[4..][..length])
+}
+
+/// When the chunk at the given chunk coordinates was last saved, in seconds
+/// since the Unix epoch.
+pub fn chunk_timestamp(file: &[u8], x: i32, z: i32) -> u32 {
+    let index = (z.rem_euclid(32) * 32 + x.rem_euclid(32)) as usize * 4;
+    let timestamps: &[[u8; 4]] = bytemuck::cast_slice(&file[4096..8192]);
+    u32::from_be_bytes(timestamps[index / 4])
+}
+
+/// Builds a region file from the stored bytes of chunks, as returned by
+/// [`chunk_bytes`], and their timestamps, by chunk coordinates. The chunks
+/// are laid out one after the other with no free sectors between them.
+pub fn build_region(chunks: &[(IVec2, u32, &[u8])]) -> Vec<u8> {
+    let mut file = vec![0; 8192];
+    for &(pos, timestamp, bytes) in chunks {
+        let index =
+            (pos.y.rem_euclid(32) * 32 + pos.x.rem_euclid(32)) as usize * 4;
+        let sector = file.len() / 4096;
+        file.extend((bytes.len() as u32).to_be_bytes());
+        file.extend(bytes);
+        file.resize(file.len().next_multiple_of(4096), 0);
+        let sector_count = file.len() / 4096 - sector;
+        file[index..index + 4].copy_from_slice(&[
+            (sector >> 16) as u8,
+            (sector >> 8) as u8,
+            sector as u8,
+            sector_count as u8,
+        ]);
+        file[4096 + index..4096 + index + 4]
+            .copy_from_slice(&timestamp.to_be_bytes());
+    }
+    file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chunk::Chunk, test_util};
+
+    #[test]
+    fn reads_chunks_by_position() {
+        let directory = test_util::temp_dir("region");
+        let path = directory.join("r.0.0.mca");
+        test_util::write_region(
+            &path,
+            &[
+                (IVec2::new(0, 0), test_util::chunk(&[])),
+                (IVec2::new(31, 31), test_util::chunk(&[])),
+                (IVec2::new(5, 2), test_util::chunk(&[])),
+            ],
+        );
+        let file = fs::read(&path).unwrap();
+        for (x, z) in [(0, 0), (31, 31), (5, 2), (-27, 34)] {
+            let chunk: Chunk = read_chunk(&file, x, z).unwrap();
+            assert_eq!(chunk.data_version, test_util::DATA_VERSION);
+        }
+        assert!(read_chunk::<Chunk>(&file, 1, 0).is_none());
+        assert!(read_chunk::<Chunk>(&file, 2, 5).is_none());
+    }
+
+    #[test]
+    fn only_loads_chunks_in_the_area() {
+        let directory = test_util::temp_dir("region-area");
+        for name in ["r.0.0.mca", "r.-1.0.mca", "r.1.0.mca"] {
+            test_util::write_region(
+                &directory.join(name),
+                &[
+                    (IVec2::new(0, 0), test_util::chunk(&[])),
+                    (IVec2::new(31, 0), test_util::chunk(&[])),
+                ],
+            );
+        }
+        // From the last chunk of r.-1.0 to the first of r.0.0
+        let options = test_util::options(IVec2::new(-1, 0)..IVec2::new(1, 1));
+        let regions =
+            Region::<Chunk>::load_all(&directory, &options, HashMap::new());
+        let mut locations: Vec<_> = regions.keys().copied().collect();
+        locations.sort_by_key(|location| location.x);
+        assert_eq!(locations, [IVec2::new(-1, 0), IVec2::new(0, 0)]);
+        let loaded = |location: IVec2| {
+            regions[&location].chunks[0]
+                .iter()
+                .map(Option::is_some)
+                .collect::<Vec<_>>()
+        };
+        let mut expected = vec![false; 32];
+        expected[31] = true;
+        assert_eq!(loaded(IVec2::new(-1, 0)), expected);
+        expected.reverse();
+        assert_eq!(loaded(IVec2::new(0, 0)), expected);
+    }
+
+    #[test]
+    fn reuses_regions_whose_files_are_the_same() {
+        let directory = test_util::temp_dir("region-reload");
+        let write = |name: &str, chunk_x: i32| {
+            test_util::write_region(
+                &directory.join(name),
+                &[(IVec2::new(chunk_x, 0), test_util::chunk(&[]))],
+            );
+        };
+        write("r.0.0.mca", 0);
+        write("r.1.0.mca", 0);
+        let options =
+            test_util::options(IVec2::new(0, 0)..IVec2::new(1024, 16));
+        let regions =
+            Region::<Chunk>::load_all(&directory, &options, HashMap::new());
+        let address = |regions: &HashMap<IVec2, Region<Chunk>>,
+                       location: IVec2| {
+            std::ptr::from_ref(&*regions[&location].chunks) as usize
+        };
+        let unchanged = address(&regions, IVec2::new(0, 0));
+
+        write("r.1.0.mca", 1);
+        let regions = Region::<Chunk>::load_all(&directory, &options, regions);
+        assert_eq!(address(&regions, IVec2::new(0, 0)), unchanged);
+        assert!(regions[&IVec2::new(1, 0)].chunks[0][1].is_some());
+
+        // Regions loaded for another area are loaded again
+        let narrow =
+            test_util::options(IVec2::new(512, 0)..IVec2::new(528, 16));
+        let regions = Region::<Chunk>::load_all(&directory, &narrow, regions);
+        assert!(regions[&IVec2::new(1, 0)].chunks[0][1].is_none());
+        let regions = Region::<Chunk>::load_all(&directory, &options, regions);
+        assert!(regions[&IVec2::new(1, 0)].chunks[0][1].is_some());
     }
 }
@@ -1,86 +1,135 @@
 use crate::{
-    mesh::Mesh,
-    shader::{self, Uniforms},
-    world::World,
+    block_source::BlockSource,
+    camera::{
+        self, Camera, Projection, ISOMETRIC_DIRECTIONS, ISOMETRIC_PITCHES,
+        ORTHOGRAPHIC_HEIGHT,
+    },
+    camera_path::CameraPath,
+    controls::{Action, KeyBindings},
+    culling::{self, Frustum},
+    mesh::{HighlightVertex, Lod, Mesh, Section, Vertex},
+    screenshot,
+    shader::{self, HighlightUniforms, SkyUniforms, Uniforms},
+    sky::{Sky, SkyTextures},
+    texture::{Atlas, Image},
     Options,
 };
-use glam::{Mat3, Mat4, Vec3};
-use itertools::Itertools;
+use glam::{Mat3, Mat4, UVec2, Vec2, Vec3};
 use miniquad::{
-    conf::Conf, Bindings, Buffer, BufferLayout, BufferType, Context,
-    EventHandler, FilterMode, KeyCode, PassAction, Pipeline, PipelineParams,
+    conf::Conf, Bindings, BlendFactor, BlendState, BlendValue, Buffer,
+    BufferLayout, BufferType, Context, Equation, EventHandler, FilterMode,
+    KeyCode, MouseButton, PassAction, Pipeline, PipelineParams, RenderPass,
     Shader, Texture, TextureFormat, TextureParams, VertexAttribute,
     VertexFormat,
 };
-use std::{fs::File, path::Path};
+use std::{collections::HashSet, ops::Range};
 
 const MOVE_SPEED: f32 = 0.2;
 const RUN_SPEED: f32 = 1.0;
 const FLY_SPEED: f32 = 0.2;
 const TURN_SPEED: f32 = 0.04;
+/// Radians turned per pixel that the mouse moves.
+const MOUSE_SENSITIVITY: f32 = 0.003;
+/// The biggest image rendered at once for a screenshot, which every GPU we
+/// care about supports. Bigger screenshots are rendered in tiles.
+const MAX_TILE_SIZE: u32 = 4096;
 
-pub fn render(world: World, options: Options) {
-    miniquad::start(Conf::default(), |ctx| {
-        Box::new(Renderer::new(options, world, ctx))
+/// Opens a window that shows the blocks and lets the camera fly around
+/// them.
+pub fn render(world: Box<dyn BlockSource>, options: Options) {
+    miniquad::start(Conf::default(), move |ctx| {
+        Box::new(Renderer::new(options, &*world, ctx))
     });
 }
 
 struct Renderer {
     pipeline: Pipeline,
-    bindings: Bindings,
-    index_count: i32,
-    camera_position: Vec3,
-    camera_pitch: f32,
-    camera_yaw: f32,
-    vfov: f32,
-    key_w: bool,
-    key_a: bool,
-    key_s: bool,
-    key_d: bool,
-    key_h: bool,
-    key_j: bool,
-    key_k: bool,
-    key_l: bool,
-    key_space: bool,
-    key_shift: bool,
-    key_ctrl: bool,
+    sky_pipeline: Pipeline,
+    sky_bindings: Bindings,
+    sky: Sky,
+    /// The distance to the far plane, where fog hides everything.
+    view_distance: f32,
+    sections: Vec<Section>,
+    /// The buffers of each section, or `None` if it has no geometry.
+    section_bindings: Vec<Option<(Bindings, i32)>>,
+    lods: Vec<Lod>,
+    lod_bindings: Vec<Option<(Bindings, i32)>>,
+    highlight_pipeline: Pipeline,
+    /// The buffers of all the highlights, or `None` if there are none.
+    highlight_bindings: Option<(Bindings, i32)>,
+    cave_culling: bool,
+    lod_distance: Option<f32>,
+    camera: Camera,
+    camera_path: Option<CameraPath>,
+    /// When the camera path started playing, if it is playing.
+    path_start: Option<f64>,
+    key_bindings: KeyBindings,
+    /// The actions whose keys are currently held down.
+    held: HashSet<Action>,
+    /// Multiplies how fast the camera moves.
+    speed: f32,
+    mouse_grabbed: bool,
+    /// How many times bigger than the window hi-res screenshots are.
+    screenshot_scale: u32,
+    /// The zoom to go back to when switching to an orthographic projection.
+    orthographic_height: f32,
+    /// Indices into `ISOMETRIC_DIRECTIONS` and `ISOMETRIC_PITCHES` of the
+    /// last isometric view.
+    isometric_direction: usize,
+    isometric_pitch: usize,
 }
 
 impl Renderer {
-    fn new(options: Options, world: World, ctx: &mut Context) -> Self {
-        let mesh = Mesh::build(&world, &options);
-        let index_count = mesh.indices.len() as i32;
-        let vertex_buffer =
-            Buffer::immutable(ctx, BufferType::VertexBuffer, &mesh.vertices);
-        let index_buffer =
-            Buffer::immutable(ctx, BufferType::IndexBuffer, &mesh.indices);
-
-        let pixels = mesh
-            .texture_names
-            .iter()
-            .flat_map(|name| {
-                Vec::from(read_block_texture(&options.resource_pack_path.join(
-                    format!("assets/minecraft/textures/block/{name}.png"),
-                )))
-            })
-            .collect::<Vec<u8>>();
+    fn new(
+        options: Options,
+        world: &dyn BlockSource,
+        ctx: &mut Context,
+    ) -> Self {
+        let mesh = Mesh::build(world, &options);
+
+        let atlas = Atlas::build(
+            &mesh.textures,
+            &options.resource_pack_path,
+            world.maps(),
+        );
         let texture = Texture::from_data_and_format(
             ctx,
-            &pixels,
+            &atlas.pixels,
             TextureParams {
                 format: TextureFormat::RGBA8,
-                wrap: miniquad::TextureWrap::Repeat,
+                wrap: miniquad::TextureWrap::Clamp,
                 filter: FilterMode::Nearest,
-                width: 16,
-                height: mesh.texture_names.len() as u32 * 16,
+                width: atlas.width,
+                height: atlas.height,
             },
         );
 
-        let bindings = Bindings {
-            vertex_buffers: vec![vertex_buffer],
-            index_buffer,
-            images: vec![texture],
-        };
+        let section_bindings = mesh
+            .sections
+            .iter()
+            .map(|section| {
+                buffers(
+                    ctx,
+                    &mesh,
+                    section.vertices.clone(),
+                    section.indices.clone(),
+                    texture,
+                )
+            })
+            .collect();
+        let lod_bindings = mesh
+            .lods
+            .iter()
+            .map(|lod| {
+                buffers(
+                    ctx,
+                    &mesh,
+                    lod.vertices.clone(),
+                    lod.indices.clone(),
+                    texture,
+                )
+            })
+            .collect();
 
         let shader =
             Shader::new(ctx, shader::VERTEX, shader::FRAGMENT, shader::meta())
@@ -104,98 +153,396 @@ impl Renderer {
             },
         );
 
+        let highlight_shader = Shader::new(
+            ctx,
+            shader::HIGHLIGHT_VERTEX,
+            shader::HIGHLIGHT_FRAGMENT,
+            shader::highlight_meta(),
+        )
+        .unwrap();
+        // Highlights are seen through everything, from both sides
+        let highlight_pipeline = Pipeline::with_params(
+            ctx,
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("pos", VertexFormat::Float3),
+                VertexAttribute::new("uv", VertexFormat::Float2),
+                VertexAttribute::new("color", VertexFormat::Float3),
+            ],
+            highlight_shader,
+            PipelineParams {
+                cull_face: miniquad::CullFace::Nothing,
+                depth_test: miniquad::Comparison::Always,
+                depth_write: false,
+                color_blend: Some(BlendState::new(
+                    Equation::Add,
+                    BlendFactor::Value(BlendValue::SourceAlpha),
+                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                )),
+                ..Default::default()
+            },
+        );
+        let highlights = &mesh.highlights;
+        let highlight_bindings = (!highlights.indices.is_empty()).then(|| {
+            let vertices: &[HighlightVertex] = &highlights.vertices;
+            let bindings = Bindings {
+                vertex_buffers: vec![Buffer::immutable(
+                    ctx,
+                    BufferType::VertexBuffer,
+                    vertices,
+                )],
+                index_buffer: Buffer::immutable(
+                    ctx,
+                    BufferType::IndexBuffer,
+                    &highlights.indices,
+                ),
+                images: Vec::new(),
+            };
+            (bindings, highlights.indices.len() as i32)
+        });
+
+        let sky_shader = Shader::new(
+            ctx,
+            shader::SKY_VERTEX,
+            shader::SKY_FRAGMENT,
+            shader::sky_meta(),
+        )
+        .unwrap();
+        let sky_pipeline = Pipeline::new(
+            ctx,
+            &[BufferLayout::default()],
+            &[VertexAttribute::new("pos", VertexFormat::Float2)],
+            sky_shader,
+        );
+        // A single triangle covering the whole screen
+        let sky_vertices = [
+            Vec2::new(-1.0, -1.0),
+            Vec2::new(3.0, -1.0),
+            Vec2::new(-1.0, 3.0),
+        ];
+        let sky_textures = SkyTextures::load(&options.resource_pack_path);
+        let sky_bindings = Bindings {
+            vertex_buffers: vec![Buffer::immutable(
+                ctx,
+                BufferType::VertexBuffer,
+                &sky_vertices,
+            )],
+            index_buffer: Buffer::immutable(
+                ctx,
+                BufferType::IndexBuffer,
+                &[0u32, 1, 2],
+            ),
+            images: vec![
+                sky_texture(ctx, sky_textures.sun.as_ref()),
+                sky_texture(ctx, sky_textures.moon.as_ref()),
+            ],
+        };
+
         Self {
             pipeline,
-            bindings,
-            index_count,
-            camera_position: options.camera_position,
-            camera_pitch: 0.0,
-            camera_yaw: 0.0,
-            vfov: options.vfov,
-            key_w: false,
-            key_a: false,
-            key_s: false,
-            key_d: false,
-            key_h: false,
-            key_j: false,
-            key_k: false,
-            key_l: false,
-            key_space: false,
-            key_shift: false,
-            key_ctrl: false,
+            sky_pipeline,
+            sky_bindings,
+            sky: Sky::new(world.dimension(), options.day_time(world)),
+            view_distance: options.view_distance(),
+            sections: mesh.sections,
+            section_bindings,
+            lods: mesh.lods,
+            lod_bindings,
+            highlight_pipeline,
+            highlight_bindings,
+            cave_culling: options.cave_culling,
+            lod_distance: options.lod_distance,
+            camera: options.camera,
+            camera_path: options.camera_path,
+            path_start: None,
+            key_bindings: options.key_bindings,
+            held: HashSet::new(),
+            speed: 1.0,
+            mouse_grabbed: false,
+            screenshot_scale: options.screenshot_scale,
+            orthographic_height: match options.camera.projection {
+                Projection::Orthographic { height } => height,
+                Projection::Perspective => ORTHOGRAPHIC_HEIGHT,
+            },
+            isometric_direction: 0,
+            isometric_pitch: 0,
         }
     }
 
-    fn rotation_matrix(&self) -> Mat3 {
-        Mat3::from_euler(
-            glam::EulerRot::ZYX,
-            0.0,
-            self.camera_yaw,
-            self.camera_pitch,
-        )
+    fn grab_mouse(&mut self, ctx: &mut Context, grab: bool) {
+        self.mouse_grabbed = grab;
+        ctx.set_cursor_grab(grab);
+        ctx.show_mouse(!grab);
     }
-}
 
-impl EventHandler for Renderer {
-    fn update(&mut self, _ctx: &mut miniquad::Context) {
-        if self.key_h {
-            self.camera_yaw += TURN_SPEED;
+    /// Widens the field of view, or zooms out of an orthographic view, by
+    /// a number of steps.
+    fn zoom_out(&mut self, steps: f32) {
+        match &mut self.camera.projection {
+            Projection::Perspective => {
+                self.camera.vfov =
+                    (self.camera.vfov + steps * 0.05).clamp(0.05, 3.0);
+            }
+            Projection::Orthographic { height } => {
+                *height = (*height * 1.25f32.powf(steps)).clamp(1.0, 10000.0);
+                self.orthographic_height = *height;
+            }
+        }
+    }
+
+    /// Looks diagonally down at the world with an orthographic projection.
+    fn isometric_view(&mut self) {
+        self.camera.yaw = ISOMETRIC_DIRECTIONS[self.isometric_direction].1;
+        self.camera.pitch =
+            ISOMETRIC_PITCHES[self.isometric_pitch].to_radians();
+        self.camera.projection = Projection::Orthographic {
+            height: self.orthographic_height,
+        };
+    }
+
+    /// Draws the sky and the world seen through `view` to the window, or to
+    /// `pass` if there is one.
+    fn draw_view(
+        &self,
+        ctx: &mut Context,
+        view: Mat4,
+        pass: Option<RenderPass>,
+    ) {
+        let sky = &self.sky;
+        let fog_color = sky.fog_color;
+        ctx.begin_pass(
+            pass,
+            PassAction::clear_color(fog_color.x, fog_color.y, fog_color.z, 1.0),
+        );
+        ctx.apply_pipeline(&self.sky_pipeline);
+        ctx.apply_bindings(&self.sky_bindings);
+        ctx.apply_uniforms(&SkyUniforms {
+            inverse_view: view.inverse(),
+            sky_color: sky.sky_color,
+            fog_color,
+            sun_direction: sky.sun_direction.unwrap_or(Vec3::Y),
+            show_sun: f32::from(u8::from(sky.sun_direction.is_some())),
+            moon_phase: f32::from(sky.moon_phase),
+        });
+        ctx.draw(0, 3, 1);
+
+        let [fog_start, fog_end] =
+            sky.fog_distances(&self.camera, self.view_distance);
+        let vs_params = Uniforms {
+            mvp: view,
+            camera_position: self.camera.position,
+            fog_color,
+            fog_start,
+            fog_end,
+        };
+        ctx.apply_pipeline(&self.pipeline);
+        ctx.apply_uniforms(&vs_params);
+        let (sections, lods) = culling::draw_list(
+            &self.sections,
+            &self.lods,
+            self.camera.position,
+            &Frustum::new(view),
+            // Cave culling finds what can be seen from the camera's position,
+            // but orthographic views see everything in front of them
+            self.cave_culling && !self.camera.is_orthographic(),
+            self.lod_distance,
+        );
+        let bindings = sections
+            .into_iter()
+            .map(|index| &self.section_bindings[index])
+            .chain(lods.into_iter().map(|index| &self.lod_bindings[index]));
+        for (bindings, index_count) in bindings.flatten() {
+            ctx.apply_bindings(bindings);
+            ctx.draw(0, *index_count, 1);
+        }
+
+        if let Some((bindings, index_count)) = &self.highlight_bindings {
+            ctx.apply_pipeline(&self.highlight_pipeline);
+            ctx.apply_bindings(bindings);
+            ctx.apply_uniforms(&HighlightUniforms { mvp: view });
+            ctx.draw(0, *index_count, 1);
         }
-        if self.key_l {
-            self.camera_yaw -= TURN_SPEED;
+        ctx.end_render_pass();
+    }
+
+    /// Renders the current view offscreen at `scale` times the size of the
+    /// window and saves it to the screenshots folder. Images too big for
+    /// the GPU are put together from several tiles.
+    fn screenshot(&self, ctx: &mut Context, scale: u32) {
+        let (width, height) = ctx.screen_size();
+        let size = UVec2::new(width as u32, height as u32) * scale;
+        let view = self.camera.view_proj(width / height, self.view_distance);
+
+        let mut pixels = vec![0; size.x as usize * size.y as usize * 4];
+        for tile_y in (0..size.y).step_by(MAX_TILE_SIZE as usize) {
+            for tile_x in (0..size.x).step_by(MAX_TILE_SIZE as usize) {
+                let tile_pos = UVec2::new(tile_x, tile_y);
+                let tile_size =
+                    (size - tile_pos).min(UVec2::splat(MAX_TILE_SIZE));
+                let params = |format| TextureParams {
+                    format,
+                    wrap: miniquad::TextureWrap::Clamp,
+                    filter: FilterMode::Nearest,
+                    width: tile_size.x,
+                    height: tile_size.y,
+                };
+                let color = Texture::new_render_texture(
+                    ctx,
+                    params(TextureFormat::RGBA8),
+                );
+                let depth = Texture::new_render_texture(
+                    ctx,
+                    params(TextureFormat::Depth),
+                );
+                let pass = RenderPass::new(ctx, color, depth);
+
+                // Image rows go down but normalized device coordinates go up
+                let min = (tile_pos.as_vec2() / size.as_vec2() * 2.0 - 1.0)
+                    * Vec2::new(1.0, -1.0);
+                let max = ((tile_pos + tile_size).as_vec2() / size.as_vec2()
+                    * 2.0
+                    - 1.0)
+                    * Vec2::new(1.0, -1.0);
+                self.draw_view(
+                    ctx,
+                    camera::crop(
+                        view,
+                        Vec2::new(min.x, max.y),
+                        Vec2::new(max.x, min.y),
+                    ),
+                    Some(pass),
+                );
+
+                let mut tile =
+                    vec![0; tile_size.x as usize * tile_size.y as usize * 4];
+                color.read_pixels(&mut tile);
+                let row_length = tile_size.x as usize * 4;
+                // Textures are read from the bottom row up
+                for (row, tile_row) in
+                    tile.chunks_exact_mut(row_length).rev().enumerate()
+                {
+                    for alpha in tile_row.iter_mut().skip(3).step_by(4) {
+                        *alpha = 255;
+                    }
+                    let start = ((tile_y as usize + row) * size.x as usize
+                        + tile_x as usize)
+                        * 4;
+                    pixels[start..][..row_length].copy_from_slice(tile_row);
+                }
+
+                pass.delete(ctx);
+                color.delete();
+                depth.delete();
+            }
         }
-        if self.key_j {
-            self.camera_pitch += TURN_SPEED;
+
+        let image = Image {
+            width: size.x,
+            height: size.y,
+            pixels: pixels.into(),
+        };
+        let path = screenshot::new_path();
+        match image.write_png(&path) {
+            Ok(()) => eprintln!("Saved screenshot to {}", path.display()),
+            Err(error) => eprintln!("Couldn't save screenshot: {error}"),
         }
-        if self.key_k {
-            self.camera_pitch -= TURN_SPEED;
+    }
+}
+
+impl EventHandler for Renderer {
+    fn update(&mut self, _ctx: &mut miniquad::Context) {
+        if let (Some(path), Some(start)) = (&self.camera_path, self.path_start)
+        {
+            let time = (miniquad::date::now() - start) as f32;
+            self.camera = Camera {
+                projection: self.camera.projection,
+                ..path.camera_at(time)
+            };
+            if time > path.duration() {
+                self.path_start = None;
+            }
+            return;
         }
-        self.camera_pitch = self.camera_pitch.clamp(
+
+        let held = |action| f32::from(u8::from(self.held.contains(&action)));
+        self.camera.yaw +=
+            (held(Action::TurnLeft) - held(Action::TurnRight)) * TURN_SPEED;
+        self.camera.pitch +=
+            (held(Action::LookDown) - held(Action::LookUp)) * TURN_SPEED;
+        self.camera.pitch = self.camera.pitch.clamp(
             -std::f32::consts::FRAC_PI_2 + 1e-5,
             std::f32::consts::FRAC_PI_2 - 1e-5,
         );
 
-        if self.key_space {
-            self.camera_position.y +=
-                if self.key_ctrl { RUN_SPEED } else { FLY_SPEED };
-        }
-        if self.key_shift {
-            self.camera_position.y -=
-                if self.key_ctrl { RUN_SPEED } else { FLY_SPEED };
-        }
-
-        let f32_from_bool = |b| if b { 1.0 } else { 0.0 };
-        self.camera_position += Mat3::from_rotation_y(self.camera_yaw)
+        let sprinting = self.held.contains(&Action::Sprint);
+        self.camera.position.y += (held(Action::Up) - held(Action::Down))
+            * if sprinting { RUN_SPEED } else { FLY_SPEED }
+            * self.speed;
+        self.camera.position += Mat3::from_rotation_y(self.camera.yaw)
             * Vec3 {
-                x: f32_from_bool(self.key_a) - f32_from_bool(self.key_d),
+                x: held(Action::Left) - held(Action::Right),
                 y: 0.0,
-                z: f32_from_bool(self.key_w) - f32_from_bool(self.key_s),
+                z: held(Action::Forward) - held(Action::Back),
             }
-            * if self.key_ctrl { RUN_SPEED } else { MOVE_SPEED };
+            * if sprinting { RUN_SPEED } else { MOVE_SPEED }
+            * self.speed;
     }
 
     fn key_down_event(
         &mut self,
-        _ctx: &mut Context,
+        ctx: &mut Context,
         keycode: KeyCode,
         _keymods: miniquad::KeyMods,
-        _repeat: bool,
+        repeat: bool,
     ) {
-        match keycode {
-            KeyCode::W => self.key_w = true,
-            KeyCode::A => self.key_a = true,
-            KeyCode::S => self.key_s = true,
-            KeyCode::D => self.key_d = true,
-            KeyCode::H => self.key_h = true,
-            KeyCode::J => self.key_j = true,
-            KeyCode::K => self.key_k = true,
-            KeyCode::L => self.key_l = true,
-            KeyCode::Space => self.key_space = true,
-            KeyCode::LeftShift => self.key_shift = true,
-            KeyCode::LeftControl => self.key_ctrl = true,
-            KeyCode::O => self.vfov += 0.05,
-            KeyCode::I => self.vfov -= 0.05,
-            _ => {}
+        let Some(action) = self.key_bindings.action(keycode) else {
+            return;
+        };
+        // Only zooming keeps going while its key is held, everything else
+        // happens once per press
+        if repeat && !matches!(action, Action::WidenFov | Action::NarrowFov) {
+            return;
+        }
+        match action {
+            Action::WidenFov => self.zoom_out(1.0),
+            Action::NarrowFov => self.zoom_out(-1.0),
+            Action::ToggleProjection => {
+                self.camera.projection = match self.camera.projection {
+                    Projection::Perspective => Projection::Orthographic {
+                        height: self.orthographic_height,
+                    },
+                    Projection::Orthographic { .. } => Projection::Perspective,
+                };
+            }
+            Action::NextIsometricDirection => {
+                if self.camera.is_orthographic() {
+                    self.isometric_direction =
+                        (self.isometric_direction + 1) % 4;
+                }
+                self.isometric_view();
+            }
+            Action::NextIsometricPitch => {
+                if self.camera.is_orthographic() {
+                    self.isometric_pitch = (self.isometric_pitch + 1) % 2;
+                }
+                self.isometric_view();
+            }
+            Action::ReleaseMouse => self.grab_mouse(ctx, false),
+            Action::Screenshot => self.screenshot(ctx, 1),
+            Action::HiResScreenshot => {
+                self.screenshot(ctx, self.screenshot_scale);
+            }
+            Action::PlayPath => {
+                self.path_start = match self.path_start {
+                    None if self.camera_path.is_some() => {
+                        Some(miniquad::date::now())
+                    }
+                    _ => None,
+                };
+            }
+            _ => {
+                self.held.insert(action);
+            }
         }
     }
 
@@ -205,92 +552,95 @@ impl EventHandler for Renderer {
         keycode: KeyCode,
         _keymods: miniquad::KeyMods,
     ) {
-        match keycode {
-            KeyCode::W => self.key_w = false,
-            KeyCode::A => self.key_a = false,
-            KeyCode::S => self.key_s = false,
-            KeyCode::D => self.key_d = false,
-            KeyCode::H => self.key_h = false,
-            KeyCode::J => self.key_j = false,
-            KeyCode::K => self.key_k = false,
-            KeyCode::L => self.key_l = false,
-            KeyCode::Space => self.key_space = false,
-            KeyCode::LeftShift => self.key_shift = false,
-            KeyCode::LeftControl => self.key_ctrl = false,
-            _ => {}
+        if let Some(action) = self.key_bindings.action(keycode) {
+            self.held.remove(&action);
         }
     }
 
-    fn draw(&mut self, ctx: &mut miniquad::Context) {
-        let (width, height) = ctx.screen_size();
-        let proj =
-            Mat4::perspective_rh_gl(self.vfov, width / height, 0.01, f32::MAX);
-        let view = Mat4::look_to_rh(
-            self.camera_position,
-            self.rotation_matrix() * Vec3::Z,
-            Vec3::Y,
-        );
-        let view = proj * view;
+    fn mouse_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        _button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        self.grab_mouse(ctx, true);
+    }
 
-        let vs_params = Uniforms { mvp: view };
+    fn raw_mouse_motion(&mut self, _ctx: &mut Context, dx: f32, dy: f32) {
+        if self.mouse_grabbed {
+            self.camera.yaw -= dx * MOUSE_SENSITIVITY;
+            self.camera.pitch = (self.camera.pitch + dy * MOUSE_SENSITIVITY)
+                .clamp(
+                    -std::f32::consts::FRAC_PI_2 + 1e-5,
+                    std::f32::consts::FRAC_PI_2 - 1e-5,
+                );
+        }
+    }
 
-        ctx.begin_default_pass(PassAction::clear_color(0.5, 0.7, 1.0, 1.0));
-        ctx.apply_pipeline(&self.pipeline);
-        ctx.apply_bindings(&self.bindings);
-        ctx.apply_uniforms(&vs_params);
-        ctx.draw(0, self.index_count, 1);
-        ctx.end_render_pass();
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
+        let steps = y.signum();
+        if self.held.contains(&Action::ScrollFov) {
+            self.zoom_out(-steps);
+        } else {
+            self.speed = (self.speed * 1.25f32.powf(steps)).clamp(0.01, 100.0);
+        }
+    }
+
+    fn draw(&mut self, ctx: &mut miniquad::Context) {
+        let (width, height) = ctx.screen_size();
+        let view = self.camera.view_proj(width / height, self.view_distance);
+        self.draw_view(ctx, view, None);
         ctx.commit_frame();
     }
 }
 
-fn read_block_texture(path: &Path) -> Box<[u8]> {
-    let Ok(file) = File::open(path) else {
-        eprintln!("Missing block texture: {}", path.display());
-        return Box::new([255; 16 * 16 * 4]);
-    };
-    let raster = png_pong::Decoder::new(file)
-        .unwrap()
-        .into_steps()
-        .next()
-        .unwrap()
-        .unwrap()
-        .raster;
-    match raster {
-        png_pong::PngRaster::Rgba8(raster) => {
-            assert_eq!(raster.width(), 16);
-            if raster.height() == 16 {
-                raster.into()
-            } else {
-                eprintln!(
-                    "Texture {} has wrong height ({})",
-                    path.display(),
-                    raster.height()
-                );
-                <Box<[u8]>>::from(raster)
-                    .iter()
-                    .copied()
-                    .take(16 * 16 * 4)
-                    .collect()
-            }
-        }
-        png_pong::PngRaster::Rgb8(raster) => {
-            assert_eq!(raster.width(), 16);
-            if raster.height() != 16 {
-                eprintln!(
-                    "Texture {} has wrong height ({})",
-                    path.display(),
-                    raster.height()
-                );
-            }
-            <Box<[u8]>>::from(raster)
-                .iter()
-                .copied()
-                .tuples()
-                .flat_map(|(r, g, b)| [r, g, b, 255])
-                .take(16 * 16 * 4)
-                .collect()
-        }
-        _ => todo!(),
+/// Uploads part of a mesh to its own buffers, returning them with the
+/// number of indices, or `None` if it is empty.
+fn buffers(
+    ctx: &mut Context,
+    mesh: &Mesh,
+    vertices: Range<usize>,
+    indices: Range<usize>,
+    texture: Texture,
+) -> Option<(Bindings, i32)> {
+    if indices.is_empty() {
+        return None;
     }
+    let vertex_start = vertices.start as u32;
+    let vertices: &[Vertex] = &mesh.vertices[vertices];
+    let vertex_buffer =
+        Buffer::immutable(ctx, BufferType::VertexBuffer, vertices);
+    let indices: Vec<u32> = mesh.indices[indices]
+        .iter()
+        .map(|index| index - vertex_start)
+        .collect();
+    let index_buffer =
+        Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
+    let bindings = Bindings {
+        vertex_buffers: vec![vertex_buffer],
+        index_buffer,
+        images: vec![texture],
+    };
+    Some((bindings, indices.len() as i32))
+}
+
+/// Uploads a texture for the sky, which is black if it is missing so that
+/// adding it to the sky does nothing.
+fn sky_texture(ctx: &mut Context, image: Option<&Image>) -> Texture {
+    let (width, height, pixels) = image
+        .map_or((1, 1, &[0, 0, 0, 255][..]), |image| {
+            (image.width, image.height, &image.pixels)
+        });
+    Texture::from_data_and_format(
+        ctx,
+        pixels,
+        TextureParams {
+            format: TextureFormat::RGBA8,
+            wrap: miniquad::TextureWrap::Clamp,
+            filter: FilterMode::Nearest,
+            width,
+            height,
+        },
+    )
 }
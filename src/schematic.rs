@@ -0,0 +1,508 @@
+mod legacy;
+
+use crate::{
+    block_source::{BlockSource, Volume},
+    chunk::{Block, BlockEntity, AIR},
+};
+use glam::IVec3;
+use internment::Intern;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{collections::HashMap, io::Cursor, path::Path};
+
+/// The data version assumed for MCEdit schematics, which is Minecraft
+/// 1.12.2, the last version with numeric block IDs.
+const MCEDIT_DATA_VERSION: i32 = 1343;
+/// The data version assumed for files that don't say, which is Minecraft
+/// 1.13, the first version with block states.
+const DEFAULT_DATA_VERSION: i32 = 1519;
+
+/// A box of blocks saved on its own rather than as part of a world, from a
+/// Sponge `.schem`, an MCEdit `.schematic` or a structure block `.nbt`
+/// file.
+pub struct Schematic {
+    /// The version of Minecraft that saved the schematic.
+    pub data_version: i32,
+    /// The blocks, with the schematic's first corner at the origin.
+    pub volume: Volume,
+}
+
+impl Schematic {
+    /// Whether a path is a file that [`Schematic::load`] can read, rather
+    /// than a world folder.
+    pub fn is_schematic(path: &Path) -> bool {
+        matches!(
+            path.extension().and_then(|extension| extension.to_str()),
+            Some("schem" | "schematic" | "nbt")
+        )
+    }
+
+    /// Reads a schematic file, panicking if it isn't valid.
+    pub fn load(path: &Path) -> Self {
+        let file = std::fs::read(path)
+            .unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("schem") => Self::sponge(&file),
+            Some("schematic") => Self::mcedit(&file),
+            Some("nbt") => Self::structure(&file),
+            _ => panic!("{}: not a schematic", path.display()),
+        }
+    }
+
+    /// Any version of the Sponge schematic format. Versions 1 and 2 store
+    /// everything at the root, and version 3 moves it into a `Schematic`
+    /// compound with the blocks in their own compound.
+    fn sponge(file: &[u8]) -> Self {
+        #[derive(Deserialize)]
+        struct Root {
+            #[serde(rename = "Schematic")]
+            schematic: Sponge,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Sponge {
+            data_version: Option<i32>,
+            width: i16,
+            height: i16,
+            length: i16,
+            // Versions 1 and 2
+            palette: Option<HashMap<String, i32>>,
+            block_data: Option<Vec<i8>>,
+            #[serde(alias = "TileEntities")]
+            block_entities: Option<Vec<SpongeBlockEntity>>,
+            // Version 3
+            blocks: Option<SpongeBlocks>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct SpongeBlocks {
+            palette: HashMap<String, i32>,
+            data: Vec<i8>,
+            #[serde(default)]
+            block_entities: Vec<SpongeBlockEntity>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct SpongeBlockEntity {
+            pos: [i32; 3],
+            id: String,
+            /// Only in version 3, which doesn't mix it with the position.
+            data: Option<HashMap<String, nbt::Value>>,
+            #[serde(flatten)]
+            other: HashMap<String, nbt::Value>,
+        }
+
+        let sponge = read_gzip::<Root>(file)
+            .map(|root| root.schematic)
+            .or_else(|_| read_gzip::<Sponge>(file))
+            .unwrap_or_else(|error| {
+                panic!("invalid Sponge schematic: {error}")
+            });
+        let (palette, data, block_entities) = match sponge.blocks {
+            Some(blocks) => {
+                (blocks.palette, blocks.data, blocks.block_entities)
+            }
+            None => (
+                sponge.palette.unwrap(),
+                sponge.block_data.unwrap(),
+                sponge.block_entities.unwrap_or_default(),
+            ),
+        };
+
+        let mut blocks_by_index = vec![*AIR; palette.len()];
+        for (state, index) in palette {
+            let index = usize::try_from(index).unwrap();
+            if index >= blocks_by_index.len() {
+                blocks_by_index.resize(index + 1, *AIR);
+            }
+            blocks_by_index[index] = Block::parse(&state);
+        }
+        // Palette indices are variable length integers, 7 bits at a time
+        // starting with the lowest
+        let mut blocks = Vec::new();
+        let mut bytes = data.into_iter().map(|byte| byte as u8);
+        while let Some(mut byte) = bytes.next() {
+            let mut index = 0;
+            let mut shift = 0;
+            while byte & 0x80 != 0 {
+                index |= usize::from(byte & 0x7f) << shift;
+                shift += 7;
+                byte = bytes.next().unwrap();
+            }
+            index |= usize::from(byte) << shift;
+            blocks.push(blocks_by_index.get(index).copied().unwrap_or(*AIR));
+        }
+
+        let block_entities = block_entities
+            .into_iter()
+            .map(|block_entity| {
+                let mut data = block_entity.data.unwrap_or(block_entity.other);
+                data.insert(
+                    "id".to_owned(),
+                    nbt::Value::String(block_entity.id),
+                );
+                BlockEntity::new(IVec3::from_array(block_entity.pos), data)
+            })
+            .collect();
+
+        Self::new(
+            IVec3::new(
+                i32::from(sponge.width as u16),
+                i32::from(sponge.height as u16),
+                i32::from(sponge.length as u16),
+            ),
+            sponge.data_version.unwrap_or(DEFAULT_DATA_VERSION),
+            blocks,
+            block_entities,
+        )
+    }
+
+    /// The format of MCEdit and of WorldEdit before 1.13, which stores
+    /// numeric block IDs. IDs from mods, which need the `AddBlocks` array,
+    /// are left out.
+    fn mcedit(file: &[u8]) -> Self {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct McEdit {
+            width: i16,
+            height: i16,
+            length: i16,
+            blocks: Vec<i8>,
+            data: Vec<i8>,
+            #[serde(default)]
+            tile_entities: Vec<BlockEntity>,
+        }
+
+        let schematic: McEdit = read_gzip(file).unwrap_or_else(|error| {
+            panic!("invalid MCEdit schematic: {error}")
+        });
+        let blocks: Vec<_> = schematic
+            .blocks
+            .iter()
+            .zip(&schematic.data)
+            .map(|(&id, &data)| legacy::block(id as u8, data as u8 & 15))
+            .collect();
+        Self::new(
+            IVec3::new(
+                i32::from(schematic.width as u16),
+                i32::from(schematic.height as u16),
+                i32::from(schematic.length as u16),
+            ),
+            MCEDIT_DATA_VERSION,
+            blocks,
+            schematic.tile_entities,
+        )
+    }
+
+    /// The format of structure blocks, which lists every block that isn't
+    /// a structure void. Only the first palette is used for structures with
+    /// several, such as shipwrecks.
+    fn structure(file: &[u8]) -> Self {
+        #[derive(Deserialize)]
+        struct Structure {
+            #[serde(rename = "DataVersion")]
+            data_version: Option<i32>,
+            size: [i32; 3],
+            palette: Option<Vec<Intern<Block>>>,
+            palettes: Option<Vec<Vec<Intern<Block>>>>,
+            blocks: Vec<StructureBlock>,
+        }
+
+        #[derive(Deserialize)]
+        struct StructureBlock {
+            pos: [i32; 3],
+            state: i32,
+            nbt: Option<HashMap<String, nbt::Value>>,
+        }
+
+        let structure: Structure = read_gzip(file)
+            .unwrap_or_else(|error| panic!("invalid structure file: {error}"));
+        let palette = structure
+            .palette
+            .or_else(|| structure.palettes?.into_iter().next())
+            .unwrap_or_default();
+        let mut volume = Volume::new(IVec3::from_array(structure.size));
+        for block in structure.blocks {
+            let pos = IVec3::from_array(block.pos);
+            volume.set_block(pos, palette[block.state as usize]);
+            if let Some(data) = block.nbt {
+                volume
+                    .block_entities
+                    .insert(pos, BlockEntity::new(pos, data));
+            }
+        }
+        Self {
+            data_version: structure
+                .data_version
+                .unwrap_or(DEFAULT_DATA_VERSION),
+            volume,
+        }
+    }
+
+    /// Fills a volume with blocks in the order Y, Z, X from the slowest
+    /// to the fastest changing, leaving air after the last one.
+    fn new(
+        size: IVec3,
+        data_version: i32,
+        blocks: impl IntoIterator<Item = Intern<Block>>,
+        block_entities: Vec<BlockEntity>,
+    ) -> Self {
+        let mut volume = Volume::new(size);
+        let positions = (0..size.y).flat_map(|y| {
+            (0..size.z).flat_map(move |z| {
+                (0..size.x).map(move |x| IVec3::new(x, y, z))
+            })
+        });
+        for (pos, block) in positions.zip(blocks) {
+            volume.set_block(pos, block);
+        }
+        volume.block_entities = block_entities
+            .into_iter()
+            .map(|block_entity| (block_entity.pos(), block_entity))
+            .collect();
+        Self {
+            data_version,
+            volume,
+        }
+    }
+}
+
+impl BlockSource for Schematic {
+    fn block_at(&self, pos: IVec3) -> Option<Intern<Block>> {
+        self.volume.block_at(pos)
+    }
+
+    fn block_entity_at(&self, pos: IVec3) -> Option<&BlockEntity> {
+        self.volume.block_entity_at(pos)
+    }
+
+    fn data_version(&self) -> Option<i32> {
+        Some(self.data_version)
+    }
+}
+
+fn read_gzip<T: DeserializeOwned>(file: &[u8]) -> nbt::Result<T> {
+    nbt::from_gzip_reader(Cursor::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nbt::{Blob, Value};
+
+    fn gzip(fields: Vec<(&str, Value)>) -> Vec<u8> {
+        let mut blob = Blob::new();
+        for (name, value) in fields {
+            blob.insert(name, value).unwrap();
+        }
+        let mut file = Vec::new();
+        blob.to_gzip_writer(&mut file).unwrap();
+        file
+    }
+
+    fn compound(fields: Vec<(&str, Value)>) -> Value {
+        Value::Compound(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.to_owned(), value))
+                .collect(),
+        )
+    }
+
+    fn ints(values: [i32; 3]) -> Value {
+        Value::List(values.into_iter().map(Value::Int).collect())
+    }
+
+    /// A 3x1x1 Sponge schematic of stone, a sign and an unknown palette
+    /// index, with the sign's block entity in `block_entities`.
+    fn sponge(version: i32, block_entities: &str) -> Schematic {
+        let mut fields = vec![
+            ("Version", Value::Int(version)),
+            ("Width", Value::Short(3)),
+            ("Height", Value::Short(1)),
+            ("Length", Value::Short(1)),
+            (
+                "Palette",
+                compound(vec![
+                    ("minecraft:stone", Value::Int(0)),
+                    ("oak_sign[rotation=4]", Value::Int(200)),
+                ]),
+            ),
+            // 200 takes two bytes, the lowest 7 bits first
+            ("BlockData", Value::ByteArray(vec![0, -56, 1, 5])),
+            (
+                block_entities,
+                Value::List(vec![compound(vec![
+                    ("Pos", Value::IntArray(vec![1, 0, 0])),
+                    ("Id", Value::String("minecraft:sign".to_owned())),
+                    ("Text1", Value::String("hello".to_owned())),
+                ])]),
+            ),
+        ];
+        if version >= 2 {
+            fields.push(("DataVersion", Value::Int(2586)));
+        }
+        Schematic::sponge(&gzip(fields))
+    }
+
+    #[test]
+    fn reads_sponge_schematics() {
+        for (version, block_entities, data_version) in [
+            (1, "TileEntities", DEFAULT_DATA_VERSION),
+            (2, "BlockEntities", 2586),
+        ] {
+            let schematic = sponge(version, block_entities);
+            assert_eq!(schematic.data_version, data_version);
+            assert_eq!(
+                schematic.block_at(IVec3::ZERO),
+                Some(Block::parse("stone"))
+            );
+            assert_eq!(
+                schematic.block_at(IVec3::X),
+                Some(Block::parse("oak_sign[rotation=4]"))
+            );
+            assert_eq!(schematic.block_at(IVec3::new(2, 0, 0)), Some(*AIR));
+            assert_eq!(schematic.block_at(IVec3::new(3, 0, 0)), None);
+
+            let sign = schematic.block_entity_at(IVec3::X).unwrap();
+            assert_eq!(sign.pos(), IVec3::X);
+            assert_eq!(
+                sign.data["id"],
+                Value::String("minecraft:sign".to_owned())
+            );
+            assert_eq!(sign.data["Text1"], Value::String("hello".to_owned()));
+        }
+    }
+
+    #[test]
+    fn reads_mcedit_schematics() {
+        // Blocks go X first, then Z, then Y
+        let schematic = Schematic::mcedit(&gzip(vec![
+            ("Width", Value::Short(2)),
+            ("Height", Value::Short(2)),
+            ("Length", Value::Short(1)),
+            ("Materials", Value::String("Alpha".to_owned())),
+            ("Blocks", Value::ByteArray(vec![1, 54, 35, 0])),
+            ("Data", Value::ByteArray(vec![1, 2, 14, 0])),
+            (
+                "TileEntities",
+                Value::List(vec![compound(vec![
+                    ("id", Value::String("minecraft:chest".to_owned())),
+                    ("x", Value::Int(1)),
+                    ("y", Value::Int(0)),
+                    ("z", Value::Int(0)),
+                ])]),
+            ),
+        ]));
+        assert_eq!(schematic.data_version, MCEDIT_DATA_VERSION);
+        assert_eq!(
+            schematic.block_at(IVec3::ZERO),
+            Some(Block::parse("granite"))
+        );
+        assert_eq!(schematic.block_at(IVec3::X), Some(Block::parse("chest")));
+        assert_eq!(
+            schematic.block_at(IVec3::Y),
+            Some(Block::parse("red_wool"))
+        );
+        assert_eq!(schematic.block_at(IVec3::new(1, 1, 0)), Some(*AIR));
+        assert_eq!(
+            schematic.block_entity_at(IVec3::X).unwrap().pos(),
+            IVec3::X
+        );
+    }
+
+    #[test]
+    fn converts_legacy_block_ids() {
+        for (id, data, block) in [
+            (0, 0, "air"),
+            (1, 0, "stone"),
+            (1, 5, "andesite"),
+            (1, 15, "stone"),
+            (5, 4, "acacia_planks"),
+            (35, 11, "blue_wool"),
+            (9, 3, "water[level=3]"),
+            (175, 9, "lilac[half=upper]"),
+            (17, 0, "oak_log[axis=y]"),
+            (17, 6, "birch_log[axis=x]"),
+            (162, 9, "dark_oak_log[axis=z]"),
+            (161, 0, "acacia_leaves"),
+            (44, 3, "cobblestone_slab[type=bottom]"),
+            (44, 11, "cobblestone_slab[type=top]"),
+            (43, 5, "stone_brick_slab[type=double]"),
+            (126, 10, "birch_slab[type=top]"),
+            (125, 5, "dark_oak_slab[type=double]"),
+            (182, 8, "red_sandstone_slab[type=top]"),
+            (204, 0, "purpur_slab[type=double]"),
+        ] {
+            assert_eq!(
+                legacy::block(id, data),
+                Block::parse(block),
+                "{id}:{data}"
+            );
+        }
+    }
+
+    #[test]
+    fn reads_structure_files() {
+        let block = |name: &str| {
+            compound(vec![("Name", Value::String(name.to_owned()))])
+        };
+        // Shipwrecks and other structures with several palettes only have
+        // `palettes`, and the first one is used
+        let structure = Schematic::structure(&gzip(vec![
+            ("size", ints([2, 1, 2])),
+            (
+                "palettes",
+                Value::List(vec![
+                    Value::List(vec![
+                        block("minecraft:oak_planks"),
+                        block("minecraft:chest"),
+                    ]),
+                    Value::List(vec![
+                        block("minecraft:spruce_planks"),
+                        block("minecraft:barrel"),
+                    ]),
+                ]),
+            ),
+            (
+                "blocks",
+                Value::List(vec![
+                    compound(vec![
+                        ("pos", ints([0, 0, 0])),
+                        ("state", Value::Int(0)),
+                    ]),
+                    compound(vec![
+                        ("pos", ints([1, 0, 1])),
+                        ("state", Value::Int(1)),
+                        (
+                            "nbt",
+                            compound(vec![(
+                                "id",
+                                Value::String("minecraft:chest".to_owned()),
+                            )]),
+                        ),
+                    ]),
+                ]),
+            ),
+        ]));
+        assert_eq!(structure.data_version, DEFAULT_DATA_VERSION);
+        assert_eq!(
+            structure.block_at(IVec3::ZERO),
+            Some(Block::parse("oak_planks"))
+        );
+        assert_eq!(
+            structure.block_at(IVec3::new(1, 0, 1)),
+            Some(Block::parse("chest"))
+        );
+        // Structure voids aren't listed and stay air
+        assert_eq!(structure.block_at(IVec3::X), Some(*AIR));
+        let chest = structure.block_entity_at(IVec3::new(1, 0, 1)).unwrap();
+        assert_eq!(chest.pos(), IVec3::new(1, 0, 1));
+        assert_eq!(
+            chest.data["id"],
+            Value::String("minecraft:chest".to_owned())
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! The numeric block IDs used before Minecraft 1.13 (the "flattening").
+
+use crate::chunk::Block;
+use internment::Intern;
+use std::collections::BTreeMap;
+
+/// The block for each ID, without the namespace. Variants that depend on
+/// the data value are picked in [`block`].
+#[rustfmt::skip]
+const BLOCKS: [&str; 256] = [
+    "air", "stone", "grass_block", "dirt", "cobblestone", "oak_planks",
+    "oak_sapling", "bedrock", "water", "water", "lava", "lava", "sand",
+    "gravel", "gold_ore", "iron_ore", "coal_ore", "oak_log", "oak_leaves",
+    "sponge", "glass", "lapis_ore", "lapis_block", "dispenser", "sandstone",
+    "note_block", "red_bed", "powered_rail", "detector_rail",
+    "sticky_piston", "cobweb", "grass", "dead_bush", "piston",
+    "piston_head", "white_wool", "moving_piston", "dandelion", "poppy",
+    "brown_mushroom", "red_mushroom", "gold_block", "iron_block",
+    "smooth_stone_slab", "smooth_stone_slab", "bricks", "tnt", "bookshelf",
+    "mossy_cobblestone", "obsidian", "torch", "fire", "spawner",
+    "oak_stairs", "chest", "redstone_wire", "diamond_ore", "diamond_block",
+    "crafting_table", "wheat", "farmland", "furnace", "furnace", "oak_sign",
+    "oak_door", "ladder", "rail", "cobblestone_stairs", "oak_wall_sign",
+    "lever", "stone_pressure_plate", "iron_door", "oak_pressure_plate",
+    "redstone_ore", "redstone_ore", "redstone_torch", "redstone_torch",
+    "stone_button", "snow", "ice", "snow_block", "cactus", "clay",
+    "sugar_cane", "jukebox", "oak_fence", "carved_pumpkin", "netherrack",
+    "soul_sand", "glowstone", "nether_portal", "jack_o_lantern", "cake",
+    "repeater", "repeater", "white_stained_glass", "oak_trapdoor",
+    "infested_stone", "stone_bricks", "brown_mushroom_block",
+    "red_mushroom_block", "iron_bars", "glass_pane", "melon",
+    "pumpkin_stem", "melon_stem", "vine", "oak_fence_gate", "brick_stairs",
+    "stone_brick_stairs", "mycelium", "lily_pad", "nether_bricks",
+    "nether_brick_fence", "nether_brick_stairs", "nether_wart",
+    "enchanting_table", "brewing_stand", "cauldron", "end_portal",
+    "end_portal_frame", "end_stone", "dragon_egg", "redstone_lamp",
+    "redstone_lamp", "oak_slab", "oak_slab", "cocoa", "sandstone_stairs",
+    "emerald_ore", "ender_chest", "tripwire_hook", "tripwire",
+    "emerald_block", "spruce_stairs", "birch_stairs", "jungle_stairs",
+    "command_block", "beacon", "cobblestone_wall", "flower_pot", "carrots",
+    "potatoes", "oak_button", "skeleton_skull", "anvil", "trapped_chest",
+    "light_weighted_pressure_plate", "heavy_weighted_pressure_plate",
+    "comparator", "comparator", "daylight_detector", "redstone_block",
+    "nether_quartz_ore", "hopper", "quartz_block", "quartz_stairs",
+    "activator_rail", "dropper", "white_terracotta",
+    "white_stained_glass_pane", "acacia_leaves", "acacia_log",
+    "acacia_stairs", "dark_oak_stairs", "slime_block", "barrier",
+    "iron_trapdoor", "prismarine", "sea_lantern", "hay_block",
+    "white_carpet", "terracotta", "coal_block", "packed_ice", "sunflower",
+    "white_banner", "white_wall_banner", "daylight_detector",
+    "red_sandstone", "red_sandstone_stairs", "red_sandstone_slab",
+    "red_sandstone_slab", "spruce_fence_gate", "birch_fence_gate",
+    "jungle_fence_gate", "dark_oak_fence_gate", "acacia_fence_gate",
+    "spruce_fence", "birch_fence", "jungle_fence", "dark_oak_fence",
+    "acacia_fence", "spruce_door", "birch_door", "jungle_door",
+    "acacia_door", "dark_oak_door", "end_rod", "chorus_plant",
+    "chorus_flower", "purpur_block", "purpur_pillar", "purpur_stairs",
+    "purpur_slab", "purpur_slab", "end_stone_bricks", "beetroots",
+    "dirt_path", "end_gateway", "repeating_command_block",
+    "chain_command_block", "frosted_ice", "magma_block",
+    "nether_wart_block", "red_nether_bricks", "bone_block",
+    "structure_void", "observer", "white_shulker_box",
+    "orange_shulker_box", "magenta_shulker_box", "light_blue_shulker_box",
+    "yellow_shulker_box", "lime_shulker_box", "pink_shulker_box",
+    "gray_shulker_box", "light_gray_shulker_box", "cyan_shulker_box",
+    "purple_shulker_box", "blue_shulker_box", "brown_shulker_box",
+    "green_shulker_box", "red_shulker_box", "black_shulker_box",
+    "white_glazed_terracotta", "orange_glazed_terracotta",
+    "magenta_glazed_terracotta", "light_blue_glazed_terracotta",
+    "yellow_glazed_terracotta", "lime_glazed_terracotta",
+    "pink_glazed_terracotta", "gray_glazed_terracotta",
+    "light_gray_glazed_terracotta", "cyan_glazed_terracotta",
+    "purple_glazed_terracotta", "blue_glazed_terracotta",
+    "brown_glazed_terracotta", "green_glazed_terracotta",
+    "red_glazed_terracotta", "black_glazed_terracotta", "white_concrete",
+    "white_concrete_powder", "air", "air", "structure_block",
+];
+
+const COLORS: [&str; 16] = [
+    "white",
+    "orange",
+    "magenta",
+    "light_blue",
+    "yellow",
+    "lime",
+    "pink",
+    "gray",
+    "light_gray",
+    "cyan",
+    "purple",
+    "blue",
+    "brown",
+    "green",
+    "red",
+    "black",
+];
+
+const WOODS: [&str; 6] =
+    ["oak", "spruce", "birch", "jungle", "acacia", "dark_oak"];
+
+const STONE_SLABS: [&str; 8] = [
+    "smooth_stone",
+    "sandstone",
+    "petrified_oak",
+    "cobblestone",
+    "brick",
+    "stone_brick",
+    "nether_brick",
+    "quartz",
+];
+
+/// Converts a block ID and data value to a block. Only the kind of block is
+/// kept, so for example stairs and doors face their default direction.
+pub fn block(id: u8, data: u8) -> Intern<Block> {
+    let name = BLOCKS[usize::from(id)];
+    let variant = |names: &[&'static str]| -> &'static str {
+        names.get(usize::from(data)).copied().unwrap_or(names[0])
+    };
+    let mut properties = BTreeMap::new();
+    let name = match id {
+        1 => variant(&[
+            "stone",
+            "granite",
+            "polished_granite",
+            "diorite",
+            "polished_diorite",
+            "andesite",
+            "polished_andesite",
+        ])
+        .to_owned(),
+        3 => variant(&["dirt", "coarse_dirt", "podzol"]).to_owned(),
+        12 => variant(&["sand", "red_sand"]).to_owned(),
+        19 => variant(&["sponge", "wet_sponge"]).to_owned(),
+        24 => variant(&["sandstone", "chiseled_sandstone", "cut_sandstone"])
+            .to_owned(),
+        179 => variant(&[
+            "red_sandstone",
+            "chiseled_red_sandstone",
+            "cut_red_sandstone",
+        ])
+        .to_owned(),
+        31 => variant(&["dead_bush", "grass", "fern"]).to_owned(),
+        38 => variant(&[
+            "poppy",
+            "blue_orchid",
+            "allium",
+            "azure_bluet",
+            "red_tulip",
+            "orange_tulip",
+            "white_tulip",
+            "pink_tulip",
+            "oxeye_daisy",
+        ])
+        .to_owned(),
+        98 => variant(&[
+            "stone_bricks",
+            "mossy_stone_bricks",
+            "cracked_stone_bricks",
+            "chiseled_stone_bricks",
+        ])
+        .to_owned(),
+        155 => {
+            variant(&["quartz_block", "chiseled_quartz_block", "quartz_pillar"])
+                .to_owned()
+        }
+        168 => variant(&["prismarine", "prismarine_bricks", "dark_prismarine"])
+            .to_owned(),
+        175 => {
+            if data & 8 != 0 {
+                properties.insert("half".to_owned(), "upper".to_owned());
+            }
+            [
+                "sunflower",
+                "lilac",
+                "tall_grass",
+                "large_fern",
+                "rose_bush",
+                "peony",
+            ]
+            .get(usize::from(data & 7))
+            .copied()
+            .unwrap_or(name)
+            .to_owned()
+        }
+        5 => format!("{}_planks", WOODS[usize::from(data % 6)]),
+        6 => format!("{}_sapling", WOODS[usize::from(data & 7) % 6]),
+        17 | 162 | 18 | 161 => {
+            let wood =
+                WOODS[usize::from(data & 3) + if id >= 161 { 4 } else { 0 }];
+            if id == 17 || id == 162 {
+                let axis = ["y", "x", "z", "y"][usize::from(data >> 2 & 3)];
+                properties.insert("axis".to_owned(), axis.to_owned());
+                format!("{wood}_log")
+            } else {
+                format!("{wood}_leaves")
+            }
+        }
+        43 | 44 | 125 | 126 => {
+            let slab_type = if id == 43 || id == 125 {
+                "double"
+            } else if data & 8 != 0 {
+                "top"
+            } else {
+                "bottom"
+            };
+            properties.insert("type".to_owned(), slab_type.to_owned());
+            if id == 43 || id == 44 {
+                format!("{}_slab", STONE_SLABS[usize::from(data & 7)])
+            } else {
+                format!("{}_slab", WOODS[usize::from(data & 7) % 6])
+            }
+        }
+        181 | 182 | 204 | 205 => {
+            let slab_type = match id {
+                181 | 204 => "double",
+                _ if data & 8 != 0 => "top",
+                _ => "bottom",
+            };
+            properties.insert("type".to_owned(), slab_type.to_owned());
+            name.to_owned()
+        }
+        35 | 95 | 159 | 160 | 171 | 251 | 252 => {
+            name.replacen("white", COLORS[usize::from(data & 15)], 1)
+        }
+        8..=11 => {
+            properties.insert("level".to_owned(), (data & 15).to_string());
+            name.to_owned()
+        }
+        _ => name.to_owned(),
+    };
+    Intern::new(Block {
+        name: Intern::new(format!("minecraft:{name}").into_boxed_str()),
+        properties: Intern::new(properties),
+    })
+}
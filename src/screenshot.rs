@@ -0,0 +1,56 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SCREENSHOTS_FOLDER: &str = "screenshots";
+
+/// A path for a new screenshot in the screenshots folder, named after the
+/// current date and time (in UTC) like Minecraft's screenshots.
+pub fn new_path() -> PathBuf {
+    fs::create_dir_all(SCREENSHOTS_FOLDER).unwrap();
+    let name = timestamp(SystemTime::now());
+    let mut path =
+        PathBuf::from(SCREENSHOTS_FOLDER).join(format!("{name}.png"));
+    let mut copy = 1;
+    while path.exists() {
+        copy += 1;
+        path = PathBuf::from(SCREENSHOTS_FOLDER)
+            .join(format!("{name}_{copy}.png"));
+    }
+    path
+}
+
+/// Formats a time as `YYYY-MM-DD_HH.MM.SS`.
+fn timestamp(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let (days, seconds) =
+        (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+
+    // Converts days since 1970 to a date in the Gregorian calendar, where
+    // eras of 400 years start on the 1st of March
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}_{:02}.{:02}.{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+    )
+}
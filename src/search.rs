@@ -0,0 +1,394 @@
+//! Counts the blocks in an area and finds where certain kinds of blocks
+//! are, such as ores, spawners or chests.
+
+use crate::{block_source::BlockSource, chunk::Block, Options};
+use glam::IVec3;
+use internment::Intern;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    fs,
+    path::Path,
+};
+
+/// Which blocks to search for, by name and optionally by their
+/// properties.
+pub struct BlockFilter {
+    patterns: Vec<BlockPattern>,
+}
+
+/// A block name with conditions on some of its properties, which a block
+/// has to meet all of.
+struct BlockPattern {
+    name: Intern<Box<str>>,
+    properties: Vec<PropertyPredicate>,
+}
+
+/// A condition on the value of a property.
+struct PropertyPredicate {
+    name: String,
+    /// Any of the values that the property must, or must not, have.
+    values: Vec<String>,
+    negated: bool,
+}
+
+impl BlockFilter {
+    /// Parses a comma separated list of blocks in the same format as
+    /// commands, such as `diamond_ore,chest[type=single]`. The namespace
+    /// defaults to `minecraft`. Properties can be compared with `!=`, and
+    /// given several values separated by `|`, such as
+    /// `oak_stairs[facing=north|south,half!=top]`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let patterns = split_outside_brackets(s)
+            .into_iter()
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(BlockPattern::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if patterns.is_empty() {
+            return Err("expected at least one block".to_owned());
+        }
+        Ok(Self { patterns })
+    }
+
+    /// Whether a block is one of those searched for.
+    pub fn matches(&self, block: &Block) -> bool {
+        self.patterns.iter().any(|pattern| pattern.matches(block))
+    }
+}
+
+impl BlockPattern {
+    fn parse(s: &str) -> Result<Self, String> {
+        let (name, properties) = match s.split_once('[') {
+            Some((name, properties)) => (
+                name,
+                properties.strip_suffix(']').ok_or_else(|| {
+                    format!("expected `]` at the end of `{s}`")
+                })?,
+            ),
+            None => (s, ""),
+        };
+        let properties = properties
+            .split(',')
+            .map(str::trim)
+            .filter(|property| !property.is_empty())
+            .map(|property| {
+                let (name, values, negated) = if let Some((name, values)) =
+                    property.split_once("!=")
+                {
+                    (name, values, true)
+                } else if let Some((name, values)) = property.split_once('=') {
+                    (name, values, false)
+                } else {
+                    return Err(format!(
+                        "expected `property=value` instead of \
+                             `{property}`"
+                    ));
+                };
+                Ok(PropertyPredicate {
+                    name: name.trim().to_owned(),
+                    values: values
+                        .split('|')
+                        .map(|value| value.trim().to_owned())
+                        .collect(),
+                    negated,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            name: Block::parse(name.trim()).name,
+            properties,
+        })
+    }
+
+    fn matches(&self, block: &Block) -> bool {
+        block.name == self.name
+            && self.properties.iter().all(|predicate| {
+                let value = block.properties.get(&predicate.name);
+                let any_equal = value.is_some_and(|value| {
+                    predicate.values.iter().any(|other| other == value)
+                });
+                any_equal != predicate.negated
+            })
+    }
+}
+
+/// Splits a list at commas, except those inside square brackets.
+fn split_outside_brackets(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in s.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&s[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// How many blocks of each kind there are in an area, and where the ones
+/// searched for are. Air isn't counted.
+#[derive(Default, Serialize)]
+pub struct BlockStatistics {
+    /// How many blocks of each kind there are, by name.
+    pub counts: BTreeMap<String, u64>,
+    /// How many blocks of each kind there are at each Y level.
+    pub counts_by_y: BTreeMap<String, BTreeMap<i32, u64>>,
+    /// The position of every block of each kind that matched the filter,
+    /// from the bottom up. Empty if there wasn't a filter.
+    pub positions: BTreeMap<String, Vec<[i32; 3]>>,
+}
+
+impl BlockStatistics {
+    /// Counts the blocks in the area to render, and lists the positions of
+    /// those that match `filter`, if there is one, counting only them.
+    ///
+    /// Sections whose palette has none of the blocks searched for are
+    /// skipped without looking at their blocks.
+    pub fn collect(
+        world: &dyn BlockSource,
+        options: &Options,
+        filter: Option<&BlockFilter>,
+    ) -> Self {
+        let mut counts_by_y: HashMap<Intern<Box<str>>, BTreeMap<i32, u64>> =
+            HashMap::new();
+        let mut positions: HashMap<Intern<Box<str>>, Vec<[i32; 3]>> =
+            HashMap::new();
+        let area = &options.area;
+        let section_range = |start: i32, end: i32| {
+            start.div_euclid(16)..=(end - 1).div_euclid(16)
+        };
+        for section_y in -4..20 {
+            for section_z in section_range(area.start.y, area.end.y) {
+                for section_x in section_range(area.start.x, area.end.x) {
+                    let pos = IVec3::new(section_x, section_y, section_z);
+                    let Some(blocks) = world.section_at(pos) else {
+                        continue;
+                    };
+                    let counted: Vec<bool> = blocks
+                        .palette
+                        .iter()
+                        .map(|block| {
+                            !block.is_air()
+                                && filter.is_none_or(|f| f.matches(block))
+                        })
+                        .collect();
+                    if !counted.contains(&true) {
+                        continue;
+                    }
+
+                    // Counts by palette index and Y within the section
+                    let mut section_counts = vec![[0u64; 16]; counted.len()];
+                    let min = (pos * 16).max(IVec3::new(
+                        area.start.x,
+                        i32::MIN,
+                        area.start.y,
+                    ));
+                    let max = (pos * 16 + 16).min(IVec3::new(
+                        area.end.x,
+                        i32::MAX,
+                        area.end.y,
+                    ));
+                    for y in min.y..max.y {
+                        for z in min.z..max.z {
+                            for x in min.x..max.x {
+                                let p = IVec3 { x, y, z };
+                                let offset =
+                                    (p - pos * 16).dot(IVec3::new(1, 256, 16));
+                                let index =
+                                    blocks.data[offset as usize] as usize;
+                                if !counted[index] {
+                                    continue;
+                                }
+                                section_counts[index][(y - min.y) as usize] +=
+                                    1;
+                                if filter.is_some() {
+                                    positions
+                                        .entry(blocks.palette[index].name)
+                                        .or_default()
+                                        .push(p.to_array());
+                                }
+                            }
+                        }
+                    }
+
+                    for (block, counts) in
+                        blocks.palette.iter().zip(section_counts)
+                    {
+                        for (y, count) in counts.into_iter().enumerate() {
+                            if count > 0 {
+                                *counts_by_y
+                                    .entry(block.name)
+                                    .or_default()
+                                    .entry(min.y + y as i32)
+                                    .or_default() += count;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Self {
+            counts: counts_by_y
+                .iter()
+                .map(|(name, counts)| (name.to_string(), counts.values().sum()))
+                .collect(),
+            counts_by_y: counts_by_y
+                .into_iter()
+                .map(|(name, counts)| (name.to_string(), counts))
+                .collect(),
+            positions: positions
+                .into_iter()
+                .map(|(name, mut positions)| {
+                    positions.sort_by_key(|&[x, y, z]| (y, z, x));
+                    (name.to_string(), positions)
+                })
+                .collect(),
+        }
+    }
+
+    /// Saves the statistics as JSON, or as CSV if the path ends in `.csv`.
+    /// CSV files hold a single table, so the counts by Y are saved next to
+    /// the file as `<name>-by-y.csv` and the positions as
+    /// `<name>-positions.csv`.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let write = |path: &Path, contents: String| {
+            fs::write(path, contents)
+                .map_err(|error| format!("{}: {error}", path.display()))
+        };
+        if path.extension().and_then(|extension| extension.to_str())
+            != Some("csv")
+        {
+            return write(path, serde_json::to_string_pretty(self).unwrap());
+        }
+
+        let mut counts = "block,count\n".to_owned();
+        for (name, count) in &self.counts {
+            writeln!(counts, "{name},{count}").unwrap();
+        }
+        write(path, counts)?;
+
+        let sibling = |suffix: &str| {
+            let stem = path.file_stem().unwrap().to_string_lossy();
+            path.with_file_name(format!("{stem}-{suffix}.csv"))
+        };
+        let mut by_y = "y".to_owned();
+        for name in self.counts_by_y.keys() {
+            write!(by_y, ",{name}").unwrap();
+        }
+        by_y.push('\n');
+        let ys = self.counts_by_y.values().flat_map(BTreeMap::keys);
+        if let (Some(&min), Some(&max)) = (ys.clone().min(), ys.max()) {
+            for y in (min..=max).rev() {
+                write!(by_y, "{y}").unwrap();
+                for counts in self.counts_by_y.values() {
+                    write!(by_y, ",{}", counts.get(&y).unwrap_or(&0)).unwrap();
+                }
+                by_y.push('\n');
+            }
+        }
+        write(&sibling("by-y"), by_y)?;
+
+        if !self.positions.is_empty() {
+            let mut positions = "block,x,y,z\n".to_owned();
+            for (name, list) in &self.positions {
+                for [x, y, z] in list {
+                    writeln!(positions, "{name},{x},{y},{z}").unwrap();
+                }
+            }
+            write(&sibling("positions"), positions)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chunk::AIR, test_util};
+    use glam::IVec2;
+
+    fn world(pos: IVec3) -> Option<Intern<Block>> {
+        Some(match pos.to_array() {
+            [0..=3, 0, 0..=1] => Block::parse("stone"),
+            [2, 5 | 20, 2] => Block::parse("diamond_ore"),
+            [17, -30, 3] => Block::parse("deepslate_diamond_ore"),
+            [40, 1, 1] => Block::parse("chest[facing=north]"),
+            _ => *AIR,
+        })
+    }
+
+    #[test]
+    fn counts_every_block_in_the_area() {
+        let options = test_util::options(IVec2::new(0, 0)..IVec2::new(32, 16));
+        let statistics = BlockStatistics::collect(&world, &options, None);
+        assert_eq!(
+            statistics.counts,
+            BTreeMap::from([
+                ("minecraft:stone".to_owned(), 8),
+                ("minecraft:diamond_ore".to_owned(), 2),
+                ("minecraft:deepslate_diamond_ore".to_owned(), 1),
+            ])
+        );
+        assert_eq!(
+            statistics.counts_by_y["minecraft:diamond_ore"],
+            BTreeMap::from([(5, 1), (20, 1)])
+        );
+        assert!(statistics.positions.is_empty());
+    }
+
+    #[test]
+    fn finds_blocks_matching_the_filter() {
+        let options = test_util::options(IVec2::new(1, 0)..IVec2::new(48, 3));
+        let filter =
+            BlockFilter::parse("diamond_ore,minecraft:chest,emerald_ore")
+                .unwrap();
+        let statistics =
+            BlockStatistics::collect(&world, &options, Some(&filter));
+        assert_eq!(
+            statistics.counts,
+            BTreeMap::from([
+                ("minecraft:diamond_ore".to_owned(), 2),
+                ("minecraft:chest".to_owned(), 1),
+            ])
+        );
+        assert_eq!(
+            statistics.positions["minecraft:diamond_ore"],
+            [[2, 5, 2], [2, 20, 2]]
+        );
+        assert_eq!(statistics.positions["minecraft:chest"], [[40, 1, 1]]);
+    }
+
+    #[test]
+    fn matches_properties() {
+        let filter = BlockFilter::parse(
+            "chest[facing=north|south,type!=left],minecraft:stone",
+        )
+        .unwrap();
+        for (block, matches) in [
+            ("chest[facing=north,type=single]", true),
+            ("chest[facing=south]", true),
+            ("chest[facing=east,type=single]", false),
+            ("chest[facing=north,type=left]", false),
+            ("trapped_chest[facing=north]", false),
+            ("stone", true),
+        ] {
+            assert_eq!(
+                filter.matches(&Block::parse(block)),
+                matches,
+                "{block}"
+            );
+        }
+        assert!(BlockFilter::parse("chest[facing]").is_err());
+        assert!(BlockFilter::parse(",").is_err());
+    }
+}
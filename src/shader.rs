@@ -1,22 +1,73 @@
-use glam::Mat4;
-use miniquad::{ShaderMeta, UniformBlockLayout, UniformDesc};
+use glam::{Mat4, Vec3};
+use miniquad::{ShaderMeta, UniformBlockLayout, UniformDesc, UniformType};
 
 pub const VERTEX: &str = include_str!("shader/shader.vert");
 pub const FRAGMENT: &str = include_str!("shader/shader.frag");
+pub const SKY_VERTEX: &str = include_str!("shader/sky.vert");
+pub const SKY_FRAGMENT: &str = include_str!("shader/sky.frag");
+pub const HIGHLIGHT_VERTEX: &str = include_str!("shader/highlight.vert");
+pub const HIGHLIGHT_FRAGMENT: &str = include_str!("shader/highlight.frag");
 
 #[repr(C)]
 pub struct Uniforms {
     pub mvp: Mat4,
+    pub camera_position: Vec3,
+    pub fog_color: Vec3,
+    pub fog_start: f32,
+    pub fog_end: f32,
 }
 
 pub fn meta() -> ShaderMeta {
     ShaderMeta {
         uniforms: UniformBlockLayout {
-            uniforms: vec![UniformDesc::new(
-                "view",
-                miniquad::UniformType::Mat4,
-            )],
+            uniforms: vec![
+                UniformDesc::new("view", UniformType::Mat4),
+                UniformDesc::new("camera_position", UniformType::Float3),
+                UniformDesc::new("fog_color", UniformType::Float3),
+                UniformDesc::new("fog_start", UniformType::Float1),
+                UniformDesc::new("fog_end", UniformType::Float1),
+            ],
         },
         images: vec!["tex".to_owned()],
     }
 }
+
+#[repr(C)]
+pub struct SkyUniforms {
+    pub inverse_view: Mat4,
+    pub sky_color: Vec3,
+    pub fog_color: Vec3,
+    pub sun_direction: Vec3,
+    pub show_sun: f32,
+    pub moon_phase: f32,
+}
+
+pub fn sky_meta() -> ShaderMeta {
+    ShaderMeta {
+        uniforms: UniformBlockLayout {
+            uniforms: vec![
+                UniformDesc::new("inverse_view", UniformType::Mat4),
+                UniformDesc::new("sky_color", UniformType::Float3),
+                UniformDesc::new("fog_color", UniformType::Float3),
+                UniformDesc::new("sun_direction", UniformType::Float3),
+                UniformDesc::new("show_sun", UniformType::Float1),
+                UniformDesc::new("moon_phase", UniformType::Float1),
+            ],
+        },
+        images: vec!["sun".to_owned(), "moon".to_owned()],
+    }
+}
+
+#[repr(C)]
+pub struct HighlightUniforms {
+    pub mvp: Mat4,
+}
+
+pub fn highlight_meta() -> ShaderMeta {
+    ShaderMeta {
+        uniforms: UniformBlockLayout {
+            uniforms: vec![UniformDesc::new("view", UniformType::Mat4)],
+        },
+        images: Vec::new(),
+    }
+}
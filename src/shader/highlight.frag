@@ -0,0 +1,16 @@
+#version 400
+
+varying vec2 face_uv;
+varying vec3 highlight_color;
+
+// The same as in `highlight_alpha`
+const float OUTLINE_WIDTH = 0.08;
+const float OUTLINE_ALPHA = 0.9;
+const float GLOW_ALPHA = 0.25;
+
+void main() {
+    vec2 edge = min(face_uv, 1.0 - face_uv);
+    float alpha =
+        min(edge.x, edge.y) < OUTLINE_WIDTH ? OUTLINE_ALPHA : GLOW_ALPHA;
+    gl_FragColor = vec4(highlight_color, alpha);
+}
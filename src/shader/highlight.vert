@@ -0,0 +1,16 @@
+#version 400
+
+attribute vec4 pos;
+attribute vec2 uv;
+attribute vec3 color;
+
+uniform mat4 view;
+
+varying vec2 face_uv;
+varying vec3 highlight_color;
+
+void main() {
+    gl_Position = view * pos;
+    face_uv = uv;
+    highlight_color = color;
+}
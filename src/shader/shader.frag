@@ -2,20 +2,30 @@
 
 varying lowp vec2 texcoord;
 varying lowp float fragment_light_level;
+varying vec3 world_pos;
 flat in float fragment_texture_index;
 
 uniform sampler2D tex;
+uniform vec3 camera_position;
+uniform vec3 fog_color;
+uniform float fog_start;
+uniform float fog_end;
 
 void main() {
-    ivec2 atlas_size = textureSize(tex, 0);
+    // The atlas is a grid of 16x16 tiles
+    ivec2 tile_count = textureSize(tex, 0) / 16;
+    int index = int(fragment_texture_index);
+    vec2 tile = vec2(index % tile_count.x, index / tile_count.x);
     vec4 color = texture(
         tex,
-        vec2(
-            texcoord.x,
-            (texcoord.y + fragment_texture_index) * atlas_size.x / atlas_size.y
-        )
+        (tile + clamp(texcoord, 0.0, 0.999)) / vec2(tile_count)
     ) * vec4(vec3(fragment_light_level), 1.0);
     if (color.w == 0.0)
         discard;
-    gl_FragColor = color;
+    float fog = smoothstep(
+        fog_start,
+        fog_end,
+        distance(world_pos, camera_position)
+    );
+    gl_FragColor = vec4(mix(color.rgb, fog_color, fog), color.a);
 }
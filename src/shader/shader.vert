@@ -9,11 +9,13 @@ uniform mat4 view;
 
 varying lowp vec2 texcoord;
 varying lowp float fragment_light_level;
+varying vec3 world_pos;
 flat out float fragment_texture_index;
 
 void main() {
     gl_Position = view * pos;
     texcoord = uv;
     fragment_light_level = light_level;
+    world_pos = pos.xyz;
     fragment_texture_index = texture_index;
 }
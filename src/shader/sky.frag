@@ -0,0 +1,56 @@
+#version 400
+
+varying vec2 screen_pos;
+
+uniform sampler2D sun;
+uniform sampler2D moon;
+uniform mat4 inverse_view;
+uniform vec3 sky_color;
+uniform vec3 fog_color;
+uniform vec3 sun_direction;
+uniform float show_sun;
+uniform float moon_phase;
+
+// Half the width of the sun and moon, as seen from a distance of 1
+const float SUN_SIZE = 0.15;
+const float MOON_SIZE = 0.1;
+
+// Where the direction hits a square facing the origin from `center`, with
+// both coordinates from 0 to 1 inside the square
+vec2 billboard_uv(vec3 direction, vec3 center, float size) {
+    vec3 up = vec3(0.0, 0.0, 1.0);
+    vec3 right = cross(center, up);
+    float depth = dot(direction, center);
+    if (depth <= 0.0)
+        return vec2(-1.0);
+    vec2 uv = vec2(dot(direction, right), dot(direction, up)) / depth;
+    return uv / size * 0.5 + 0.5;
+}
+
+bool inside(vec2 uv) {
+    return all(greaterThanEqual(uv, vec2(0.0)))
+        && all(lessThan(uv, vec2(1.0)));
+}
+
+void main() {
+    vec4 near_point = inverse_view * vec4(screen_pos, -1.0, 1.0);
+    vec4 far_point = inverse_view * vec4(screen_pos, 1.0, 1.0);
+    vec3 direction = normalize(
+        far_point.xyz / far_point.w - near_point.xyz / near_point.w
+    );
+
+    vec3 color = mix(fog_color, sky_color, smoothstep(0.0, 0.4, direction.y));
+    if (show_sun > 0.5) {
+        // The sun and moon textures have black backgrounds and are added to
+        // the sky
+        vec2 sun_uv = billboard_uv(direction, sun_direction, SUN_SIZE);
+        if (inside(sun_uv))
+            color += texture(sun, sun_uv).rgb;
+        vec2 moon_uv = billboard_uv(direction, -sun_direction, MOON_SIZE);
+        if (inside(moon_uv)) {
+            vec2 phase = vec2(mod(moon_phase, 4.0), floor(moon_phase / 4.0));
+            color += texture(moon, (phase + moon_uv) / vec2(4.0, 2.0)).rgb;
+        }
+    }
+    gl_FragColor = vec4(color, 1.0);
+}
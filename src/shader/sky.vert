@@ -0,0 +1,10 @@
+#version 400
+
+attribute vec2 pos;
+
+varying vec2 screen_pos;
+
+void main() {
+    gl_Position = vec4(pos, 0.0, 1.0);
+    screen_pos = pos;
+}
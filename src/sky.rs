@@ -0,0 +1,166 @@
+use crate::{
+    camera::Camera,
+    texture::{read_image, Image},
+    world::Dimension,
+};
+use glam::{Vec2, Vec3};
+use std::{f32::consts::TAU, path::Path};
+
+const TICKS_PER_DAY: i64 = 24000;
+/// Half the width of the sun and moon, as seen from a distance of 1.
+const SUN_SIZE: f32 = 0.15;
+const MOON_SIZE: f32 = 0.1;
+
+/// The colors of the sky and fog and the position of the sun for a
+/// dimension at a time of day.
+pub struct Sky {
+    /// The color of the background.
+    pub sky_color: Vec3,
+    /// The color that faraway things fade to.
+    pub fog_color: Vec3,
+    /// Where fog starts, as a fraction of the view distance.
+    pub fog_start: f32,
+    /// Where fog hides everything, as a fraction of the view distance.
+    pub fog_end: f32,
+    /// The direction towards the sun, if the dimension has one.
+    pub sun_direction: Option<Vec3>,
+    /// Which of the 8 moon phases to show.
+    pub moon_phase: u8,
+}
+
+impl Sky {
+    /// The sky of a dimension at a time of day in ticks, where 0 is sunrise.
+    pub fn new(dimension: Dimension, day_time: i64) -> Self {
+        match dimension {
+            Dimension::Overworld => {
+                // The sun rises in the east at tick 0 and sets in the west
+                let angle = (day_time.rem_euclid(TICKS_PER_DAY) as f32
+                    / TICKS_PER_DAY as f32)
+                    * TAU;
+                let sun_direction = Vec3::new(angle.cos(), angle.sin(), 0.0);
+                let daylight =
+                    sun_direction.y.mul_add(2.0, 0.5).clamp(0.0, 1.0);
+                Self {
+                    sky_color: Vec3::new(0.47, 0.65, 1.0) * daylight,
+                    fog_color: Vec3::new(0.75, 0.85, 1.0)
+                        * daylight.mul_add(0.94, 0.06),
+                    fog_start: 0.75,
+                    fog_end: 1.0,
+                    sun_direction: Some(sun_direction),
+                    moon_phase: (day_time.div_euclid(TICKS_PER_DAY) % 8) as u8,
+                }
+            }
+            Dimension::Nether => Self {
+                sky_color: Vec3::new(0.2, 0.03, 0.03),
+                fog_color: Vec3::new(0.2, 0.03, 0.03),
+                fog_start: 0.1,
+                fog_end: 0.6,
+                sun_direction: None,
+                moon_phase: 0,
+            },
+            Dimension::End => Self {
+                sky_color: Vec3::ZERO,
+                fog_color: Vec3::ZERO,
+                fog_start: 0.75,
+                fog_end: 1.0,
+                sun_direction: None,
+                moon_phase: 0,
+            },
+        }
+    }
+}
+
+impl Sky {
+    /// The distances from the camera where fog starts and ends. There's no
+    /// fog in orthographic views, which have the camera in their middle.
+    pub fn fog_distances(
+        &self,
+        camera: &Camera,
+        view_distance: f32,
+    ) -> [f32; 2] {
+        if camera.is_orthographic() {
+            [f32::MAX / 2.0, f32::MAX]
+        } else {
+            [self.fog_start * view_distance, self.fog_end * view_distance]
+        }
+    }
+
+    /// The color of the sky in a direction, the same as drawn by the sky
+    /// shader.
+    pub fn color(&self, direction: Vec3, textures: &SkyTextures) -> Vec3 {
+        let elevation = (direction.y / 0.4).clamp(0.0, 1.0);
+        let elevation = elevation * elevation * (3.0 - 2.0 * elevation);
+        let mut color = self.fog_color.lerp(self.sky_color, elevation);
+        let Some(sun_direction) = self.sun_direction else {
+            return color;
+        };
+        // The sun and moon textures have black backgrounds and are added to
+        // the sky
+        if let (Some(uv), Some(sun)) = (
+            billboard_uv(direction, sun_direction, SUN_SIZE),
+            &textures.sun,
+        ) {
+            color += sample(sun, uv);
+        }
+        if let (Some(uv), Some(moon)) = (
+            billboard_uv(direction, -sun_direction, MOON_SIZE),
+            &textures.moon,
+        ) {
+            let phase = Vec2::new(
+                f32::from(self.moon_phase % 4),
+                f32::from(self.moon_phase / 4),
+            );
+            color += sample(moon, (phase + uv) / Vec2::new(4.0, 2.0));
+        }
+        color
+    }
+}
+
+/// The textures of the sun and the moon.
+pub struct SkyTextures {
+    /// The sun, if the resource pack has one.
+    pub sun: Option<Image>,
+    /// All 8 phases of the moon in a 4x2 grid.
+    pub moon: Option<Image>,
+}
+
+impl SkyTextures {
+    /// Loads the textures from a resource pack.
+    pub fn load(resource_pack: &Path) -> Self {
+        let load = |name| {
+            let path = resource_pack
+                .join(format!("assets/minecraft/textures/environment/{name}"));
+            let image = read_image(&path);
+            if image.is_none() {
+                eprintln!("Missing texture: {}", path.display());
+            }
+            image
+        };
+        Self {
+            sun: load("sun.png"),
+            moon: load("moon_phases.png"),
+        }
+    }
+}
+
+/// Where a direction hits a square facing the origin from `center`, with
+/// both coordinates from 0 to 1 inside the square.
+fn billboard_uv(direction: Vec3, center: Vec3, size: f32) -> Option<Vec2> {
+    let up = Vec3::Z;
+    let right = center.cross(up);
+    let depth = direction.dot(center);
+    if depth <= 0.0 {
+        return None;
+    }
+    let uv = Vec2::new(direction.dot(right), direction.dot(up)) / depth / size
+        * 0.5
+        + 0.5;
+    (uv.cmpge(Vec2::ZERO).all() && uv.cmplt(Vec2::ONE).all()).then_some(uv)
+}
+
+fn sample(image: &Image, uv: Vec2) -> Vec3 {
+    let x = ((uv.x * image.width as f32) as u32).min(image.width - 1);
+    let y = ((uv.y * image.height as f32) as u32).min(image.height - 1);
+    let [r, g, b, _] = image.pixel(x, y);
+    Vec3::new(f32::from(r), f32::from(g), f32::from(b)) / 255.0
+}
@@ -0,0 +1,180 @@
+//! Builds small worlds for tests, by writing region files with chunks made
+//! from hand-picked palettes.
+
+use crate::{
+    camera::{Camera, Projection},
+    entity::EntityCategories,
+    Options,
+};
+use glam::{IVec2, Vec3};
+use nbt::{Blob, Value};
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+/// The data version of chunks written by tests, which is Minecraft 1.19.
+pub const DATA_VERSION: i32 = 3105;
+
+/// An empty directory that no other test uses.
+pub fn temp_dir(name: &str) -> PathBuf {
+    static COUNT: AtomicU32 = AtomicU32::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "mcrender-{}-{}-{name}",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed),
+    ));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    path
+}
+
+/// Options that render `area` and nothing else.
+pub fn options(area: Range<IVec2>) -> Options {
+    Options {
+        resource_pack_path: PathBuf::from("resource-pack"),
+        camera: Camera {
+            position: Vec3::ZERO,
+            yaw: 0.0,
+            pitch: 0.0,
+            vfov: 1.0,
+            projection: Projection::Perspective,
+        },
+        camera_path: None,
+        resolution: [64, 64],
+        screenshot_scale: 1,
+        area,
+        entities: EntityCategories::ALL,
+        cave_culling: false,
+        lod_distance: None,
+        view_distance: None,
+        time: None,
+        highlight: None,
+        hide_unhighlighted: false,
+        max_y: None,
+        slice: None,
+        x_ray: None,
+        changes: None,
+        chunk_overlay: None,
+        #[cfg(feature = "window")]
+        key_bindings: crate::KeyBindings::default(),
+    }
+}
+
+/// A section's `block_states` compound, with the indices packed into longs
+/// the way Minecraft 1.16 and later do, without spanning two longs. All of
+/// the section is the first block if `indices` is empty.
+pub fn block_states(palette: &[&str], indices: &[u16]) -> Value {
+    let entries = palette
+        .iter()
+        .map(|name| {
+            Value::Compound(
+                [("Name".to_owned(), Value::String((*name).to_owned()))].into(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut compound =
+        nbt::Map::from([("palette".to_owned(), Value::List(entries))]);
+    if !indices.is_empty() {
+        let bits = (usize::BITS - (palette.len() - 1).leading_zeros()).max(4);
+        let per_long = (64 / bits) as usize;
+        let longs = indices
+            .chunks(per_long)
+            .map(|chunk| {
+                chunk.iter().enumerate().fold(0u64, |long, (i, &index)| {
+                    long | u64::from(index) << (i as u32 * bits)
+                }) as i64
+            })
+            .collect();
+        compound.insert("data".to_owned(), Value::LongArray(longs));
+    }
+    Value::Compound(compound)
+}
+
+/// A chunk with the given `block_states` for some sections, by section Y
+/// from -4 to 19. Every other section is air.
+pub fn chunk(sections: &[(i32, Value)]) -> Blob {
+    let sections = (-4..20)
+        .map(|y| {
+            let block_states = sections
+                .iter()
+                .find(|(section_y, _)| *section_y == y)
+                .map_or_else(
+                    || block_states(&["minecraft:air"], &[]),
+                    |(_, block_states)| block_states.clone(),
+                );
+            Value::Compound(nbt::Map::from([
+                ("Y".to_owned(), Value::Byte(y as i8)),
+                ("block_states".to_owned(), block_states),
+            ]))
+        })
+        .collect();
+    let mut blob = Blob::new();
+    blob.insert("DataVersion", DATA_VERSION).unwrap();
+    blob.insert("sections", Value::List(sections)).unwrap();
+    blob
+}
+
+/// A chunk for [`world`], with blocks in its section from Y 0 to 15 and
+/// air everywhere else.
+pub struct TestChunk<'a> {
+    /// The position of the chunk, from 0 to 31 along both axes.
+    pub pos: IVec2,
+    /// The blocks of the section, as in [`block_states`].
+    pub palette: &'a [&'a str],
+    /// The index in the palette of each block in the section, as in
+    /// [`block_states`].
+    pub indices: &'a [u16],
+    /// Fields to add to the chunk, such as `InhabitedTime`.
+    pub fields: Vec<(&'a str, Value)>,
+}
+
+/// A new world folder whose region `r.0.0.mca` has the given chunks.
+pub fn world(name: &str, chunks: &[TestChunk]) -> PathBuf {
+    let directory = temp_dir(name);
+    let chunks: Vec<_> = chunks
+        .iter()
+        .map(|test_chunk| {
+            let block_states =
+                block_states(test_chunk.palette, test_chunk.indices);
+            let mut chunk = chunk(&[(0, block_states)]);
+            for (name, value) in &test_chunk.fields {
+                chunk.insert(*name, value.clone()).unwrap();
+            }
+            (test_chunk.pos, chunk)
+        })
+        .collect();
+    write_region(&directory.join("region/r.0.0.mca"), &chunks);
+    directory
+}
+
+/// Writes a region file with zlib compressed chunks at chunk positions
+/// within the region, from 0 to 31.
+pub fn write_region(path: &Path, chunks: &[(IVec2, Blob)]) {
+    let mut locations = vec![0; 4096];
+    let mut data = Vec::new();
+    for (pos, chunk) in chunks {
+        let mut compressed = Vec::new();
+        chunk.to_zlib_writer(&mut compressed).unwrap();
+        let sector = 2 + data.len() / 4096;
+        data.extend(((compressed.len() + 1) as u32).to_be_bytes());
+        data.push(2);
+        data.extend(compressed);
+        data.resize(data.len().next_multiple_of(4096), 0);
+        let sector_count = 2 + data.len() / 4096 - sector;
+        let location = (pos.y * 32 + pos.x) as usize * 4;
+        locations[location..location + 4].copy_from_slice(&[
+            (sector >> 16) as u8,
+            (sector >> 8) as u8,
+            sector as u8,
+            sector_count as u8,
+        ]);
+    }
+    let mut file = locations;
+    file.extend([0; 4096]);
+    file.extend(data);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, file).unwrap();
+}
@@ -0,0 +1,316 @@
+use crate::entity::MapColors;
+use indexmap::IndexSet;
+use pix::{
+    el::Pixel,
+    rgb::{SRgb8, SRgba8},
+    Raster,
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+pub const TILE_SIZE: usize = 16;
+
+/// A single tile of the texture atlas.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Texture {
+    /// A whole block texture, `assets/minecraft/textures/block/{name}.png`.
+    Block(&'static str),
+    /// The texture of an item, falling back to the block texture of the same
+    /// name for items that are drawn as blocks.
+    Item(&'static str),
+    /// A tile of a single color, used for distant terrain.
+    Color([u8; 3]),
+    /// One of the 8x8 tiles that the contents of a map are split into.
+    Map {
+        /// The ID of the map.
+        id: i32,
+        /// Which column of tiles, from 0 to 7.
+        x: u8,
+        /// Which row of tiles, from 0 to 7.
+        y: u8,
+    },
+    /// A rectangle cut out of an arbitrary texture and stretched to fill the
+    /// tile, used for entity models, fonts and the like.
+    Sprite {
+        /// The texture path relative to `assets/minecraft/textures`,
+        /// without the `.png` extension.
+        path: &'static str,
+        /// The part of the texture to show.
+        rect: Rect,
+        /// A color to multiply the texture by, if any.
+        tint: Option<[u8; 3]>,
+    },
+}
+
+/// A rectangle within a texture, measured in the pixels of a texture that
+/// is `image_width` pixels wide so that higher resolution resource packs
+/// still line up.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect {
+    /// The left edge.
+    pub x: u16,
+    /// The top edge.
+    pub y: u16,
+    /// How wide the rectangle is.
+    pub width: u16,
+    /// How high the rectangle is.
+    pub height: u16,
+    /// How wide the texture the rectangle is measured in is.
+    pub image_width: u16,
+}
+
+/// Every texture of a mesh in one image, as square tiles.
+pub struct Atlas {
+    /// RGBA pixels, row by row.
+    pub pixels: Vec<u8>,
+    /// The width of the image in pixels.
+    pub width: u32,
+    /// The height of the image in pixels.
+    pub height: u32,
+}
+
+impl Atlas {
+    /// Loads the textures from a resource pack and packs them together,
+    /// with missing ones left white.
+    pub fn build(
+        textures: &IndexSet<Texture>,
+        resource_pack: &Path,
+        maps: &HashMap<i32, MapColors>,
+    ) -> Self {
+        let columns = (textures.len() as f64).sqrt().ceil().max(1.0) as usize;
+        let rows = textures.len().div_ceil(columns).max(1);
+        let width = columns * TILE_SIZE;
+        let mut pixels = vec![0; width * rows * TILE_SIZE * 4];
+
+        let textures_path = resource_pack.join("assets/minecraft/textures");
+        let mut images = HashMap::new();
+        for (index, texture) in textures.iter().enumerate() {
+            let tile = texture.load(&textures_path, maps, &mut images);
+            let (column, row) = (index % columns, index / columns);
+            for (y, tile_row) in tile.chunks_exact(TILE_SIZE * 4).enumerate() {
+                let start =
+                    ((row * TILE_SIZE + y) * width + column * TILE_SIZE) * 4;
+                pixels[start..][..TILE_SIZE * 4].copy_from_slice(tile_row);
+            }
+        }
+
+        Self {
+            pixels,
+            width: width as u32,
+            height: (rows * TILE_SIZE) as u32,
+        }
+    }
+}
+
+impl Texture {
+    fn load(
+        self,
+        textures_path: &Path,
+        maps: &HashMap<i32, MapColors>,
+        images: &mut HashMap<&'static str, Option<Image>>,
+    ) -> Box<[u8]> {
+        match self {
+            Self::Item(name) => {
+                let Some(image) =
+                    read_image(&textures_path.join(format!("item/{name}.png")))
+                        .or_else(|| {
+                            read_image(
+                                &textures_path
+                                    .join(format!("block/{name}.png")),
+                            )
+                        })
+                else {
+                    eprintln!("Missing item texture: {name}");
+                    return Box::new([255; TILE_SIZE * TILE_SIZE * 4]);
+                };
+                image.scaled_tile(0, 0, image.width, image.width, [255; 3])
+            }
+            Self::Color([r, g, b]) => {
+                [r, g, b, 255].repeat(TILE_SIZE * TILE_SIZE).into()
+            }
+            Self::Map { id, x, y } => {
+                let Some(map) = maps.get(&id) else {
+                    return Box::new([0; TILE_SIZE * TILE_SIZE * 4]);
+                };
+                (0..TILE_SIZE * TILE_SIZE)
+                    .flat_map(|i| {
+                        map.pixel(
+                            usize::from(x) * TILE_SIZE + i % TILE_SIZE,
+                            usize::from(y) * TILE_SIZE + i / TILE_SIZE,
+                        )
+                    })
+                    .collect()
+            }
+            Self::Block(name) => {
+                let path = textures_path.join(format!("block/{name}.png"));
+                let Some(image) = read_image(&path) else {
+                    eprintln!("Missing block texture: {}", path.display());
+                    return Box::new([255; TILE_SIZE * TILE_SIZE * 4]);
+                };
+                // Animated textures are stored as a vertical strip of frames,
+                // of which only the first one is used
+                image.scaled_tile(0, 0, image.width, image.width, [255; 3])
+            }
+            Self::Sprite { path, rect, tint } => {
+                let image = images.entry(path).or_insert_with(|| {
+                    let full_path = textures_path.join(format!("{path}.png"));
+                    let image = read_image(&full_path);
+                    if image.is_none() {
+                        eprintln!("Missing texture: {}", full_path.display());
+                    }
+                    image
+                });
+                let Some(image) = image else {
+                    return Box::new([255; TILE_SIZE * TILE_SIZE * 4]);
+                };
+                let scale = image.width as f32 / f32::from(rect.image_width);
+                let scaled = |n: u16| (f32::from(n) * scale) as u32;
+                image.scaled_tile(
+                    scaled(rect.x),
+                    scaled(rect.y),
+                    scaled(rect.width).max(1),
+                    scaled(rect.height).max(1),
+                    tint.unwrap_or([255; 3]),
+                )
+            }
+        }
+    }
+}
+
+/// An image in memory.
+pub struct Image {
+    /// The width of the image in pixels.
+    pub width: u32,
+    /// The height of the image in pixels.
+    pub height: u32,
+    /// RGBA pixels, row by row.
+    pub pixels: Box<[u8]>,
+}
+
+impl Image {
+    /// The color of the pixel at a position, from the top left corner.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let start = (y * self.width + x) as usize * 4;
+        self.pixels[start..][..4].try_into().unwrap()
+    }
+
+    /// Saves the image as a PNG file.
+    pub fn write_png(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path)
+            .map_err(|error| format!("{}: {error}", path.display()))?;
+        self.encode_png(BufWriter::new(file))
+            .map_err(|error| format!("{}: {error}", path.display()))
+    }
+
+    /// Encodes the image as PNG.
+    pub fn encode_png(
+        &self,
+        writer: impl Write,
+    ) -> Result<(), png_pong::encode::Error> {
+        let raster = Raster::<SRgba8>::with_u8_buffer(
+            self.width,
+            self.height,
+            &*self.pixels,
+        );
+        png_pong::Encoder::new(writer)
+            .into_step_enc()
+            .still(&raster)
+    }
+
+    /// The average color of the image's opaque pixels.
+    pub fn average_color(&self) -> [u8; 3] {
+        let mut sum = [0u64; 3];
+        let mut weight = 0;
+        for pixel in self.pixels.chunks_exact(4) {
+            let alpha = u64::from(pixel[3]);
+            for (sum, &channel) in sum.iter_mut().zip(pixel) {
+                *sum += u64::from(channel) * alpha;
+            }
+            weight += alpha;
+        }
+        sum.map(|sum| (sum / weight.max(1)) as u8)
+    }
+
+    /// Stretches a rectangle of the image to a single atlas tile using
+    /// nearest neighbour sampling, multiplying each pixel by `tint`.
+    fn scaled_tile(
+        &self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        tint: [u8; 3],
+    ) -> Box<[u8]> {
+        let mut tile = vec![0; TILE_SIZE * TILE_SIZE * 4];
+        for (i, pixel) in tile.chunks_exact_mut(4).enumerate() {
+            let tile_x = (i % TILE_SIZE) as u32;
+            let tile_y = (i / TILE_SIZE) as u32;
+            let source_x = x + tile_x * width / TILE_SIZE as u32;
+            let source_y = y + tile_y * height / TILE_SIZE as u32;
+            if source_x >= self.width || source_y >= self.height {
+                continue;
+            }
+            let [r, g, b, a] = self.pixel(source_x, source_y);
+            let tinted = |channel: u8, tint: u8| {
+                (u16::from(channel) * u16::from(tint) / 255) as u8
+            };
+            pixel.copy_from_slice(&[
+                tinted(r, tint[0]),
+                tinted(g, tint[1]),
+                tinted(b, tint[2]),
+                a,
+            ]);
+        }
+        tile.into()
+    }
+}
+
+pub fn read_image(path: &Path) -> Option<Image> {
+    let file = File::open(path).ok()?;
+    let raster = png_pong::Decoder::new(file)
+        .ok()?
+        .into_steps()
+        .next()?
+        .ok()?
+        .raster;
+    let raster = match raster {
+        png_pong::PngRaster::Gray8(raster) => Raster::with_raster(&raster),
+        png_pong::PngRaster::Gray16(raster) => Raster::with_raster(&raster),
+        png_pong::PngRaster::Rgb8(raster) => {
+            Raster::<SRgba8>::with_raster(&raster)
+        }
+        png_pong::PngRaster::Rgb16(raster) => Raster::with_raster(&raster),
+        png_pong::PngRaster::Palette(indices, palette, alphas) => {
+            let pixels = indices
+                .pixels()
+                .iter()
+                .flat_map(|&index| {
+                    let index = usize::from(u8::from(index.one()));
+                    let color =
+                        palette.entry(index).unwrap_or(SRgb8::new(0, 0, 0));
+                    let alpha = alphas.get(index).copied().unwrap_or(255);
+                    [
+                        u8::from(color.one()),
+                        u8::from(color.two()),
+                        u8::from(color.three()),
+                        alpha,
+                    ]
+                })
+                .collect::<Vec<u8>>();
+            Raster::with_u8_buffer(indices.width(), indices.height(), pixels)
+        }
+        png_pong::PngRaster::Graya8(raster) => Raster::with_raster(&raster),
+        png_pong::PngRaster::Graya16(raster) => Raster::with_raster(&raster),
+        png_pong::PngRaster::Rgba8(raster) => raster,
+        png_pong::PngRaster::Rgba16(raster) => Raster::with_raster(&raster),
+    };
+    Some(Image {
+        width: raster.width(),
+        height: raster.height(),
+        pixels: raster.as_u8_slice().into(),
+    })
+}
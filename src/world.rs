@@ -1,26 +1,164 @@
-use crate::{chunk::Block, region::Region, Options};
+use crate::{
+    block_source::BlockSource,
+    chunk::{Block, BlockEntity, BlockStates, Chunk, Section},
+    entity::{Entity, EntityChunk, MapColors},
+    region::Region,
+    Options,
+};
 use glam::{IVec2, IVec3, Vec3Swizzles};
 use internment::Intern;
-use std::{collections::HashMap, path::Path};
+use serde::Deserialize;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    io::Cursor,
+    path::Path,
+};
 
+/// Where in the game a world is, which decides the sky and the light.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    /// The surface of the world.
+    Overworld,
+    /// The Nether.
+    Nether,
+    /// The End.
+    End,
+}
+
+impl Dimension {
+    /// Guesses the dimension from the name of the folder it is stored in.
+    fn of(world_path: &Path) -> Self {
+        match world_path.file_name().and_then(|name| name.to_str()) {
+            Some("DIM-1" | "the_nether") => Self::Nether,
+            Some("DIM1" | "the_end") => Self::End,
+            _ => Self::Overworld,
+        }
+    }
+
+    /// The Y coordinate of the bottom of the dimension.
+    pub(crate) const fn min_y(self) -> i32 {
+        match self {
+            Self::Overworld => -64,
+            Self::Nether | Self::End => 0,
+        }
+    }
+}
+
+/// A world saved by Minecraft: Java Edition, as a folder of region files.
 pub struct World {
-    regions: HashMap<IVec2, Region>,
+    regions: HashMap<IVec2, Region<Chunk>>,
+    entity_regions: HashMap<IVec2, Region<EntityChunk>>,
+    maps: HashMap<i32, MapColors>,
+    dimension: Dimension,
+    /// The time of day in ticks, where 0 is sunrise.
+    day_time: Option<i64>,
+    seed: Option<i64>,
 }
 
 impl World {
+    /// Loads the regions of a dimension's folder that overlap the area to
+    /// render.
     pub fn new(world_path: &Path, options: &Options) -> Self {
+        Self::load(world_path, options, None)
+    }
+
+    /// Loads another copy of the world, such as a later backup, with the
+    /// same options. Regions whose files are the same in both copies are
+    /// taken from this one instead of having their chunks deserialized
+    /// again.
+    pub fn reload(self, world_path: &Path, options: &Options) -> Self {
+        Self::load(world_path, options, Some(self))
+    }
+
+    fn load(
+        world_path: &Path,
+        options: &Options,
+        previous: Option<Self>,
+    ) -> Self {
+        let (previous_regions, previous_entity_regions) = previous
+            .map(|previous| (previous.regions, previous.entity_regions))
+            .unwrap_or_default();
+        let entity_regions: HashMap<_, Region<EntityChunk>> = Region::load_all(
+            &world_path.join("entities"),
+            options,
+            previous_entity_regions,
+        );
+        let dimension = Dimension::of(world_path);
+        // Other dimensions are stored inside the overworld's folder
+        let level_path = if dimension == Dimension::Overworld {
+            world_path
+        } else {
+            world_path
+                .ancestors()
+                .find(|path| path.join("level.dat").exists())
+                .unwrap_or(world_path)
+        };
+        let level = read_level(level_path);
+        // Maps are stored in the overworld's folder whichever dimension
+        // they are shown in, and many item frames can show the same one
+        let map_ids: HashSet<i32> = entity_regions
+            .values()
+            .flat_map(|region| region.chunks.iter().flatten().flatten())
+            .flat_map(|chunk| &chunk.entities)
+            .filter_map(Entity::map_id)
+            .collect();
+        let maps = map_ids
+            .into_iter()
+            .filter_map(|id| Some((id, MapColors::load(level_path, id)?)))
+            .collect();
         Self {
-            regions: Region::load_all(world_path, options),
+            regions: Region::load_all(
+                &world_path.join("region"),
+                options,
+                previous_regions,
+            ),
+            entity_regions,
+            maps,
+            dimension,
+            day_time: level.as_ref().map(|level| level.day_time),
+            seed: level.and_then(|level| {
+                level
+                    .world_gen_settings
+                    .map(|settings| settings.seed)
+                    .or(level.random_seed)
+            }),
         }
     }
 
-    pub fn block_at(&self, pos: IVec3) -> Option<Intern<Block>> {
+    /// One of the light levels at a position, from 0 to 15, which is 0
+    /// where nothing is loaded.
+    fn light(&self, pos: IVec3, light_map: impl Fn(&Section) -> &[i8]) -> u8 {
+        (|| {
+            let chunk = self.chunk_at(pos)?;
+            let section = chunk.section(pos.y >> 4)?;
+            let offset_within_lightmap = (pos.y.rem_euclid(16) * 256
+                + pos.z.rem_euclid(16) * 16
+                + pos.x.rem_euclid(16))
+                as usize;
+            let byte =
+                *light_map(section).get(offset_within_lightmap >> 1)? as u8;
+            Some(if offset_within_lightmap.is_multiple_of(2) {
+                byte & 0xf
+            } else {
+                byte >> 4
+            })
+        })()
+        .unwrap_or(0)
+    }
+
+    fn chunk_at(&self, pos: IVec3) -> Option<&Chunk> {
         let region = &self.regions.get(&(pos.xz() >> 9))?;
-        let chunk = region.chunks[(pos.z >> 4).rem_euclid(32) as usize]
+        region.chunks[(pos.z >> 4).rem_euclid(32) as usize]
             [(pos.x >> 4).rem_euclid(32) as usize]
-            .as_ref()?;
-        let section =
-            chunk.sections.get(usize::try_from(pos.y + 64).ok()? / 16)?;
+            .as_ref()
+    }
+}
+
+impl BlockSource for World {
+    fn block_at(&self, pos: IVec3) -> Option<Intern<Block>> {
+        let chunk = self.chunk_at(pos)?;
+        let section = chunk.section(pos.y >> 4)?;
         let offset_within_section = pos.y.rem_euclid(16) * 256
             + pos.z.rem_euclid(16) * 16
             + pos.x.rem_euclid(16);
@@ -33,38 +171,442 @@ impl World {
         )
     }
 
-    pub fn light_at(&self, pos: IVec3) -> u8 {
-        (|| {
-            let region = &self.regions.get(&(pos.xz() >> 9))?;
-            let chunk = region.chunks[(pos.z >> 4).rem_euclid(32) as usize]
-                [(pos.x >> 4).rem_euclid(32) as usize]
-                .as_ref()?;
-            let section =
-                chunk.sections.get(usize::try_from(pos.y + 64).ok()? / 16)?;
-            let offset_within_lightmap = (pos.y.rem_euclid(16) * 256
-                + pos.z.rem_euclid(16) * 16
-                + pos.x.rem_euclid(16))
-                as usize;
-            let block_light_byte = *section
-                .block_light
-                .get(offset_within_lightmap >> 1)
-                .unwrap_or(&0) as u8;
-            let block_light = if offset_within_lightmap % 2 == 0 {
-                block_light_byte & 0xf
-            } else {
-                block_light_byte >> 4
-            };
-            let sky_light_byte = *section
-                .sky_light
-                .get(offset_within_lightmap >> 1)
-                .unwrap_or(&0) as u8;
-            let sky_light = if offset_within_lightmap % 2 == 0 {
-                sky_light_byte & 0xf
-            } else {
-                sky_light_byte >> 4
-            };
-            Some(block_light + sky_light)
-        })()
-        .unwrap_or(0)
+    fn section_at(&self, pos: IVec3) -> Option<Cow<'_, BlockStates>> {
+        let chunk = self.chunk_at(pos * 16)?;
+        let section = chunk.section(pos.y)?;
+        Some(Cow::Borrowed(&section.block_states))
+    }
+
+    fn block_entity_at(&self, pos: IVec3) -> Option<&BlockEntity> {
+        self.chunk_at(pos)?.block_entities.get(&pos)
+    }
+
+    fn block_light_at(&self, pos: IVec3) -> u8 {
+        self.light(pos, |section| &section.block_light)
+    }
+
+    fn sky_light_at(&self, pos: IVec3) -> u8 {
+        self.light(pos, |section| &section.sky_light)
+    }
+
+    fn biome_at(&self, pos: IVec3) -> Option<Intern<Box<str>>> {
+        let chunk = self.chunk_at(pos)?;
+        let section = chunk.section(pos.y >> 4)?;
+        let cell = pos.y.rem_euclid(16) / 4 * 16
+            + pos.z.rem_euclid(16) / 4 * 4
+            + pos.x.rem_euclid(16) / 4;
+        let biomes = &section.biomes;
+        biomes
+            .palette
+            .get(usize::from(biomes.data[cell as usize]))
+            .copied()
+    }
+
+    fn surface_at(&self, x: i32, z: i32) -> Option<(i32, Intern<Block>)> {
+        let chunk = self.chunk_at(IVec3::new(x, 0, z))?;
+        // The heightmap is only trusted if it agrees with the blocks
+        if let Some(y) =
+            chunk.heightmaps.surface_y(x, z, self.dimension.min_y())
+        {
+            let block = self.block_at(IVec3::new(x, y, z))?;
+            let above = self.block_at(IVec3::new(x, y + 1, z));
+            if !block.is_air() && above.is_none_or(|above| above.is_air()) {
+                return Some((y, block));
+            }
+        }
+        chunk
+            .sections
+            .iter()
+            .rev()
+            .filter(|section| {
+                !section
+                    .block_states
+                    .palette
+                    .iter()
+                    .all(|block| block.is_air())
+            })
+            .find_map(|section| {
+                let section_y = i32::from(section.y) * 16;
+                (0..16).rev().find_map(|y| {
+                    let offset = y * 256
+                        + z.rem_euclid(16) as usize * 16
+                        + x.rem_euclid(16) as usize;
+                    let block = section.block_states.palette
+                        [usize::from(section.block_states.data[offset])];
+                    (!block.is_air()).then_some((section_y + y as i32, block))
+                })
+            })
+    }
+
+    /// All loaded entities, including those slightly outside of the area
+    /// but in the same chunk.
+    fn entities(&self) -> Box<dyn Iterator<Item = &Entity> + '_> {
+        Box::new(
+            self.entity_regions
+                .values()
+                .flat_map(|region| region.chunks.iter().flatten().flatten())
+                .flat_map(|chunk| &chunk.entities),
+        )
+    }
+
+    fn maps(&self) -> &HashMap<i32, MapColors> {
+        &self.maps
+    }
+
+    fn dimension(&self) -> Dimension {
+        self.dimension
+    }
+
+    fn day_time(&self) -> Option<i64> {
+        self.day_time
+    }
+
+    fn seed(&self) -> Option<i64> {
+        self.seed
+    }
+
+    fn chunk(&self, pos: IVec2) -> Option<&Chunk> {
+        self.chunk_at(IVec3::new(pos.x, 0, pos.y) * 16)
+    }
+
+    fn data_version(&self) -> Option<i32> {
+        self.regions
+            .values()
+            .flat_map(|region| region.chunks.iter().flatten().flatten())
+            .map(|chunk| chunk.data_version)
+            .max()
+    }
+}
+
+/// The parts of `level.dat` that matter for rendering.
+#[derive(Deserialize)]
+struct LevelData {
+    #[serde(rename = "DayTime")]
+    day_time: i64,
+    /// Where the seed is since Minecraft 1.16.
+    #[serde(rename = "WorldGenSettings")]
+    world_gen_settings: Option<WorldGenSettings>,
+    /// Where the seed was before Minecraft 1.16.
+    #[serde(rename = "RandomSeed")]
+    random_seed: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct WorldGenSettings {
+    seed: i64,
+}
+
+fn read_level(world_path: &Path) -> Option<LevelData> {
+    #[derive(Deserialize)]
+    struct Level {
+        #[serde(rename = "Data")]
+        data: LevelData,
+    }
+
+    let file = std::fs::read(world_path.join("level.dat")).ok()?;
+    let level: Level = nbt::from_gzip_reader(Cursor::new(file)).ok()?;
+    Some(level.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chunk::AIR, test_util};
+
+    /// A world with one chunk on each side of the boundaries between
+    /// regions and between negative and positive coordinates. Each chunk's
+    /// section from Y 0 to 15 is full of a different block, except for
+    /// air at its first corner.
+    fn world() -> World {
+        let directory = test_util::temp_dir("world");
+        let region = |name: &str, chunks: &[(IVec2, &str)]| {
+            let chunks: Vec<_> = chunks
+                .iter()
+                .map(|&(pos, block)| {
+                    let mut indices = vec![1; 4096];
+                    indices[0] = 0;
+                    let block_states = test_util::block_states(
+                        &["minecraft:air", block],
+                        &indices,
+                    );
+                    (pos, test_util::chunk(&[(0, block_states)]))
+                })
+                .collect();
+            test_util::write_region(
+                &directory.join("region").join(name),
+                &chunks,
+            );
+        };
+        region("r.-1.-1.mca", &[(IVec2::new(31, 31), "minecraft:stone")]);
+        region(
+            "r.0.0.mca",
+            &[
+                (IVec2::new(0, 0), "minecraft:dirt"),
+                (IVec2::new(31, 0), "minecraft:sand"),
+            ],
+        );
+        region("r.1.0.mca", &[(IVec2::new(0, 0), "minecraft:gravel")]);
+        World::new(
+            &directory,
+            &test_util::options(IVec2::new(-16, -16)..IVec2::new(528, 16)),
+        )
+    }
+
+    fn name(block: Option<Intern<Block>>) -> Option<String> {
+        block.map(|block| block.name.to_string())
+    }
+
+    #[test]
+    fn finds_blocks_at_negative_coordinates() {
+        let world = world();
+        let block_at = |x, y, z| name(world.block_at(IVec3::new(x, y, z)));
+        assert_eq!(block_at(-16, 0, -16), Some("minecraft:air".into()));
+        assert_eq!(block_at(-15, 0, -16), Some("minecraft:stone".into()));
+        assert_eq!(block_at(-1, 15, -1), Some("minecraft:stone".into()));
+        assert_eq!(block_at(-1, 16, -1), Some("minecraft:air".into()));
+        assert_eq!(block_at(-1, -1, -1), Some("minecraft:air".into()));
+        // Chunks that weren't generated
+        assert_eq!(block_at(-17, 0, -1), None);
+        assert_eq!(block_at(-1, 0, 0), None);
+    }
+
+    #[test]
+    fn finds_blocks_across_region_boundaries() {
+        let world = world();
+        let block_at = |x, y, z| name(world.block_at(IVec3::new(x, y, z)));
+        assert_eq!(block_at(0, 0, 0), Some("minecraft:air".into()));
+        assert_eq!(block_at(15, 0, 0), Some("minecraft:dirt".into()));
+        assert_eq!(block_at(16, 0, 0), None);
+        assert_eq!(block_at(511, 5, 15), Some("minecraft:sand".into()));
+        assert_eq!(block_at(512, 0, 0), Some("minecraft:air".into()));
+        assert_eq!(block_at(513, 0, 0), Some("minecraft:gravel".into()));
+        assert_eq!(block_at(527, 15, 15), Some("minecraft:gravel".into()));
+        assert_eq!(block_at(528, 0, 0), None);
+    }
+
+    #[test]
+    fn finds_blocks_at_the_limits_of_the_height() {
+        let world = world();
+        assert_eq!(world.block_at(IVec3::new(1, -64, 1)), Some(*AIR));
+        assert_eq!(world.block_at(IVec3::new(1, 319, 1)), Some(*AIR));
+        assert_eq!(world.block_at(IVec3::new(1, -65, 1)), None);
+        assert_eq!(world.block_at(IVec3::new(1, 320, 1)), None);
+    }
+
+    #[test]
+    fn finds_sections_and_surfaces() {
+        let world = world();
+        let section = world.section_at(IVec3::new(-1, 0, -1)).unwrap();
+        assert_eq!(
+            name(Some(section.palette[1])),
+            Some("minecraft:stone".into())
+        );
+        assert_eq!(section.data[0], 0);
+        assert!(section.data[1..].iter().all(|&index| index == 1));
+        assert!(world.section_at(IVec3::new(-2, 0, -1)).is_none());
+
+        let (y, block) = world.surface_at(-5, -5).unwrap();
+        assert_eq!((y, &**block.name), (15, "minecraft:stone"));
+        assert!(world.surface_at(100, 0).is_none());
+    }
+
+    #[test]
+    fn reads_heightmaps_biomes_light_and_the_seed() {
+        use nbt::Value;
+
+        let directory = test_util::temp_dir("world-data");
+        let mut chunk = test_util::chunk(&[(
+            0,
+            test_util::block_states(&["minecraft:stone"], &[]),
+        )]);
+        let Some(Value::List(sections)) = chunk.get("sections") else {
+            unreachable!();
+        };
+        let mut sections = sections.clone();
+        let Value::Compound(section) = &mut sections[4] else {
+            unreachable!();
+        };
+        // The east quarter of the section is desert
+        section.insert(
+            "biomes".to_owned(),
+            Value::Compound(nbt::Map::from([
+                (
+                    "palette".to_owned(),
+                    Value::List(vec![
+                        Value::String("minecraft:plains".to_owned()),
+                        Value::String("minecraft:desert".to_owned()),
+                    ]),
+                ),
+                (
+                    "data".to_owned(),
+                    Value::LongArray(vec![0x8888_8888_8888_8888_u64 as i64]),
+                ),
+            ])),
+        );
+        section.insert(
+            "BlockLight".to_owned(),
+            Value::ByteArray(vec![0x52; 2048]),
+        );
+        section
+            .insert("SkyLight".to_owned(), Value::ByteArray(vec![0x0f; 2048]));
+        chunk.insert("sections", Value::List(sections)).unwrap();
+        // Every column is 15 high but the first, which is wrong
+        let heights: Vec<u64> =
+            (0..256).map(|i| if i == 0 { 68 } else { 80 }).collect();
+        let longs = heights
+            .chunks(7)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |long, (i, height)| long | height << (i * 9))
+                    as i64
+            })
+            .collect();
+        chunk
+            .insert(
+                "Heightmaps",
+                Value::Compound(nbt::Map::from([(
+                    "WORLD_SURFACE".to_owned(),
+                    Value::LongArray(longs),
+                )])),
+            )
+            .unwrap();
+        test_util::write_region(
+            &directory.join("region/r.0.0.mca"),
+            &[(IVec2::ZERO, chunk)],
+        );
+        let mut level = nbt::Blob::new();
+        level
+            .insert(
+                "Data",
+                Value::Compound(nbt::Map::from([
+                    ("DayTime".to_owned(), Value::Long(1000)),
+                    (
+                        "WorldGenSettings".to_owned(),
+                        Value::Compound(nbt::Map::from([(
+                            "seed".to_owned(),
+                            Value::Long(-42),
+                        )])),
+                    ),
+                ])),
+            )
+            .unwrap();
+        let mut file =
+            std::fs::File::create(directory.join("level.dat")).unwrap();
+        level.to_gzip_writer(&mut file).unwrap();
+
+        let world = World::new(
+            &directory,
+            &test_util::options(IVec2::ZERO..IVec2::new(16, 16)),
+        );
+        assert_eq!(world.seed(), Some(-42));
+        assert_eq!(world.day_time(), Some(1000));
+        assert_eq!(world.surface_at(0, 0).unwrap().0, 15);
+        assert_eq!(world.surface_at(7, 9).unwrap().0, 15);
+        let biome = |x| world.biome_at(IVec3::new(x, 5, 3)).unwrap();
+        assert_eq!(&**biome(11), "minecraft:plains");
+        assert_eq!(&**biome(12), "minecraft:desert");
+        assert_eq!(world.block_light_at(IVec3::new(2, 3, 4)), 2);
+        assert_eq!(world.block_light_at(IVec3::new(3, 3, 4)), 5);
+        assert_eq!(world.sky_light_at(IVec3::new(3, 3, 4)), 0);
+        assert_eq!(world.light_at(IVec3::new(2, 3, 4)), 17);
+    }
+
+    #[test]
+    fn loads_maps_from_the_overworld_in_other_dimensions() {
+        use nbt::Value;
+
+        let directory = test_util::temp_dir("world-maps");
+        std::fs::File::create(directory.join("level.dat")).unwrap();
+        let frame = |id| {
+            Value::Compound(nbt::Map::from([
+                ("id".to_owned(), Value::String("item_frame".to_owned())),
+                ("Pos".to_owned(), Value::List(vec![Value::Double(1.5); 3])),
+                (
+                    "Item".to_owned(),
+                    Value::Compound(nbt::Map::from([
+                        (
+                            "id".to_owned(),
+                            Value::String("minecraft:filled_map".to_owned()),
+                        ),
+                        (
+                            "tag".to_owned(),
+                            Value::Compound(nbt::Map::from([(
+                                "map".to_owned(),
+                                Value::Int(id),
+                            )])),
+                        ),
+                    ])),
+                ),
+            ]))
+        };
+        let mut chunk = nbt::Blob::new();
+        chunk
+            .insert("Entities", Value::List(vec![frame(3), frame(3), frame(4)]))
+            .unwrap();
+        let nether = directory.join("DIM-1");
+        test_util::write_region(
+            &nether.join("entities/r.0.0.mca"),
+            &[(IVec2::ZERO, chunk)],
+        );
+        let mut map = nbt::Blob::new();
+        map.insert(
+            "data",
+            Value::Compound(nbt::Map::from([(
+                "colors".to_owned(),
+                Value::ByteArray(vec![4; MapColors::SIZE * MapColors::SIZE]),
+            )])),
+        )
+        .unwrap();
+        std::fs::create_dir(directory.join("data")).unwrap();
+        let mut file =
+            std::fs::File::create(directory.join("data/map_3.dat")).unwrap();
+        map.to_gzip_writer(&mut file).unwrap();
+
+        let world = World::new(
+            &nether,
+            &test_util::options(IVec2::ZERO..IVec2::new(16, 16)),
+        );
+        let ids: Vec<i32> = world.maps().keys().copied().collect();
+        assert_eq!(ids, [3]);
+    }
+
+    #[test]
+    fn reads_chunks_of_the_nether() {
+        use nbt::Value;
+
+        let directory = test_util::temp_dir("world-nether").join("DIM-1");
+        let mut chunk = test_util::chunk(&[(
+            7,
+            test_util::block_states(&["minecraft:netherrack"], &[]),
+        )]);
+        // Sections from Y 0 to 255, and one below with only light
+        let Some(Value::List(sections)) = chunk.get("sections") else {
+            unreachable!();
+        };
+        let mut sections: Vec<Value> = sections[3..20].to_vec();
+        sections[0] = Value::Compound(nbt::Map::from([
+            ("Y".to_owned(), Value::Byte(-1)),
+            ("SkyLight".to_owned(), Value::ByteArray(vec![0; 2048])),
+        ]));
+        chunk.insert("sections", Value::List(sections)).unwrap();
+        test_util::write_region(
+            &directory.join("region/r.0.0.mca"),
+            &[(IVec2::ZERO, chunk)],
+        );
+
+        let world = World::new(
+            &directory,
+            &test_util::options(IVec2::ZERO..IVec2::new(16, 16)),
+        );
+        assert!(world.dimension() == Dimension::Nether);
+        let block_at = |y| name(world.block_at(IVec3::new(3, y, 3)));
+        assert_eq!(block_at(112), Some("minecraft:netherrack".into()));
+        assert_eq!(block_at(0), Some("minecraft:air".into()));
+        assert_eq!(block_at(-1), Some("minecraft:air".into()));
+        assert_eq!(block_at(-17), None);
+        assert_eq!(block_at(256), None);
+        assert_eq!(world.surface_at(3, 3).unwrap().0, 127);
     }
 }